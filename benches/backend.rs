@@ -0,0 +1,49 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use hare::compiler::register::DEFAULT_REGISTERS;
+use hare::interpreter::Interpreter;
+use hare::ir::lower;
+use hare::parser::parse;
+
+/// 基准测试使用的程序，包含算术、比较、变量与 if 语句
+const PROGRAM: &str = "
+let a = 1;
+let b = 2;
+let c = a * 3 + b * (4 - a) / 2;
+if c > 5 {
+    a = a + b * c - (c % 3);
+} elif c == 5 {
+    a = 0;
+} else {
+    b = b * b + c;
+}
+(a + b) * (b + c) - (c + a) * 2;
+";
+
+/// 分别以栈式字节码与寄存器指令在参考解释器上执行相同的程序
+///
+/// # 参数
+///
+/// * `c` - 用于配置和运行基准测试的 Criterion 对象
+fn criterion_benchmark(c: &mut Criterion) {
+    let ast = parse(PROGRAM).unwrap();
+    let codes = ast.compile().unwrap();
+    let program = lower(&ast)
+        .unwrap()
+        .emit_registers(DEFAULT_REGISTERS)
+        .unwrap();
+
+    c.bench_function("run_stack", |b| {
+        b.iter(|| Interpreter::new().run(black_box(&codes)).unwrap());
+    });
+
+    c.bench_function("run_register", |b| {
+        b.iter(|| {
+            Interpreter::new()
+                .run_registers(black_box(&program))
+                .unwrap()
+        });
+    });
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+#![no_main]
+
+use hare::fuzz::{parse_and_compile, Generator};
+use hare::parser::parse;
+use libfuzzer_sys::fuzz_target;
+
+// 以任意字节为种子生成语法正确的程序，生成的程序必须能被解析，编译只允许返回类型化的错误
+fuzz_target!(|data: &[u8]| {
+    let program = Generator::from_bytes(data).program();
+
+    assert!(parse(&program).is_ok(), "{:?}", program);
+    let _ = parse_and_compile(&program);
+});
@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// 任意字节作为源代码解析并编译，只允许返回类型化的错误，不允许 panic
+fuzz_target!(|data: &[u8]| {
+    let _ = hare::fuzz::fuzz_bytes(data);
+});
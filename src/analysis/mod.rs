@@ -0,0 +1,271 @@
+pub mod symbols;
+pub mod types;
+
+use serde_json::{json, Value};
+
+use crate::compiler::{CompilerError, CompilerWarning, Located};
+use crate::parser::ast::AstNode;
+use crate::parser::errors::{self, render_snippet, Fix, Label};
+use crate::parser::serialize::SpanTree;
+use crate::parser::span::Span;
+use crate::parser::{parse_items, parse_pair, Item};
+
+pub use symbols::{Reference, ReferenceKind, Symbol, SymbolTable};
+pub use types::{Type, TypeEnv};
+
+/// 诊断信息的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// 分析源代码得到的诊断信息，包括解析错误以及编译期的错误与警告
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub severity: Severity,
+    /// 错误或警告的代码，见 `ParserError::code` 与 `CompilerError::code`
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// 修改建议
+    pub hint: Option<String>,
+    /// 补充说明
+    pub notes: Vec<String>,
+    /// 与诊断信息相关的其他位置
+    pub labels: Vec<Label>,
+    /// 实现修改建议的修改
+    pub fix: Option<Fix>,
+}
+
+impl From<&errors::Diagnostic> for Diagnostic {
+    fn from(diagnostic: &errors::Diagnostic) -> Self {
+        Self {
+            span: diagnostic.span,
+            severity: Severity::Error,
+            code: Some(diagnostic.error.code()),
+            message: diagnostic.message(),
+            hint: diagnostic.hint.clone(),
+            notes: vec![],
+            labels: diagnostic.labels.clone(),
+            fix: diagnostic.fix.clone(),
+        }
+    }
+}
+
+impl Diagnostic {
+    /// 没有代码、建议与说明的诊断信息
+    pub fn new(span: Span, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            severity,
+            code: None,
+            message: message.into(),
+            hint: None,
+            notes: vec![],
+            labels: vec![],
+            fix: None,
+        }
+    }
+
+    /// 编译期错误，位置为出错的节点
+    pub fn compiler_error(error: &Located<CompilerError>) -> Self {
+        let mut diagnostic = Self::new(error.span, Severity::Error, error.value.to_string());
+        diagnostic.code = Some(error.value.code());
+        diagnostic.labels = error.labels.clone();
+        if let CompilerError::DivisionByZero(_) = error.value {
+            diagnostic.notes.push(
+                "expressions whose operands are all constants are evaluated at compile time"
+                    .to_string(),
+            );
+        }
+        diagnostic
+    }
+
+    /// 编译期警告，位置为产生警告的节点
+    pub fn compiler_warning(warning: &Located<CompilerWarning>) -> Self {
+        let mut diagnostic = Self::new(warning.span, Severity::Warning, warning.value.to_string());
+        diagnostic.code = Some(warning.value.code());
+        diagnostic.labels = warning.labels.clone();
+        match warning.value {
+            CompilerWarning::UnreachableBranch(_) => diagnostic
+                .notes
+                .push("the condition is a constant, so the branch is removed".to_string()),
+        }
+        diagnostic
+    }
+
+    /// 将诊断信息渲染为带有源代码片段的文本，格式与解析错误的 `render` 相同，
+    /// 次要位置与补充说明逐行附在最后
+    pub fn render(&self, path: Option<&str>, input: &str) -> String {
+        let mut output = render_snippet(
+            self.severity.as_str(),
+            &self.message,
+            self.hint.as_deref(),
+            self.span,
+            path,
+            input,
+        );
+
+        let (line, _) = self.span.location(input);
+        let padding = " ".repeat(line.to_string().len());
+        for label in &self.labels {
+            let (line, column) = label.span.location(input);
+            let location = match path {
+                Some(path) => format!("{}:{}:{}", path, line, column),
+                None => format!("{}:{}", line, column),
+            };
+            output.push_str(&format!(
+                "{} = note: {} at {}\n",
+                padding, label.message, location
+            ));
+        }
+        for note in &self.notes {
+            output.push_str(&format!("{} = note: {}\n", padding, note));
+        }
+        output
+    }
+
+    /// 将诊断信息转换为 JSON，供 `--error-format=json` 使用
+    ///
+    /// ```json
+    /// {
+    ///   "severity": "error",
+    ///   "code": "E0001",
+    ///   "message": "unclosed block",
+    ///   "spans": [
+    ///     { "file": "main.ba", "byte_start": 0, "byte_end": 1, "line_start": 1, "column_start": 1,
+    ///       "line_end": 1, "column_end": 2, "is_primary": true, "label": null }
+    ///   ],
+    ///   "notes": [],
+    ///   "suggestions": [
+    ///     { "message": "add a '}' to close the block opened here",
+    ///       "replacements": [{ "span": { "file": "main.ba", "byte_start": 9, ... }, "text": "}" }] }
+    ///   ]
+    /// }
+    /// ```
+    ///
+    /// 第一个区间是主要位置，其余的是 `labels` 中的次要位置。行号与列号从 1 开始，列号以字符计，
+    /// `byte_end` 与 `column_end` 不包含在区间内。
+    pub fn to_json(&self, path: &str, input: &str) -> Value {
+        let primary = json_span(path, input, self.span, Some((true, None)));
+        let secondary = self
+            .labels
+            .iter()
+            .map(|label| json_span(path, input, label.span, Some((false, Some(&label.message)))));
+
+        let replacements: Vec<Value> = self
+            .fix
+            .iter()
+            .map(|fix| {
+                json!({
+                    "span": json_span(path, input, fix.span, None),
+                    "text": fix.replacement,
+                })
+            })
+            .collect();
+        let suggestions: Vec<Value> = match (&self.hint, replacements.is_empty()) {
+            (Some(hint), _) => vec![json!({ "message": hint, "replacements": replacements })],
+            (None, false) => vec![json!({ "message": null, "replacements": replacements })],
+            (None, true) => vec![],
+        };
+
+        json!({
+            "severity": self.severity.as_str(),
+            "code": self.code,
+            "message": self.message,
+            "spans": std::iter::once(primary).chain(secondary).collect::<Vec<Value>>(),
+            "notes": self.notes,
+            "suggestions": suggestions,
+        })
+    }
+}
+
+/// 区间的位置，`label` 不为 `None` 时附带 `is_primary` 与 `label`
+fn json_span(path: &str, input: &str, span: Span, label: Option<(bool, Option<&str>)>) -> Value {
+    let (line_start, column_start) = span.location(input);
+    let (line_end, column_end) = Span::new(span.end, span.end).location(input);
+    let mut value = json!({
+        "file": path,
+        "byte_start": span.start,
+        "byte_end": span.end,
+        "line_start": line_start,
+        "column_start": column_start,
+        "line_end": line_end,
+        "column_end": column_end,
+    });
+    if let Some((primary, label)) = label {
+        value["is_primary"] = json!(primary);
+        value["label"] = json!(label);
+    }
+    value
+}
+
+/// 对一段源代码的分析结果，供编辑器等工具使用
+#[derive(Debug, Clone, PartialEq)]
+pub struct Analysis {
+    /// 按位置排列的诊断信息
+    pub diagnostics: Vec<Diagnostic>,
+    pub symbols: SymbolTable,
+}
+
+/// 分析源代码
+///
+/// 在语法错误处恢复并继续分析，被成功解析的部分仍会被加入符号表。
+/// 只有在没有语法错误时才会检查编译期错误，每个顶层的块或语句单独进行常量折叠、死分支消除与编译，
+/// 产生的错误与警告标记在对应的块或语句上。
+pub fn analyze(input: &str) -> Analysis {
+    let (items, parse_errors) = parse_items(input);
+    let mut diagnostics: Vec<Diagnostic> = parse_errors.iter().map(Diagnostic::from).collect();
+
+    if diagnostics.is_empty() {
+        let mut types = TypeEnv::new();
+        for item in &items {
+            diagnostics.extend(compile_diagnostics(input, item, &mut types));
+        }
+    }
+
+    Analysis {
+        diagnostics,
+        symbols: SymbolTable::build(&items),
+    }
+}
+
+/// 编译一个顶层的块或语句，返回其中的编译期错误与警告，位置为出错的节点
+///
+/// `types` 为之前的项中变量的类型，编译之后包含这一项中的赋值。
+fn compile_diagnostics(input: &str, item: &Item, types: &mut TypeEnv) -> Vec<Diagnostic> {
+    use crate::compiler::section::SectionNamer;
+    use crate::optimizer::optimize_located;
+
+    let Ok(node) = parse_pair(&item.pair) else {
+        return vec![];
+    };
+    let program = AstNode::Program(vec![node]);
+    let spans = SpanTree {
+        span: item.span(),
+        children: vec![SpanTree::from_item(item)],
+    };
+    let mut diagnostics: Vec<Diagnostic> = vec![];
+
+    match optimize_located(&program, &spans, input, 1) {
+        Ok((_, _, warnings)) => {
+            diagnostics.extend(warnings.iter().map(Diagnostic::compiler_warning))
+        }
+        Err(err) => diagnostics.push(Diagnostic::compiler_error(&err)),
+    }
+    if let Err(err) = program.compile_located(&mut SectionNamer::new(), &spans, types) {
+        diagnostics.push(Diagnostic::compiler_error(&err));
+    }
+
+    diagnostics
+}
@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+
+use pest::iterators::Pair;
+
+use super::types::{infer, Type};
+use crate::parser::span::Span;
+use crate::parser::{parse_expr, Item, Rule};
+
+/// 由 `let` 语句声明的变量
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// 变量的类型：有可识别的类型注解时为注解的类型，否则为初始值推断出的类型
+    pub ty: Type,
+    /// 声明中变量名的区间
+    pub span: Span,
+    /// 整个声明语句的区间
+    pub statement: Span,
+}
+
+/// 变量名在源代码中的一次出现的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceKind {
+    /// `let` 语句中的声明
+    Declaration,
+    /// 在表达式中读取
+    Read,
+    /// 被赋值语句赋值
+    Write,
+}
+
+/// 变量名在源代码中的一次出现
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub span: Span,
+    pub kind: ReferenceKind,
+    /// 对应的声明在 `SymbolTable::symbols` 中的下标，变量未被声明时为 `None`
+    pub symbol: Option<usize>,
+    /// 变量在此处的类型
+    pub ty: Type,
+}
+
+/// 符号表，记录所有变量的声明与每一次出现
+///
+/// 变量存放在虚拟机的全局堆中，块不会引入新的作用域，因此变量名总是对应按源代码顺序在它之前最近的一次声明。
+/// 变量的类型随赋值而变化，每一次出现都记录了变量在该处的类型。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolTable {
+    pub symbols: Vec<Symbol>,
+    /// 按位置排列的所有出现
+    pub references: Vec<Reference>,
+}
+
+impl SymbolTable {
+    /// 由解析出的各项建立符号表
+    pub fn build(items: &[Item]) -> SymbolTable {
+        let mut builder = Builder {
+            table: SymbolTable::default(),
+            scope: HashMap::new(),
+        };
+        for item in items {
+            builder.statement(item, &item.pair);
+        }
+
+        let mut table = builder.table;
+        table
+            .references
+            .sort_by_key(|reference| reference.span.start);
+        table
+    }
+
+    /// 返回位于 `offset` 处的变量名，光标紧跟在变量名之后时也视为位于变量名上
+    pub fn reference_at(&self, offset: usize) -> Option<&Reference> {
+        self.references
+            .iter()
+            .find(|reference| reference.span.start <= offset && offset <= reference.span.end)
+    }
+
+    /// 返回位于 `offset` 处的变量名对应的声明
+    pub fn definition_at(&self, offset: usize) -> Option<&Symbol> {
+        self.reference_at(offset)?
+            .symbol
+            .map(|index| &self.symbols[index])
+    }
+
+    /// 返回变量的所有出现，包括声明
+    pub fn references_of(&self, symbol: usize) -> Vec<&Reference> {
+        self.references
+            .iter()
+            .filter(|reference| reference.symbol == Some(symbol))
+            .collect()
+    }
+}
+
+struct Builder {
+    table: SymbolTable,
+    /// 变量名到当前有效的声明与变量当前类型的映射
+    scope: HashMap<String, (usize, Type)>,
+}
+
+impl Builder {
+    fn statement(&mut self, item: &Item, pair: &Pair<Rule>) {
+        match pair.as_rule() {
+            Rule::assign_statement => {
+                let mut pairs = pair.clone().into_inner();
+                let name = pairs.next().unwrap();
+                let (annotation, value) = match (pairs.next(), pairs.next()) {
+                    (Some(annotation), Some(value)) => (Some(annotation), value),
+                    (Some(value), None) => (None, value),
+                    _ => return,
+                };
+
+                let inferred = self.expr(item, &value);
+                let ty = match annotation.map(|annotation| Type::from_name(annotation.as_str())) {
+                    Some(Type::Unknown) | None => inferred,
+                    Some(ty) => ty,
+                };
+
+                let index = self.table.symbols.len();
+                self.table.symbols.push(Symbol {
+                    name: name.as_str().to_string(),
+                    ty,
+                    span: item.span_of(&name),
+                    statement: item.span_of(pair),
+                });
+                self.scope.insert(name.as_str().to_string(), (index, ty));
+                self.reference(item, &name, ReferenceKind::Declaration, Some(index), ty);
+            }
+            Rule::set_value_statement => {
+                let mut pairs = pair.clone().into_inner();
+                let (Some(name), Some(value)) = (pairs.next(), pairs.next()) else {
+                    return;
+                };
+
+                let ty = self.expr(item, &value);
+                let symbol = self.scope.get_mut(name.as_str()).map(|(index, current)| {
+                    *current = ty;
+                    *index
+                });
+                self.reference(item, &name, ReferenceKind::Write, symbol, ty);
+            }
+            Rule::expr => {
+                self.expr(item, pair);
+            }
+            _ => {
+                for child in pair.clone().into_inner() {
+                    self.statement(item, &child);
+                }
+            }
+        }
+    }
+
+    /// 记录表达式中读取的变量，返回表达式的类型
+    fn expr(&mut self, item: &Item, pair: &Pair<Rule>) -> Type {
+        self.reads(item, pair);
+
+        let scope = &self.scope;
+        parse_expr(pair).map_or(Type::Unknown, |node| {
+            infer(&node, &|name| {
+                scope.get(name).map_or(Type::Unknown, |(_, ty)| *ty)
+            })
+        })
+    }
+
+    /// 按顺序记录表达式中读取的变量，作为值的块中的语句与其他语句一样处理
+    fn reads(&mut self, item: &Item, pair: &Pair<Rule>) {
+        for child in pair.clone().into_inner() {
+            match child.as_rule() {
+                Rule::ident => {
+                    let (symbol, ty) = match self.scope.get(child.as_str()) {
+                        Some((index, ty)) => (Some(*index), *ty),
+                        None => (None, Type::Unknown),
+                    };
+                    self.reference(item, &child, ReferenceKind::Read, symbol, ty);
+                }
+                Rule::block => self.statement(item, &child),
+                _ => self.reads(item, &child),
+            }
+        }
+    }
+
+    fn reference(
+        &mut self,
+        item: &Item,
+        name: &Pair<Rule>,
+        kind: ReferenceKind,
+        symbol: Option<usize>,
+        ty: Type,
+    ) {
+        self.table.references.push(Reference {
+            span: item.span_of(name),
+            kind,
+            symbol,
+            ty,
+        });
+    }
+}
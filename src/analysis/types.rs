@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::compiler::value::Value;
+use crate::parser::ast::AstNode;
+use crate::parser::BinaryOp;
+
+/// 静态推断出的值的类型
+///
+/// 推断规则与 `Value::binary` 的运算语义一致，无法确定类型时为 `Unknown`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Float,
+    Bool,
+    Str,
+    Unknown,
+}
+
+impl Type {
+    /// 返回值的类型
+    pub fn of_value(value: &Value) -> Type {
+        match value {
+            Value::Int(_) => Type::Int,
+            Value::Float(_) => Type::Float,
+            Value::Bool(_) => Type::Bool,
+            Value::Str(_) => Type::Str,
+        }
+    }
+
+    /// 由类型注解中的类型名得到类型，无法识别的类型名返回 `Unknown`
+    pub fn from_name(name: &str) -> Type {
+        match name {
+            "int" => Type::Int,
+            "float" => Type::Float,
+            "bool" => Type::Bool,
+            "str" => Type::Str,
+            _ => Type::Unknown,
+        }
+    }
+
+    /// 返回类型名
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::Bool => "bool",
+            Type::Str => "str",
+            Type::Unknown => "unknown",
+        }
+    }
+
+    /// 返回对两个该类型的值进行二元运算的结果的类型，运算无法进行时返回 `Unknown`
+    pub fn binary(&self, op: &BinaryOp, rhs: &Type) -> Type {
+        let comparison = matches!(
+            op,
+            BinaryOp::Eq
+                | BinaryOp::Neq
+                | BinaryOp::Gt
+                | BinaryOp::Gte
+                | BinaryOp::Lt
+                | BinaryOp::Lte
+        );
+
+        match (self, rhs) {
+            _ if matches!(op, BinaryOp::Eq | BinaryOp::Neq) => Type::Bool,
+            (Type::Int, Type::Int) if comparison => Type::Bool,
+            (Type::Int, Type::Int) => Type::Int,
+            (Type::Int | Type::Float, Type::Int | Type::Float) if comparison => Type::Bool,
+            (Type::Int | Type::Float, Type::Int | Type::Float) => Type::Float,
+            (Type::Str, Type::Str) if comparison => Type::Bool,
+            (Type::Str, Type::Str) if *op == BinaryOp::Add => Type::Str,
+            (Type::Unknown, _) | (_, Type::Unknown) if comparison => Type::Bool,
+            _ => Type::Unknown,
+        }
+    }
+
+    /// 返回 if 表达式的两个分支的值的共同类型，类型不同时返回 `None`
+    ///
+    /// 任意一个分支的类型无法确定时，结果也无法确定。
+    pub fn unify(&self, other: &Type) -> Option<Type> {
+        match (self, other) {
+            (Type::Unknown, _) | (_, Type::Unknown) => Some(Type::Unknown),
+            (lhs, rhs) if lhs == rhs => Some(*lhs),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// 按语句的顺序跟踪变量的类型，作为 `infer` 的 `lookup`
+///
+/// 条件执行的代码（if 的分支、作为值的块与含有 `rtb` 的块）中的赋值不一定会发生，
+/// 因此赋值的类型与变量之前的类型合并，两者不同时变量的类型变为 `Unknown`。
+#[derive(Debug, Default)]
+pub struct TypeEnv {
+    types: HashMap<String, Type>,
+    /// 当前所在的条件执行的代码的层数
+    conditional: usize,
+}
+
+impl TypeEnv {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 返回变量当前的类型，没有被赋值过的变量为 `Unknown`
+    pub fn lookup(&self, name: &str) -> Type {
+        self.types.get(name).copied().unwrap_or(Type::Unknown)
+    }
+
+    /// 记录对变量的赋值
+    pub fn assign(&mut self, name: &str, ty: Type) {
+        let ty = match self.types.get(name) {
+            Some(current) if self.conditional > 0 => current.unify(&ty).unwrap_or(Type::Unknown),
+            _ => ty,
+        };
+        self.types.insert(name.to_string(), ty);
+    }
+
+    /// 进入条件执行的代码
+    pub fn enter(&mut self) {
+        self.conditional += 1;
+    }
+
+    /// 离开条件执行的代码
+    pub fn leave(&mut self) {
+        self.conditional -= 1;
+    }
+}
+
+/// 推断表达式的类型
+///
+/// # 参数
+///
+/// - `node`: 要推断类型的表达式。
+/// - `lookup`: 返回变量当前的类型。
+pub fn infer(node: &AstNode, lookup: &dyn Fn(&str) -> Type) -> Type {
+    match node {
+        AstNode::Constant(literal) => {
+            Value::from_literal(literal).map_or(Type::Unknown, |value| Type::of_value(&value))
+        }
+        AstNode::Identifier(name) => lookup(name),
+        AstNode::Expr(left, Some(op), Some(right)) => {
+            infer(left, lookup).binary(op, &infer(right, lookup))
+        }
+        AstNode::Expr(left, _, _) => infer(left, lookup),
+        AstNode::Block(_) => node
+            .block_value()
+            .map_or(Type::Unknown, |value| infer(value, lookup)),
+        // 没有 else 分支或分支的类型不同时无法确定类型
+        AstNode::If(_, _, _, _) => node
+            .if_branches()
+            .and_then(|if_branches| {
+                let otherwise = if_branches.otherwise?;
+                let mut types = if_branches
+                    .branches
+                    .iter()
+                    .map(|(_, block)| *block)
+                    .chain([otherwise])
+                    .map(|block| infer(block, lookup));
+                let first = types.next()?;
+                types.try_fold(first, |ty, branch| ty.unify(&branch))
+            })
+            .unwrap_or(Type::Unknown),
+        _ => Type::Unknown,
+    }
+}
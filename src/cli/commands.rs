@@ -0,0 +1,399 @@
+use std::path::Path;
+
+use clap::ArgMatches;
+
+use crate::analysis::{analyze, Diagnostic, Severity, TypeEnv};
+use crate::compiler::asm::assemble;
+use crate::compiler::c::compile_c;
+use crate::compiler::debug::{compile_items, DebugInfo};
+use crate::compiler::disasm::{disassemble, disassemble_object, disassemble_with_source};
+use crate::compiler::dot::{ast_to_dot, cfg_to_dot};
+use crate::compiler::format_bytecodes;
+use crate::compiler::object::{is_object, Object, EXTENSION};
+use crate::compiler::register::{RegisterProgram, DEFAULT_REGISTERS};
+use crate::compiler::section::SectionNamer;
+use crate::compiler::verify::verify;
+use crate::compiler::wat::compile_wat;
+use crate::compiler::{ByteCode, CompilerError, CompilerWarning, Located};
+use crate::format::format;
+use crate::interpreter::Interpreter;
+use crate::ir::lower_with;
+use crate::optimizer::{optimize_bytecodes, optimize_located};
+use crate::parser::serialize::{self, SpanTree};
+use crate::parser::{parse_items, parse_with_recovery};
+
+use super::{
+    emit, read_input, report, report_compiler_error, Input, Io, EXIT_FAILURE, EXIT_IO,
+    EXIT_SUCCESS, EXIT_USAGE,
+};
+
+/// `hare build`：编译源代码，写入与输入同名、扩展名为 `EXTENSION` 的目标文件
+pub(super) fn build(matches: &ArgMatches, input: &Input, io: &mut Io) -> Result<(), i32> {
+    let output = match matches.get_one::<String>("output") {
+        Some(_) if matches.get_many::<String>("FILES").unwrap().len() > 1 => {
+            return Err(usage(
+                io,
+                "`--output` cannot be used with multiple input files",
+            ))
+        }
+        Some(output) => output.clone(),
+        None if input.is_stdin() => {
+            return Err(usage(io, "`--output` is required when reading from stdin"))
+        }
+        None => Path::new(&input.path)
+            .with_extension(EXTENSION)
+            .to_string_lossy()
+            .into_owned(),
+    };
+
+    let code = input.text(io)?;
+    let (codes, debug) = compile_source(matches, &input.name, code, io)?;
+    let bytes = Object::new(codes).with_debug(debug).to_bytes();
+
+    let written = match output.as_str() {
+        super::STDIN => io.stdout.write_all(&bytes),
+        path => std::fs::write(path, bytes),
+    };
+    written.map_err(|err| report(io, &output, err, EXIT_IO))
+}
+
+/// `hare run`：用参考解释器执行源代码或目标文件，输出程序的结果
+///
+/// 使用 `--target register` 时源代码被编译为寄存器指令执行，目标文件只包含栈式字节码，不能以寄存器目标执行。
+pub(super) fn run(matches: &ArgMatches, input: &Input, io: &mut Io) -> Result<(), i32> {
+    let mut interpreter = Interpreter::new();
+
+    if matches.get_one::<String>("target").map(String::as_str) == Some("register") {
+        if is_object(&input.bytes) {
+            return Err(report(
+                io,
+                &input.name,
+                "bytecode object files cannot be run with `--target register`",
+                EXIT_USAGE,
+            ));
+        }
+
+        let code = input.text(io)?;
+        let program = compile_registers(matches, &input.name, code, io)?;
+        interpreter
+            .run_registers(&program)
+            .map_err(|err| report(io, &input.name, err, EXIT_FAILURE))?;
+        let _ = write!(io.stdout, "{}", interpreter.output());
+        return Ok(());
+    }
+
+    let codes = match is_object(&input.bytes) {
+        true => {
+            Object::load(&input.bytes)
+                .map_err(|err| report(io, &input.name, err, EXIT_FAILURE))?
+                .codes
+        }
+        false => {
+            let code = input.text(io)?;
+            compile_source(matches, &input.name, code, io)?.0
+        }
+    };
+
+    interpreter
+        .run(&codes)
+        .map_err(|err| report(io, &input.name, err, EXIT_FAILURE))?;
+    let _ = write!(io.stdout, "{}", interpreter.output());
+    Ok(())
+}
+
+/// `hare check`：解析并分析源代码，输出所有的错误与警告
+pub(super) fn check(_: &ArgMatches, input: &Input, io: &mut Io) -> Result<(), i32> {
+    let code = input.text(io)?;
+    let analysis = analyze(code);
+
+    for diagnostic in &analysis.diagnostics {
+        emit(io, &input.name, code, diagnostic);
+    }
+
+    match analysis
+        .diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == Severity::Error)
+    {
+        true => Err(EXIT_FAILURE),
+        false => Ok(()),
+    }
+}
+
+/// `hare fmt`：格式化源代码，文件被原地改写，标准输入的结果输出到标准输出
+pub(super) fn fmt(matches: &ArgMatches, input: &Input, io: &mut Io) -> Result<(), i32> {
+    let code = input.text(io)?;
+    syntax_errors(&input.name, code, io)?;
+    let formatted = format(code).map_err(|err| report(io, &input.name, err, EXIT_FAILURE))?;
+
+    if matches.get_flag("check") {
+        return match formatted == code {
+            true => Ok(()),
+            false => Err(report(io, &input.name, "not formatted", EXIT_FAILURE)),
+        };
+    }
+
+    match input.is_stdin() {
+        true => {
+            let _ = write!(io.stdout, "{}", formatted);
+            Ok(())
+        }
+        false if formatted == code => Ok(()),
+        false => std::fs::write(&input.path, formatted)
+            .map_err(|err| report(io, &input.name, err, EXIT_IO)),
+    }
+}
+
+/// `hare ast`：输出抽象语法树
+///
+/// `json` 与 `sexpr` 格式中带有每个节点在源代码中的区间，格式见 `parser::serialize`。
+pub(super) fn ast(matches: &ArgMatches, input: &Input, io: &mut Io) -> Result<(), i32> {
+    let code = input.text(io)?;
+    let ast = syntax_errors(&input.name, code, io)?;
+    let spans = || SpanTree::from_items(code, &parse_items(code).0);
+
+    let _ = match matches.get_one::<String>("format").map(String::as_str) {
+        Some("json") => writeln!(io.stdout, "{:#}", serialize::to_json(&ast, Some(&spans()))),
+        Some("sexpr") => write!(io.stdout, "{}", serialize::to_sexpr(&ast, Some(&spans()))),
+        _ => writeln!(io.stdout, "{:#?}", ast),
+    };
+    Ok(())
+}
+
+/// `hare disasm`：反汇编目标文件
+pub(super) fn disasm(matches: &ArgMatches, input: &Input, io: &mut Io) -> Result<(), i32> {
+    let object = Object::from_bytes(&input.bytes)
+        .map_err(|err| report(io, &input.name, err, EXIT_FAILURE))?;
+    let _ = write!(
+        io.stdout,
+        "{}",
+        disassemble_object(&object, matches.get_flag("source-lines"))
+    );
+    Ok(())
+}
+
+/// `hare asm`：汇编 `.basm` 文件
+pub(super) fn asm(matches: &ArgMatches, io: &mut Io) -> i32 {
+    let result = (|| {
+        let input = read_input(matches.get_one::<String>("FILE").unwrap(), io)?;
+        let codes =
+            assemble(input.text(io)?).map_err(|err| report(io, &input.name, err, EXIT_FAILURE))?;
+        verify_codes(&codes, &input.name, io)?;
+
+        match matches.get_one::<String>("output") {
+            Some(path) => std::fs::write(path, Object::new(codes).to_bytes())
+                .map_err(|err| report(io, path, err, EXIT_IO)),
+            None => {
+                let _ = write!(io.stdout, "{}", format_bytecodes(&codes));
+                Ok(())
+            }
+        }
+    })();
+
+    result.err().unwrap_or(EXIT_SUCCESS)
+}
+
+/// `hare lsp`：在标准输入与标准输出上运行语言服务器
+pub(super) fn lsp(io: &mut Io) -> i32 {
+    match crate::lsp::run(&mut *io.stdin, &mut *io.stdout) {
+        Ok(()) => EXIT_SUCCESS,
+        Err(err) => {
+            let _ = writeln!(io.stderr, "error: {}", err);
+            EXIT_FAILURE
+        }
+    }
+}
+
+/// `hare explain`：输出错误代码的详细解释
+pub(super) fn explain(matches: &ArgMatches, io: &mut Io) -> i32 {
+    let code = matches.get_one::<String>("CODE").unwrap();
+    match crate::explain::explain(code) {
+        Some(text) => {
+            let _ = write!(io.stdout, "{}", text);
+            EXIT_SUCCESS
+        }
+        None => usage(io, &format!("unknown error code `{}`", code)),
+    }
+}
+
+/// 不带子命令时：编译 `--input` 或 `--code` 给出的源代码，按 `--emit` 与 `--target` 输出
+pub(super) fn compile(matches: &ArgMatches, io: &mut Io) -> i32 {
+    let result = (|| {
+        let (name, code) = match matches.get_one::<String>("code") {
+            Some(code) => ("<code>".to_string(), code.clone()),
+            None => {
+                let input = read_input(matches.get_one::<String>("input").unwrap(), io)?;
+                let code = input.text(io)?.to_string();
+                (input.name, code)
+            }
+        };
+        compile_to_stdout(matches, &name, &code, io)
+    })();
+
+    result.err().unwrap_or(EXIT_SUCCESS)
+}
+
+fn compile_to_stdout(matches: &ArgMatches, name: &str, code: &str, io: &mut Io) -> Result<(), i32> {
+    // 目标文件与带源代码行的反汇编需要调试信息，逐项编译
+    let output = matches.get_one::<String>("output");
+    if output.is_some() || matches.get_flag("source-lines") {
+        let (codes, debug) = compile_source(matches, name, code, io)?;
+        return match output {
+            Some(path) => std::fs::write(path, Object::new(codes).with_debug(debug).to_bytes())
+                .map_err(|err| report(io, path, err, EXIT_IO)),
+            None => {
+                let _ = write!(io.stdout, "{}", disassemble_with_source(&codes, &debug));
+                Ok(())
+            }
+        };
+    }
+
+    let ast = syntax_errors(name, code, io)?;
+    let emit = matches.get_one::<String>("emit").map(String::as_str);
+    let graph = matches.get_one::<String>("graph").map(String::as_str);
+    if (emit, graph) == (Some("dot"), Some("ast")) {
+        let _ = write!(io.stdout, "{}", ast_to_dot(&ast));
+        return Ok(());
+    }
+
+    let level = *matches.get_one::<u8>("opt-level").unwrap_or(&0);
+    let spans = SpanTree::from_items(code, &parse_items(code).0);
+    let (ast, spans, warnings) = optimize_located(&ast, &spans, code, level)
+        .map_err(|err| located_error(io, name, code, &err))?;
+    located_warnings(io, name, code, &warnings);
+
+    if matches.get_flag("dump-ir") {
+        let ir = lower_with(&ast, &spans, &mut TypeEnv::new())
+            .map_err(|err| located_error(io, name, code, &err))?;
+        let _ = write!(io.stdout, "{}", ir);
+        return Ok(());
+    }
+
+    let target = matches.get_one::<String>("target").map(String::as_str);
+    let text = match (emit, target) {
+        (Some("wat"), _) => compile_wat(&ast),
+        (Some("c"), _) => compile_c(&ast),
+        (emit, Some("register")) if emit != Some("dot") => {
+            lower_with(&ast, &spans, &mut TypeEnv::new())
+                .map_err(|err| located_error(io, name, code, &err))?
+                .emit_registers(register_count(matches))
+                .map(|program| program.to_string())
+        }
+        _ => {
+            let codes = ast
+                .compile_located(&mut namer(matches), &spans, &mut TypeEnv::new())
+                .map_err(|err| located_error(io, name, code, &err))?;
+            let (codes, stats) = optimize_bytecodes(&codes, level);
+            log::info!(target: "optimizer", "{}", stats);
+            verify_codes(&codes, name, io)?;
+            Ok(match emit {
+                Some("disasm") => disassemble(&codes),
+                Some("dot") => cfg_to_dot(&codes),
+                _ => format_bytecodes(&codes),
+            })
+        }
+    };
+
+    let text = text.map_err(|err| report_compiler_error(io, name, &err))?;
+    let _ = write!(io.stdout, "{}", text);
+    Ok(())
+}
+
+/// 逐项编译源代码并校验生成的字节码，出错时输出诊断信息
+fn compile_source(
+    matches: &ArgMatches,
+    name: &str,
+    code: &str,
+    io: &mut Io,
+) -> Result<(Vec<ByteCode>, DebugInfo), i32> {
+    let (items, diagnostics) = parse_items(code);
+    if !diagnostics.is_empty() {
+        for diagnostic in &diagnostics {
+            emit(io, name, code, &diagnostic.into());
+        }
+        return Err(EXIT_FAILURE);
+    }
+
+    let level = *matches.get_one::<u8>("opt-level").unwrap_or(&0);
+    let (codes, warnings, debug) = compile_items(code, &items, level, &mut namer(matches))
+        .map_err(|err| located_error(io, name, code, &err))?;
+    located_warnings(io, name, code, &warnings);
+
+    verify_codes(&codes, name, io)?;
+    Ok((codes, debug))
+}
+
+/// 将源代码编译为寄存器指令，出错时输出诊断信息
+fn compile_registers(
+    matches: &ArgMatches,
+    name: &str,
+    code: &str,
+    io: &mut Io,
+) -> Result<RegisterProgram, i32> {
+    let ast = syntax_errors(name, code, io)?;
+    let level = *matches.get_one::<u8>("opt-level").unwrap_or(&0);
+    let spans = SpanTree::from_items(code, &parse_items(code).0);
+    let (ast, spans, warnings) = optimize_located(&ast, &spans, code, level)
+        .map_err(|err| located_error(io, name, code, &err))?;
+    located_warnings(io, name, code, &warnings);
+
+    lower_with(&ast, &spans, &mut TypeEnv::new())
+        .map_err(|err| located_error(io, name, code, &err))?
+        .emit_registers(register_count(matches))
+        .map_err(|err| report_compiler_error(io, name, &err))
+}
+
+/// 输出一条带有位置的编译期错误，返回 `EXIT_FAILURE`
+fn located_error(io: &mut Io, name: &str, code: &str, error: &Located<CompilerError>) -> i32 {
+    emit(io, name, code, &Diagnostic::compiler_error(error));
+    EXIT_FAILURE
+}
+
+/// 输出带有位置的编译期警告
+fn located_warnings(io: &mut Io, name: &str, code: &str, warnings: &[Located<CompilerWarning>]) {
+    for warning in warnings {
+        emit(io, name, code, &Diagnostic::compiler_warning(warning));
+    }
+}
+
+/// 寄存器目标可用的寄存器数量，由 `--registers` 决定
+fn register_count(matches: &ArgMatches) -> usize {
+    matches
+        .get_one::<u16>("registers")
+        .map_or(DEFAULT_REGISTERS, |count| *count as usize)
+}
+
+/// 解析源代码，有语法错误时输出所有的错误
+fn syntax_errors(name: &str, code: &str, io: &mut Io) -> Result<crate::parser::ast::AstNode, i32> {
+    let (ast, diagnostics) = parse_with_recovery(code);
+    if diagnostics.is_empty() {
+        return Ok(ast);
+    }
+
+    for diagnostic in &diagnostics {
+        emit(io, name, code, &diagnostic.into());
+    }
+    Err(EXIT_FAILURE)
+}
+
+/// 校验字节码，不合法时输出所有的错误
+fn verify_codes(codes: &[ByteCode], name: &str, io: &mut Io) -> Result<(), i32> {
+    verify(codes).map_err(|errors| {
+        for err in errors {
+            let _ = writeln!(io.stderr, "error: {}: {}", name, err);
+        }
+        EXIT_FAILURE
+    })
+}
+
+fn namer(matches: &ArgMatches) -> SectionNamer {
+    match matches.get_one::<u64>("section-seed") {
+        Some(seed) => SectionNamer::seeded(*seed),
+        None => SectionNamer::new(),
+    }
+}
+
+fn usage(io: &mut Io, message: &str) -> i32 {
+    let _ = writeln!(io.stderr, "error: {}", message);
+    EXIT_USAGE
+}
@@ -0,0 +1,385 @@
+//! 命令行界面
+//!
+//! `hare` 由若干子命令组成，不带子命令时编译源代码并输出字节码。所有子命令都不会因为输入有误而 panic，
+//! 错误被输出到标准错误，并以下面的退出码之一结束：
+//!
+//! - `EXIT_SUCCESS`：成功；
+//! - `EXIT_FAILURE`：输入有错误，如语法错误、编译错误、运行时错误或不合法的目标文件；
+//! - `EXIT_USAGE`：命令行参数有误；
+//! - `EXIT_IO`：读写文件失败。
+//!
+//! 接受多个输入文件的子命令逐个处理所有文件，即使其中的一些失败，退出码取所有文件中最大的一个。
+//! 文件名 `-` 表示从标准输入读取。
+//!
+//! 使用 `--error-format=json` 时，每条错误与警告以一行 JSON 输出到标准错误，格式见 `Diagnostic::to_json`。
+//! 没有源代码位置的错误（如读取文件失败或运行时错误）只有一个区间，其中只有 `file` 与 `is_primary` 不为 `null`。
+
+mod commands;
+
+use std::ffi::OsString;
+use std::io::{BufRead, Write};
+
+use clap::{ArgMatches, Command};
+use serde_json::json;
+
+use crate::analysis::{Diagnostic, Severity};
+use crate::compiler::CompilerError;
+
+/// 成功
+pub const EXIT_SUCCESS: i32 = 0;
+/// 输入有错误
+pub const EXIT_FAILURE: i32 = 1;
+/// 命令行参数有误，与 clap 报告参数错误时的退出码一致
+pub const EXIT_USAGE: i32 = 2;
+/// 读写文件失败
+pub const EXIT_IO: i32 = 3;
+
+/// 表示标准输入的文件名
+pub const STDIN: &str = "-";
+
+/// 命令行程序使用的输入与输出
+pub struct Io<'a> {
+    pub stdin: &'a mut dyn BufRead,
+    pub stdout: &'a mut dyn Write,
+    pub stderr: &'a mut dyn Write,
+    /// 由 `--error-format` 决定
+    error_format: ErrorFormat,
+}
+
+impl<'a> Io<'a> {
+    pub fn new(
+        stdin: &'a mut dyn BufRead,
+        stdout: &'a mut dyn Write,
+        stderr: &'a mut dyn Write,
+    ) -> Self {
+        Self {
+            stdin,
+            stdout,
+            stderr,
+            error_format: ErrorFormat::Human,
+        }
+    }
+}
+
+/// 错误与警告的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    /// 带有源代码片段的文本
+    Human,
+    /// 每条一行的 JSON
+    Json,
+}
+
+/// 构造命令行的定义
+pub fn command() -> Command {
+    let files = || clap::arg!(<FILES>... "Input files, `-` reads from stdin");
+
+    Command::new("hare")
+        .version("0.0.1")
+        .about("Hare is the official compiler of Blue Arch Programming Language")
+        .author("XYCode <xycode-xyc@outlook.com>")
+        .arg(clap::arg!(-i --input <INPUT> "Input file").required_unless_present("code"))
+        .arg(clap::arg!(-c --code <CODE> "Code to be compiled").required_unless_present("input"))
+        .arg(opt_level())
+        .arg(section_seed())
+        .arg(clap::arg!(--"dump-ir" "Print the intermediate representation instead of bytecode"))
+        .arg(
+            clap::arg!(--emit <KIND> "Output format (bytecode: one instruction per line, disasm: disassembly, wat: WebAssembly text format, c: C source to be linked with -lm, dot: Graphviz graph selected by --graph)")
+                .value_parser(["bytecode", "disasm", "wat", "c", "dot"])
+                .default_value("bytecode"),
+        )
+        .arg(
+            clap::arg!(--graph <GRAPH> "Graph written by --emit dot (ast: syntax tree before optimization, cfg: control-flow graph of the compiled sections and jumps)")
+                .value_parser(["ast", "cfg"])
+                .default_value("cfg"),
+        )
+        .arg(target().conflicts_with_all(["output", "source-lines"]))
+        .arg(registers())
+        .arg(clap::arg!(--"source-lines" "Interleave source lines in the disassembly (implies --emit disasm)"))
+        .arg(clap::arg!(-o --output <FILE> "Write a bytecode object file instead of printing"))
+        .subcommand(
+            Command::new("build")
+                .about("Compile source files to bytecode object files")
+                .arg(files())
+                .arg(clap::arg!(-o --output <FILE> "Output file, `-` writes to stdout (only with a single input)"))
+                .arg(opt_level())
+                .arg(section_seed()),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Run source files or bytecode object files with the reference interpreter")
+                .arg(files())
+                .arg(opt_level())
+                .arg(section_seed())
+                .arg(target())
+                .arg(registers()),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("Parse and analyze source files without generating code")
+                .arg(files()),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("Format source files in place, or stdin to stdout")
+                .arg(files())
+                .arg(clap::arg!(--check "Only check whether the files are formatted")),
+        )
+        .subcommand(
+            Command::new("ast")
+                .about("Print the abstract syntax tree of source files")
+                .arg(files())
+                .arg(
+                    clap::arg!(--format <FORMAT> "Output format (debug: Rust debug output, json: nodes with spans and typed literals, sexpr: S-expressions)")
+                        .value_parser(["debug", "json", "sexpr"])
+                        .default_value("debug"),
+                ),
+        )
+        .subcommand(
+            Command::new("disasm")
+                .about("Disassemble bytecode object files")
+                .arg(files())
+                .arg(clap::arg!(--"source-lines" "Interleave source lines recorded in the file")),
+        )
+        .subcommand(
+            Command::new("asm")
+                .about("Assemble a .basm file")
+                .arg(clap::arg!(<FILE> ".basm file, `-` reads from stdin"))
+                .arg(clap::arg!(-o --output <FILE> "Write a bytecode object file instead of printing")),
+        )
+        .subcommand(Command::new("lsp").about("Run the language server over stdio"))
+        .subcommand(
+            Command::new("explain")
+                .about("Print a detailed explanation of an error code")
+                .arg(clap::arg!(<CODE> "Error code, e.g. E0002")),
+        )
+        .arg(
+            clap::arg!(--"error-format" <FORMAT> "Format of errors and warnings (human: text with source snippets, json: one JSON object per line)")
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .global(true),
+        )
+        .subcommand_negates_reqs(true)
+        .args_conflicts_with_subcommands(true)
+}
+
+fn opt_level() -> clap::Arg {
+    clap::arg!(-O --"opt-level" <LEVEL> "Optimization level (0: none, 1: constant folding and dead branch elimination, 2: peephole)")
+        .value_parser(clap::value_parser!(u8).range(0..=2))
+        .default_value("0")
+}
+
+fn target() -> clap::Arg {
+    clap::arg!(--target <TARGET> "Code generation target (stack: stack bytecode, register: three-address register instructions)")
+        .value_parser(["stack", "register"])
+        .default_value("stack")
+}
+
+fn registers() -> clap::Arg {
+    clap::arg!(--registers <COUNT> "Number of registers available to the register target")
+        .value_parser(clap::value_parser!(u16).range(2..))
+}
+
+fn section_seed() -> clap::Arg {
+    clap::arg!(--"section-seed" <SEED> "Seed for generating pseudo-random section names")
+        .value_parser(clap::value_parser!(u64))
+}
+
+/// 解析命令行参数并执行，返回退出码
+pub fn run<I, T>(args: I, io: &mut Io) -> i32
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let matches = match command().try_get_matches_from(args) {
+        Ok(matches) => matches,
+        Err(err) => {
+            // `--help` 与 `--version` 也以错误的形式返回，它们输出到标准输出
+            let output: &mut dyn Write = match err.use_stderr() {
+                true => io.stderr,
+                false => io.stdout,
+            };
+            let _ = write!(output, "{}", err.render());
+            return err.exit_code();
+        }
+    };
+
+    if matches
+        .get_one::<String>("error-format")
+        .map(String::as_str)
+        == Some("json")
+    {
+        io.error_format = ErrorFormat::Json;
+    }
+
+    match matches.subcommand() {
+        Some(("build", matches)) => for_each_file(matches, io, Headers::Always, commands::build),
+        Some(("run", matches)) => for_each_file(matches, io, Headers::Always, commands::run),
+        Some(("check", matches)) => {
+            for_each_file(matches, io, Headers::WhenReported, commands::check)
+        }
+        Some(("fmt", matches)) => for_each_file(matches, io, Headers::Always, commands::fmt),
+        Some(("ast", matches)) => for_each_file(matches, io, Headers::Always, commands::ast),
+        Some(("disasm", matches)) => for_each_file(matches, io, Headers::Always, commands::disasm),
+        Some(("asm", matches)) => commands::asm(matches, io),
+        Some(("lsp", _)) => commands::lsp(io),
+        Some(("explain", matches)) => commands::explain(matches, io),
+        _ => commands::compile(&matches, io),
+    }
+}
+
+/// 一个输入文件
+struct Input {
+    /// 用于诊断信息的名字，标准输入显示为 `<stdin>`
+    name: String,
+    /// 命令行中的路径
+    path: String,
+    bytes: Vec<u8>,
+}
+
+impl Input {
+    fn is_stdin(&self) -> bool {
+        self.path == STDIN
+    }
+
+    /// 以 UTF-8 文本的形式返回输入的内容
+    fn text(&self, io: &mut Io) -> Result<&str, i32> {
+        std::str::from_utf8(&self.bytes)
+            .map_err(|_| report(io, &self.name, "input is not valid UTF-8", EXIT_FAILURE))
+    }
+}
+
+/// 读取输入文件
+fn read_input(path: &str, io: &mut Io) -> Result<Input, i32> {
+    let (name, bytes) = match path {
+        STDIN => {
+            let mut bytes = vec![];
+            ("<stdin>", io.stdin.read_to_end(&mut bytes).map(|_| bytes))
+        }
+        path => (path, std::fs::read(path)),
+    };
+
+    match bytes {
+        Ok(bytes) => Ok(Input {
+            name: name.to_string(),
+            path: path.to_string(),
+            bytes,
+        }),
+        Err(err) => Err(report(io, name, err, EXIT_IO)),
+    }
+}
+
+/// 有多个文件时何时输出 `==> 文件名 <==`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Headers {
+    /// 在每个文件的输出之前
+    Always,
+    /// 只在文件有错误或警告时，用于只向标准错误输出的命令
+    WhenReported,
+}
+
+/// 逐个处理 `FILES` 中的文件，有多个文件时按 `headers` 在文件的输出之前加上 `==> 文件名 <==`
+fn for_each_file(
+    matches: &ArgMatches,
+    io: &mut Io,
+    headers: Headers,
+    command: fn(&ArgMatches, &Input, &mut Io) -> Result<(), i32>,
+) -> i32 {
+    let files: Vec<&String> = matches.get_many::<String>("FILES").unwrap().collect();
+    let mut status = EXIT_SUCCESS;
+    let mut separator = "";
+
+    for path in files.iter() {
+        let mut header = |io: &mut Io| {
+            if files.len() > 1 {
+                let _ = writeln!(io.stdout, "{}==> {} <==", separator, path);
+                separator = "\n";
+            }
+        };
+
+        let result = match headers {
+            Headers::Always => {
+                header(io);
+                read_input(path, io).and_then(|input| command(matches, &input, io))
+            }
+            Headers::WhenReported => {
+                // 先收集文件的错误与警告，有内容时再输出文件名
+                let mut reported: Vec<u8> = vec![];
+                let result = {
+                    let mut file_io = Io {
+                        stdin: &mut *io.stdin,
+                        stdout: &mut *io.stdout,
+                        stderr: &mut reported,
+                        error_format: io.error_format,
+                    };
+                    read_input(path, &mut file_io)
+                        .and_then(|input| command(matches, &input, &mut file_io))
+                };
+                if !reported.is_empty() {
+                    header(io);
+                    let _ = io.stderr.write_all(&reported);
+                }
+                result
+            }
+        };
+        if let Err(code) = result {
+            status = status.max(code);
+        }
+    }
+
+    status
+}
+
+/// 输出一条与文件相关的、没有源代码位置的错误，返回 `code`
+fn report(io: &mut Io, name: &str, message: impl std::fmt::Display, code: i32) -> i32 {
+    emit_message(io, name, Severity::Error, None, message);
+    code
+}
+
+/// 输出一条没有源代码位置的编译期错误，返回 `EXIT_FAILURE`
+fn report_compiler_error(io: &mut Io, name: &str, error: &CompilerError) -> i32 {
+    emit_message(io, name, Severity::Error, Some(error.code()), error);
+    EXIT_FAILURE
+}
+
+fn emit_message(
+    io: &mut Io,
+    name: &str,
+    severity: Severity,
+    code: Option<&str>,
+    message: impl std::fmt::Display,
+) {
+    let _ = match io.error_format {
+        ErrorFormat::Human => writeln!(io.stderr, "{}: {}: {}", severity.as_str(), name, message),
+        ErrorFormat::Json => {
+            let span = json!({
+                "file": name,
+                "byte_start": null,
+                "byte_end": null,
+                "line_start": null,
+                "column_start": null,
+                "line_end": null,
+                "column_end": null,
+                "is_primary": true,
+                "label": null,
+            });
+            let diagnostic = json!({
+                "severity": severity.as_str(),
+                "code": code,
+                "message": message.to_string(),
+                "spans": [span],
+                "notes": [],
+                "suggestions": [],
+            });
+            writeln!(io.stderr, "{}", diagnostic)
+        }
+    };
+}
+
+/// 输出一条带有源代码位置的诊断信息
+fn emit(io: &mut Io, name: &str, input: &str, diagnostic: &Diagnostic) {
+    let _ = match io.error_format {
+        ErrorFormat::Human => write!(io.stderr, "{}", diagnostic.render(Some(name), input)),
+        ErrorFormat::Json => writeln!(io.stderr, "{}", diagnostic.to_json(name, input)),
+    };
+}
@@ -0,0 +1,214 @@
+//! `.basm` 汇编器
+//!
+//! `.basm` 是字节码的文本形式，与 `disasm` 的输出格式相同，每行至多一条指令：
+//!
+//! ```text
+//! ; 注释从 `;` 开始直到行尾
+//! 0000:  Push 1 2.5 true "s"     ; 行首的地址是可选的，汇编时被忽略
+//!        StoreName a             ; 操作码的名字与 `OpCode` 的名字相同
+//!        .section @body          ; 即 `MakeSection @body`，`@body` 为 Section 的标签
+//!            LoadName a
+//!            Return
+//!        .end                    ; 即 `EndMakeSection`
+//!        Jump @body
+//! ```
+//!
+//! 参数按操作码的要求检查种类：常量为整数、浮点数、布尔值或带引号的字符串字面量，名字为标识符
+//! （包含空白等特殊字符时加上引号），Section 为 `@` 加上名字。跳转的目标必须是文件中定义的 Section。
+
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use super::operand::{Operand, OperandKind};
+use super::{ByteCode, OpCode};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum AsmError {
+    #[error("line {0}: unknown instruction `{1}`")]
+    UnknownInstruction(usize, String),
+    #[error("line {0}: {1} expects {2}, found {3}")]
+    WrongArity(usize, String, String, usize),
+    #[error("line {0}: operand `{1}` of {2} must be {3}")]
+    WrongOperand(usize, String, String, String),
+    #[error("line {0}: unterminated string")]
+    UnterminatedString(usize),
+    #[error("line {0}: `.end` without a matching `.section`")]
+    UnmatchedEnd(usize),
+    #[error("line {0}: section @{1} is never closed")]
+    UnclosedSection(usize, String),
+    #[error("line {0}: section @{1} is already defined")]
+    DuplicateSection(usize, String),
+    #[error("line {0}: undefined section @{1}")]
+    UndefinedSection(usize, String),
+}
+
+/// 一行中的标记
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// 不带引号的单词
+    Word(String),
+    /// 带引号的字符串，包含两侧的引号
+    Quoted(String),
+    /// `@` 开头的 Section 标签，不包含 `@`
+    Label(String),
+}
+
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Word(word) | Token::Quoted(word) => word.clone(),
+            Token::Label(label) => format!("@{}", label),
+        }
+    }
+}
+
+/// 将 `.basm` 文本汇编为字节码
+///
+/// # 返回值
+///
+/// 返回汇编出的字节码；文本中有错误时返回第一个错误，错误中包含出错的行号（从 1 开始）。
+pub fn assemble(text: &str) -> Result<Vec<ByteCode>, AsmError> {
+    let mut codes: Vec<ByteCode> = vec![];
+    // 打开的 Section 的名字与所在行
+    let mut open: Vec<(String, usize)> = vec![];
+    let mut defined: HashSet<String> = HashSet::new();
+    // 跳转的目标与所在行
+    let mut jumps: Vec<(String, usize)> = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let mut tokens = tokenize(line, number)?;
+
+        // 行首的地址
+        if let Some(Token::Word(word)) = tokens.first() {
+            let address = word.strip_suffix(':');
+            if address.is_some_and(|address| address.chars().all(|c| c.is_ascii_digit())) {
+                tokens.remove(0);
+            }
+        }
+        let Some(Token::Word(mnemonic)) = tokens.first().cloned() else {
+            match tokens.first() {
+                Some(token) => {
+                    return Err(AsmError::UnknownInstruction(number, token.text()));
+                }
+                None => continue,
+            }
+        };
+
+        let op = match mnemonic.as_str() {
+            ".section" => OpCode::MakeSection,
+            ".end" => OpCode::EndMakeSection,
+            mnemonic => OpCode::from_name(mnemonic)
+                .ok_or_else(|| AsmError::UnknownInstruction(number, mnemonic.to_string()))?,
+        };
+        let args = operands(&op, &tokens[1..], number)?;
+
+        let name = args.first().and_then(Operand::name).map(str::to_string);
+        match op {
+            OpCode::MakeSection => {
+                let name = name.unwrap();
+                if !defined.insert(name.clone()) {
+                    return Err(AsmError::DuplicateSection(number, name));
+                }
+                open.push((name, number));
+            }
+            OpCode::EndMakeSection => {
+                open.pop().ok_or(AsmError::UnmatchedEnd(number))?;
+            }
+            OpCode::Jump | OpCode::JumpIf | OpCode::JumpIfElse => jumps.extend(
+                args.iter()
+                    .filter_map(Operand::name)
+                    .map(|name| (name.to_string(), number)),
+            ),
+            _ => {}
+        }
+
+        codes.push(ByteCode::new(op, args).expect("operands are checked by `operands`"));
+    }
+
+    if let Some((name, number)) = open.pop() {
+        return Err(AsmError::UnclosedSection(number, name));
+    }
+    if let Some((name, number)) = jumps.into_iter().find(|(name, _)| !defined.contains(name)) {
+        return Err(AsmError::UndefinedSection(number, name));
+    }
+
+    Ok(codes)
+}
+
+/// 按操作码的要求检查参数的个数与种类，返回指令的参数
+fn operands(op: &OpCode, tokens: &[Token], number: usize) -> Result<Vec<Operand>, AsmError> {
+    let arity = op.arity();
+    if !arity.accepts(tokens.len()) {
+        return Err(AsmError::WrongArity(
+            number,
+            op.name(),
+            arity.describe(),
+            tokens.len(),
+        ));
+    }
+
+    let Some(kind) = arity.kind() else {
+        return Ok(vec![]);
+    };
+
+    tokens
+        .iter()
+        .map(|token| {
+            let arg = match (kind, token) {
+                (OperandKind::Constant, Token::Word(text) | Token::Quoted(text))
+                | (OperandKind::Name, Token::Word(text))
+                | (OperandKind::Section, Token::Label(text)) => Operand::parse(kind, text),
+                (OperandKind::Name, Token::Quoted(string)) => {
+                    Operand::parse(kind, &string[1..string.len() - 1])
+                }
+                _ => None,
+            };
+
+            arg.ok_or_else(|| {
+                AsmError::WrongOperand(number, token.text(), op.name(), kind.describe().to_string())
+            })
+        })
+        .collect()
+}
+
+/// 将一行切分为标记，忽略注释
+fn tokenize(line: &str, number: usize) -> Result<Vec<Token>, AsmError> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut rest = line.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let (token, length) = match c {
+            ';' => break,
+            '"' => {
+                let length = quoted(rest).ok_or(AsmError::UnterminatedString(number))?;
+                (Token::Quoted(rest[..length].to_string()), length)
+            }
+            '@' if rest[1..].starts_with('"') => {
+                let length = quoted(&rest[1..]).ok_or(AsmError::UnterminatedString(number))?;
+                (Token::Label(rest[2..length].to_string()), length + 1)
+            }
+            _ => {
+                let length = rest
+                    .find(|c: char| c.is_whitespace() || c == ';' || c == '"')
+                    .unwrap_or(rest.len());
+                let word = &rest[..length];
+                match word.strip_prefix('@') {
+                    Some(label) => (Token::Label(label.to_string()), length),
+                    None => (Token::Word(word.to_string()), length),
+                }
+            }
+        };
+
+        tokens.push(token);
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// 返回 `text` 开头的带引号字符串的长度（包含两侧的引号），没有闭合的引号时返回 `None`
+fn quoted(text: &str) -> Option<usize> {
+    text[1..].find('"').map(|index| index + 2)
+}
@@ -0,0 +1,420 @@
+//! 将抽象语法树翻译为 C 源代码
+//!
+//! 生成的文件只依赖 C 标准库：`runtime.h` 中的运行时被原样嵌入文件开头，提供动态类型的值与运算。
+//! 浮点数取模使用 `math.h` 中的 `fmod`，因此链接时需要数学库，例如 `cc main.c -lm`。
+//! 每个变量对应 `main` 中的一个 `hare_value` 局部变量，初始时未定义，读取未定义的变量会在运行时报错。
+//! 表达式按从左到右的顺序逐个计算到临时变量中，因此运行时错误的顺序与虚拟机一致。
+//! 作为值的块与 if 的值保存在一个临时变量中，`rtb` 给它赋值后用 `goto` 跳转到块之后的标号。
+//!
+//! 程序的输出与参考解释器的 `Interpreter::output` 相同：先按顺序输出顶层表达式语句的值，
+//! 程序结束时再按名字的顺序输出所有已定义的变量。
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::analysis::types::{infer, TypeEnv};
+use crate::parser::ast::AstNode;
+use crate::parser::BinaryOp;
+
+use super::value::Value;
+use super::CompilerError;
+
+/// 嵌入生成的文件中的运行时
+pub const RUNTIME: &str = include_str!("runtime.h");
+
+/// 后端的名字，用于错误信息
+const BACKEND: &str = "C";
+
+/// 将抽象语法树翻译为 C 源代码
+pub fn compile_c(ast: &AstNode) -> Result<String, CompilerError> {
+    let nodes = match ast {
+        AstNode::Program(nodes) => nodes.as_slice(),
+        node => std::slice::from_ref(node),
+    };
+
+    let mut writer = CWriter::default();
+    for node in nodes {
+        writer.collect_names(node);
+    }
+
+    writer.depth = 1;
+    for node in nodes {
+        writer.statement(node, true)?;
+    }
+
+    Ok(writer.finish())
+}
+
+#[derive(Default)]
+struct CWriter {
+    /// 程序中出现的变量名，按名字排列
+    names: BTreeSet<String>,
+    /// 变量名到 C 中变量的下标
+    variables: HashMap<String, usize>,
+    body: Vec<String>,
+    temps: usize,
+    labels: usize,
+    depth: usize,
+    /// `rtb` 保存值的临时变量与跳转到的标号，不在作为值的块中时没有临时变量
+    returns: Option<(Option<String>, String)>,
+    /// 变量的类型，用于检查作为值的 if 的分支
+    types: TypeEnv,
+}
+
+impl CWriter {
+    fn finish(self) -> String {
+        let mut output = format!("{}\nint main(void) {{\n", RUNTIME);
+
+        for (name, index) in self.sorted_variables() {
+            output.push_str(&format!(
+                "    hare_value v{} = hare_undefined; /* {} */\n",
+                index, name
+            ));
+        }
+        for line in &self.body {
+            output.push_str(line);
+            output.push('\n');
+        }
+        for (name, index) in self.sorted_variables() {
+            output.push_str(&format!(
+                "    hare_print_name({}, v{});\n",
+                string_literal(name),
+                index
+            ));
+        }
+
+        output.push_str("    return 0;\n}\n");
+        output
+    }
+
+    fn sorted_variables(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.names.iter().map(|name| (name, self.variables[name]))
+    }
+
+    /// 收集节点中出现的所有变量名
+    fn collect_names(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Identifier(name) if !self.variables.contains_key(name) => {
+                self.variables.insert(name.clone(), self.variables.len());
+                self.names.insert(name.clone());
+            }
+            AstNode::Program(nodes) | AstNode::Block(nodes) => {
+                nodes.iter().for_each(|node| self.collect_names(node));
+            }
+            AstNode::Expr(left, _, right) => {
+                self.collect_names(left);
+                if let Some(right) = right {
+                    self.collect_names(right);
+                }
+            }
+            AstNode::Assign(identifier, _, value) | AstNode::SetValue(identifier, value) => {
+                self.collect_names(identifier);
+                self.collect_names(value);
+            }
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                self.collect_names(cond);
+                self.collect_names(block);
+                elif_nodes.iter().for_each(|node| self.collect_names(node));
+                if let Some(node) = else_node {
+                    self.collect_names(node);
+                }
+            }
+            AstNode::Elif(node, block) => {
+                self.collect_names(node);
+                self.collect_names(block);
+            }
+            AstNode::Else(node) | AstNode::ReturnBlock(node) => self.collect_names(node),
+            _ => {}
+        }
+    }
+
+    fn emit(&mut self, line: impl AsRef<str>) {
+        self.body
+            .push(format!("{}{}", "    ".repeat(self.depth), line.as_ref()));
+    }
+
+    fn statement(&mut self, node: &AstNode, top_level: bool) -> Result<(), CompilerError> {
+        match node {
+            AstNode::Expr(_, _, _) | AstNode::Identifier(_) | AstNode::Constant(_) => {
+                let temp = self.expr(node)?;
+                match top_level {
+                    true => self.emit(format!("hare_print({});", temp)),
+                    false => self.emit(format!("(void){};", temp)),
+                }
+            }
+            AstNode::Assign(identifier, _, value) | AstNode::SetValue(identifier, value) => {
+                let temp = self.expr(value)?;
+                match identifier.as_ref() {
+                    AstNode::Identifier(name) => {
+                        let ty = infer(value, &|name| self.types.lookup(name));
+                        self.types.assign(name, ty);
+                        let variable = self.variables[name];
+                        self.emit(format!("v{} = {};", variable, temp));
+                    }
+                    node => return Err(CompilerError::ExpectedIdentifier(node.as_code())),
+                }
+            }
+            AstNode::Block(_) => {
+                self.emit("{");
+                self.depth += 1;
+                self.block(node)?;
+                self.depth -= 1;
+                self.emit("}");
+            }
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                self.if_chain(cond, block, elif_nodes, else_node.as_deref(), None)?;
+            }
+            AstNode::ReturnBlock(value) => {
+                let (result, label) = self
+                    .returns
+                    .clone()
+                    .ok_or_else(|| CompilerError::ReturnOutsideBlock(node.as_code()))?;
+                let temp = self.expr(value)?;
+                if let Some(result) = result {
+                    self.emit(format!("{} = {};", result, temp));
+                }
+                self.emit(format!("goto {};", label));
+            }
+            AstNode::Error(code) => return Err(CompilerError::SyntaxErrors(code.clone())),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// 生成块中的语句，直到块中的 `rtb` 为止
+    fn statements(&mut self, nodes: &[AstNode]) -> Result<(), CompilerError> {
+        for node in nodes {
+            self.statement(node, false)?;
+            if matches!(node, AstNode::ReturnBlock(_)) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 生成语句块中的语句，花括号由调用者生成
+    ///
+    /// 不在作为值的块中、且含有 `rtb` 的块在最后生成一个标号，`rtb` 的值被丢弃。
+    fn block(&mut self, block: &AstNode) -> Result<(), CompilerError> {
+        let nodes = match block {
+            AstNode::Block(nodes) => nodes,
+            node => return Err(CompilerError::ExpectedBlock(node.as_code())),
+        };
+        if self.returns.is_some() || !block.contains_return() {
+            return self.statements(nodes);
+        }
+
+        let label = self.new_label();
+        self.returns = Some((None, label.clone()));
+        self.types.enter();
+        let written = self.statements(nodes);
+        self.types.leave();
+        self.returns = None;
+        written?;
+
+        self.emit(format!("{}:;", label));
+        Ok(())
+    }
+
+    /// 生成作为值的块中的语句，块中的 `rtb` 将值赋给 `result` 并跳转到 `label`，花括号由调用者生成
+    fn value_block(
+        &mut self,
+        block: &AstNode,
+        result: &str,
+        label: &str,
+    ) -> Result<(), CompilerError> {
+        let nodes = match block {
+            AstNode::Block(nodes) => nodes,
+            node => return Err(CompilerError::ExpectedBlock(node.as_code())),
+        };
+        if block.block_value().is_none() {
+            return Err(CompilerError::BlockWithoutValue(block.as_code()));
+        }
+
+        let returns = self
+            .returns
+            .replace((Some(result.to_string()), label.to_string()));
+        self.types.enter();
+        let written = self.statements(nodes);
+        self.types.leave();
+        self.returns = returns;
+        written
+    }
+
+    /// 生成 if 语句，elif 分支被生成为嵌套在 else 中的 if 语句，使得每个条件在前面的条件不成立之后才被计算
+    ///
+    /// 作为值时 `value` 是保存值的临时变量与 if 之后的标号。
+    fn if_chain(
+        &mut self,
+        cond: &AstNode,
+        block: &AstNode,
+        elif_nodes: &[AstNode],
+        else_node: Option<&AstNode>,
+        value: Option<(&str, &str)>,
+    ) -> Result<(), CompilerError> {
+        let cond = self.expr(cond)?;
+        match value.is_none() && (!elif_nodes.is_empty() || else_node.is_some()) {
+            // 与字节码一致，带有 else 的 if 语句先对条件取反再判断
+            true => self.emit(format!("if (!hare_else_condition({})) {{", cond)),
+            false => self.emit(format!("if (hare_condition({})) {{", cond)),
+        }
+        self.branch(block, value)?;
+
+        match (elif_nodes.split_first(), else_node) {
+            (Some((AstNode::Elif(cond, block), rest)), _) => {
+                self.emit("} else {");
+                self.depth += 1;
+                self.if_chain(cond, block, rest, else_node, value)?;
+                self.depth -= 1;
+            }
+            (Some((node, _)), _) => return Err(CompilerError::UnexpectedElif(node.as_code())),
+            (None, Some(AstNode::Else(block))) => {
+                self.emit("} else {");
+                self.branch(block, value)?;
+            }
+            (None, Some(node)) => return Err(CompilerError::UnexpectedElse(node.as_code())),
+            (None, None) => {}
+        }
+
+        self.emit("}");
+        Ok(())
+    }
+
+    /// 生成 if 语句的一个分支的语句，花括号由调用者生成
+    fn branch(
+        &mut self,
+        block: &AstNode,
+        value: Option<(&str, &str)>,
+    ) -> Result<(), CompilerError> {
+        self.depth += 1;
+        self.types.enter();
+        let written = match value {
+            Some((result, label)) => self.value_block(block, result, label),
+            None => self.block(block),
+        };
+        self.types.leave();
+        self.depth -= 1;
+        written
+    }
+
+    /// 将表达式计算到一个新的临时变量中，返回临时变量的名字
+    fn expr(&mut self, node: &AstNode) -> Result<String, CompilerError> {
+        let value = match node {
+            AstNode::Expr(left, Some(op), Some(right)) => {
+                let lhs = self.expr(left)?;
+                let rhs = self.expr(right)?;
+                format!("hare_binary({}, {}, {})", op_name(op), lhs, rhs)
+            }
+            AstNode::Expr(left, None, _) => return self.expr(left),
+            AstNode::Identifier(name) => format!(
+                "hare_load(v{}, {})",
+                self.variables[name],
+                string_literal(name)
+            ),
+            AstNode::Constant(literal) => match Value::from_literal(literal) {
+                Some(value) => constant(&value),
+                None => return Err(CompilerError::InvalidConstant(literal.clone())),
+            },
+            AstNode::Block(_) => {
+                let (result, label) = (self.new_temp(), self.new_label());
+                self.emit(format!("hare_value {} = hare_undefined;", result));
+                self.emit("{");
+                self.depth += 1;
+                self.value_block(node, &result, &label)?;
+                self.depth -= 1;
+                self.emit("}");
+                self.emit(format!("{}:;", label));
+                return Ok(result);
+            }
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                let (result, label) = (self.new_temp(), self.new_label());
+                self.emit(format!("hare_value {} = hare_undefined;", result));
+                let value = Some((result.as_str(), label.as_str()));
+                self.if_chain(cond, block, elif_nodes, else_node.as_deref(), value)?;
+                node.check_if_value(&|name| self.types.lookup(name))?;
+                self.emit(format!("{}:;", label));
+                return Ok(result);
+            }
+            node => {
+                return Err(CompilerError::Unsupported(
+                    BACKEND,
+                    format!("expression `{}`", node.as_code()),
+                ))
+            }
+        };
+
+        let temp = self.new_temp();
+        self.emit(format!("hare_value {} = {};", temp, value));
+        Ok(temp)
+    }
+
+    fn new_temp(&mut self) -> String {
+        self.temps += 1;
+        format!("t{}", self.temps - 1)
+    }
+
+    fn new_label(&mut self) -> String {
+        self.labels += 1;
+        format!("l{}", self.labels - 1)
+    }
+}
+
+/// 返回构造常量的 C 表达式
+fn constant(value: &Value) -> String {
+    match value {
+        Value::Int(int) => match i64::try_from(*int) {
+            // `INT64_MIN` 不能直接写成字面量
+            Ok(int) if int != i64::MIN => format!("hare_int_value(INT64_C({}))", int),
+            _ => format!(
+                "hare_int_parts(INT64_C({}), UINT64_C({}))",
+                (int >> 64) as i64,
+                *int as u64
+            ),
+        },
+        Value::Float(float) => format!("hare_float_value({})", float_literal(*float)),
+        Value::Bool(boolean) => format!("hare_bool_value({})", *boolean as i32),
+        Value::Str(string) => format!("hare_str_value({})", string_literal(string)),
+    }
+}
+
+/// 返回能精确还原浮点数的 C 字面量
+fn float_literal(float: f64) -> String {
+    if float.is_nan() {
+        "NAN".to_string()
+    } else if float.is_infinite() {
+        format!("{}INFINITY", if float < 0.0 { "-" } else { "" })
+    } else {
+        format!("{:e}", float)
+    }
+}
+
+/// 返回 C 的字符串字面量，非 ASCII 字节与特殊字符以八进制转义
+fn string_literal(string: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in string.bytes() {
+        match byte {
+            b'"' | b'\\' | b'?' => literal.push_str(&format!("\\{}", byte as char)),
+            0x20..=0x7e => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn op_name(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "HARE_ADD",
+        BinaryOp::Sub => "HARE_SUB",
+        BinaryOp::Mul => "HARE_MUL",
+        BinaryOp::Div => "HARE_DIV",
+        BinaryOp::Mod => "HARE_MOD",
+        BinaryOp::Eq => "HARE_EQ",
+        BinaryOp::Neq => "HARE_NEQ",
+        BinaryOp::Gt => "HARE_GT",
+        BinaryOp::Gte => "HARE_GTE",
+        BinaryOp::Lt => "HARE_LT",
+        BinaryOp::Lte => "HARE_LTE",
+    }
+}
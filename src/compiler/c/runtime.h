@@ -0,0 +1,371 @@
+/*
+ * Blue Arch 的 C 运行时
+ *
+ * 值是动态类型的，运算语义与 `Value::binary` 一致：整数为 128 位有符号整数，溢出与除数为零时报错；
+ * 整数与浮点数混合运算时提升为浮点数；字符串支持拼接与比较；布尔值只支持相等性比较。
+ * 出错时向标准错误输出错误信息并以状态码 1 退出。值的输出格式与 `Value::to_literal` 一致。
+ *
+ * 浮点数取模使用 `fmod`，链接时需要数学库：cc main.c -lm
+ */
+#include <math.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef __int128 hare_int;
+
+typedef enum {
+    HARE_UNDEFINED,
+    HARE_INT,
+    HARE_FLOAT,
+    HARE_BOOL,
+    HARE_STR,
+} hare_type;
+
+typedef struct {
+    hare_type type;
+    union {
+        hare_int i;
+        double f;
+        int b;
+        const char *s;
+    } as;
+} hare_value;
+
+typedef enum {
+    HARE_ADD,
+    HARE_SUB,
+    HARE_MUL,
+    HARE_DIV,
+    HARE_MOD,
+    HARE_EQ,
+    HARE_NEQ,
+    HARE_GT,
+    HARE_GTE,
+    HARE_LT,
+    HARE_LTE,
+} hare_op;
+
+static const char *const hare_op_names[] = {"+", "-", "*", "/", "%", "==", "!=", ">", ">=", "<", "<="};
+
+static const hare_value hare_undefined = {HARE_UNDEFINED, {0}};
+
+static inline hare_value hare_int_value(hare_int i) {
+    hare_value value = {HARE_INT, {0}};
+    value.as.i = i;
+    return value;
+}
+
+/* 由高 64 位与低 64 位构造整数，用于超出 64 位的常量 */
+static inline hare_value hare_int_parts(int64_t high, uint64_t low) {
+    return hare_int_value((hare_int)(((unsigned __int128)(uint64_t)high << 64) | low));
+}
+
+static inline hare_value hare_float_value(double f) {
+    hare_value value = {HARE_FLOAT, {0}};
+    value.as.f = f;
+    return value;
+}
+
+static inline hare_value hare_bool_value(int b) {
+    hare_value value = {HARE_BOOL, {0}};
+    value.as.b = b != 0;
+    return value;
+}
+
+static inline hare_value hare_str_value(const char *s) {
+    hare_value value = {HARE_STR, {0}};
+    value.as.s = s;
+    return value;
+}
+
+/* 返回值的字面量，数字写入至少有 64 字节的 out 中，字符串加上引号后另行分配 */
+static inline const char *hare_literal(hare_value value, char *out) {
+    switch (value.type) {
+    case HARE_INT: {
+        unsigned __int128 magnitude = value.as.i < 0 ? -(unsigned __int128)value.as.i : (unsigned __int128)value.as.i;
+        char digits[64];
+        int count = 0;
+        do {
+            digits[count++] = (char)('0' + (int)(magnitude % 10));
+            magnitude /= 10;
+        } while (magnitude > 0);
+        char *cursor = out;
+        if (value.as.i < 0) {
+            *cursor++ = '-';
+        }
+        while (count > 0) {
+            *cursor++ = digits[--count];
+        }
+        *cursor = '\0';
+        return out;
+    }
+    case HARE_FLOAT: {
+        double f = value.as.f;
+        if (isnan(f)) {
+            return "NaN";
+        }
+        if (isinf(f)) {
+            return f > 0 ? "inf" : "-inf";
+        }
+        if (f == 0) {
+            return signbit(f) ? "-0.0" : "0.0";
+        }
+
+        /* 最短的能还原出相同浮点数的有效数字 */
+        char scientific[40];
+        for (int precision = 1; precision <= 17; precision++) {
+            snprintf(scientific, sizeof scientific, "%.*e", precision - 1, f);
+            if (strtod(scientific, NULL) == f) {
+                break;
+            }
+        }
+
+        char digits[24];
+        int count = 0;
+        const char *cursor = scientific + (f < 0);
+        for (; *cursor != 'e'; cursor++) {
+            if (*cursor != '.') {
+                digits[count++] = *cursor;
+            }
+        }
+        digits[count] = '\0';
+        int exponent = atoi(cursor + 1);
+
+        char *output = out;
+        if (f < 0) {
+            *output++ = '-';
+        }
+        /* 与 Rust 的 `{:?}` 一致：绝对值在 [1e-4, 1e16) 之间时使用小数形式，否则使用科学计数法 */
+        if (fabs(f) >= 1e-4 && fabs(f) < 1e16) {
+            if (exponent >= 0) {
+                for (int i = 0; i <= exponent; i++) {
+                    *output++ = i < count ? digits[i] : '0';
+                }
+                *output++ = '.';
+                if (exponent + 1 < count) {
+                    strcpy(output, digits + exponent + 1);
+                    output += strlen(output);
+                } else {
+                    *output++ = '0';
+                }
+            } else {
+                *output++ = '0';
+                *output++ = '.';
+                for (int i = 0; i < -exponent - 1; i++) {
+                    *output++ = '0';
+                }
+                strcpy(output, digits);
+                output += count;
+            }
+            *output = '\0';
+        } else if (count > 1) {
+            sprintf(output, "%c.%se%d", digits[0], digits + 1, exponent);
+        } else {
+            sprintf(output, "%ce%d", digits[0], exponent);
+        }
+        return out;
+    }
+    case HARE_BOOL:
+        return value.as.b ? "true" : "false";
+    case HARE_STR: {
+        size_t length = strlen(value.as.s);
+        char *quoted = malloc(length + 3);
+        quoted[0] = '"';
+        memcpy(quoted + 1, value.as.s, length);
+        quoted[length + 1] = '"';
+        quoted[length + 2] = '\0';
+        return quoted;
+    }
+    default:
+        return "undefined";
+    }
+}
+
+static inline void hare_error(const char *kind, hare_value lhs, hare_op op, hare_value rhs) {
+    char left[64], right[64];
+    fprintf(stderr, "%s: %s %s %s\n", kind, hare_literal(lhs, left), hare_op_names[op], hare_literal(rhs, right));
+    exit(1);
+}
+
+/* 读取变量，变量未定义时报错 */
+static inline hare_value hare_load(hare_value value, const char *name) {
+    if (value.type == HARE_UNDEFINED) {
+        fprintf(stderr, "Undefined name: %s\n", name);
+        exit(1);
+    }
+    return value;
+}
+
+/* 判断条件是否成立，条件不是布尔值时报错 */
+static inline int hare_condition(hare_value value) {
+    if (value.type != HARE_BOOL) {
+        char literal[64];
+        fprintf(stderr, "Condition must be a bool, found: %s\n", hare_literal(value, literal));
+        exit(1);
+    }
+    return value.as.b;
+}
+
+/*
+ * 判断带有 else 的 if 语句是否进入 else 分支
+ *
+ * 与字节码 `Dup; Neg; JumpIf` 一致：先对条件取反，取反失败或结果不是布尔值时报错。
+ */
+static inline int hare_else_condition(hare_value value) {
+    char literal[64];
+    switch (value.type) {
+    case HARE_INT:
+        if (value.as.i == (hare_int)((unsigned __int128)1 << 127)) {
+            fprintf(stderr, "Integer overflow: -%s\n", hare_literal(value, literal));
+            exit(1);
+        }
+        return hare_condition(hare_int_value(-value.as.i));
+    case HARE_FLOAT:
+        return hare_condition(hare_float_value(-value.as.f));
+    case HARE_STR:
+        fprintf(stderr, "Type error: -%s\n", hare_literal(value, literal));
+        exit(1);
+    default:
+        return !hare_condition(value);
+    }
+}
+
+static inline int hare_is_number(hare_value value) {
+    return value.type == HARE_INT || value.type == HARE_FLOAT;
+}
+
+static inline double hare_as_float(hare_value value) {
+    return value.type == HARE_INT ? (double)value.as.i : value.as.f;
+}
+
+static inline int hare_equals(hare_value lhs, hare_value rhs) {
+    if (hare_is_number(lhs) && hare_is_number(rhs) && lhs.type != rhs.type) {
+        return hare_as_float(lhs) == hare_as_float(rhs);
+    }
+    if (lhs.type != rhs.type) {
+        return 0;
+    }
+    switch (lhs.type) {
+    case HARE_INT:
+        return lhs.as.i == rhs.as.i;
+    case HARE_FLOAT:
+        return lhs.as.f == rhs.as.f;
+    case HARE_BOOL:
+        return lhs.as.b == rhs.as.b;
+    case HARE_STR:
+        return strcmp(lhs.as.s, rhs.as.s) == 0;
+    default:
+        return 0;
+    }
+}
+
+/* 比较结果小于、等于、大于零时，分别返回 <、<=、>、>= 的结果 */
+static inline hare_value hare_compare(hare_op op, int order) {
+    switch (op) {
+    case HARE_GT:
+        return hare_bool_value(order > 0);
+    case HARE_GTE:
+        return hare_bool_value(order >= 0);
+    case HARE_LT:
+        return hare_bool_value(order < 0);
+    default:
+        return hare_bool_value(order <= 0);
+    }
+}
+
+static inline hare_value hare_binary(hare_op op, hare_value lhs, hare_value rhs) {
+    if (op == HARE_EQ || op == HARE_NEQ) {
+        return hare_bool_value(hare_equals(lhs, rhs) == (op == HARE_EQ));
+    }
+
+    if (lhs.type == HARE_INT && rhs.type == HARE_INT) {
+        hare_int a = lhs.as.i, b = rhs.as.i, result = 0;
+        int overflow = 0;
+        switch (op) {
+        case HARE_ADD:
+            overflow = __builtin_add_overflow(a, b, &result);
+            break;
+        case HARE_SUB:
+            overflow = __builtin_sub_overflow(a, b, &result);
+            break;
+        case HARE_MUL:
+            overflow = __builtin_mul_overflow(a, b, &result);
+            break;
+        case HARE_DIV:
+        case HARE_MOD:
+            if (b == 0) {
+                hare_error("Division by zero", lhs, op, rhs);
+            }
+            /* 最小值除以 -1 溢出 */
+            overflow = b == -1 && a == (hare_int)((unsigned __int128)1 << 127);
+            if (!overflow) {
+                result = op == HARE_DIV ? a / b : a % b;
+            }
+            break;
+        default:
+            return hare_compare(op, (a > b) - (a < b));
+        }
+        if (overflow) {
+            hare_error("Integer overflow", lhs, op, rhs);
+        }
+        return hare_int_value(result);
+    }
+
+    if (hare_is_number(lhs) && hare_is_number(rhs)) {
+        double a = hare_as_float(lhs), b = hare_as_float(rhs);
+        switch (op) {
+        case HARE_ADD:
+            return hare_float_value(a + b);
+        case HARE_SUB:
+            return hare_float_value(a - b);
+        case HARE_MUL:
+            return hare_float_value(a * b);
+        case HARE_DIV:
+        case HARE_MOD:
+            if (b == 0.0) {
+                hare_error("Division by zero", lhs, op, rhs);
+            }
+            return hare_float_value(op == HARE_DIV ? a / b : fmod(a, b));
+        case HARE_GT:
+            return hare_bool_value(a > b);
+        case HARE_GTE:
+            return hare_bool_value(a >= b);
+        case HARE_LT:
+            return hare_bool_value(a < b);
+        default:
+            return hare_bool_value(a <= b);
+        }
+    }
+
+    if (lhs.type == HARE_STR && rhs.type == HARE_STR) {
+        if (op == HARE_ADD) {
+            size_t left = strlen(lhs.as.s), right = strlen(rhs.as.s);
+            char *concat = malloc(left + right + 1);
+            memcpy(concat, lhs.as.s, left);
+            memcpy(concat + left, rhs.as.s, right + 1);
+            return hare_str_value(concat);
+        }
+        if (op >= HARE_GT) {
+            return hare_compare(op, strcmp(lhs.as.s, rhs.as.s));
+        }
+    }
+
+    hare_error("Type error", lhs, op, rhs);
+    return hare_undefined;
+}
+
+/* 输出程序顶层的表达式语句的值 */
+static inline void hare_print(hare_value value) {
+    char literal[64];
+    printf("%s\n", hare_literal(value, literal));
+}
+
+/* 程序结束时输出变量的值，未定义的变量不输出 */
+static inline void hare_print_name(const char *name, hare_value value) {
+    char literal[64];
+    if (value.type != HARE_UNDEFINED) {
+        printf("%s = %s\n", name, hare_literal(value, literal));
+    }
+}
@@ -0,0 +1,100 @@
+use super::section::SectionNamer;
+use super::{ByteCode, CompilerError, CompilerWarning, Located};
+use crate::analysis::types::TypeEnv;
+use crate::optimizer::{optimize_bytecodes, optimize_located};
+use crate::parser::ast::AstNode;
+use crate::parser::serialize::SpanTree;
+use crate::parser::{parse_pair, Item};
+
+/// 一段连续的指令与生成它们的源代码行
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineEntry {
+    /// 第一条指令的下标，这段指令一直延续到下一项的 `code` 为止
+    pub code: usize,
+    /// 源代码的第一行，从 1 开始
+    pub first_line: usize,
+    /// 源代码的最后一行，包含在内
+    pub last_line: usize,
+}
+
+/// 调试信息：源代码以及指令与源代码行的对应关系
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DebugInfo {
+    pub source: String,
+    /// 按 `code` 排列
+    pub lines: Vec<LineEntry>,
+}
+
+impl DebugInfo {
+    /// 返回从下标为 `code` 的指令开始的一段指令对应的源代码行
+    pub fn lines_at(&self, code: usize) -> Option<&LineEntry> {
+        self.lines.iter().find(|entry| entry.code == code)
+    }
+}
+
+/// 逐项编译的结果：字节码、附带位置的警告以及调试信息
+pub type CompiledItems = (Vec<ByteCode>, Vec<Located<CompilerWarning>>, DebugInfo);
+
+/// 逐项编译解析出的各个顶层的块与语句，并记录每项生成的指令对应的源代码行
+///
+/// 每项单独进行优化：各项定义的 Section 只会被同一项中的指令引用，因此逐项优化是安全的，
+/// 但跨越两项的窥孔优化（例如合并相邻两项的 `Push`）不会进行。
+///
+/// # 参数
+///
+/// - `source`: 源代码，`items` 必须是由它解析出的、没有语法错误的结果。
+/// - `level`: 优化等级，与 `optimize` 和 `optimize_bytecodes` 相同。
+///
+/// # 返回值
+///
+/// 警告与错误附带产生它们的节点在源代码中的区间，见 `optimize_located` 与 `AstNode::compile_located`。
+pub fn compile_items(
+    source: &str,
+    items: &[Item],
+    level: u8,
+    namer: &mut SectionNamer,
+) -> Result<CompiledItems, Located<CompilerError>> {
+    let mut codes: Vec<ByteCode> = vec![];
+    let mut warnings: Vec<Located<CompilerWarning>> = vec![];
+    let mut debug = DebugInfo {
+        source: source.to_string(),
+        lines: vec![],
+    };
+
+    let mut types = TypeEnv::new();
+
+    for item in items {
+        let span = item.span();
+
+        let node = parse_pair(&item.pair)
+            .map_err(|err| Located::new(CompilerError::ParserError(Box::new(err)), span))?;
+        let spans = SpanTree {
+            span,
+            children: vec![SpanTree::from_item(item)],
+        };
+        let (program, spans, item_warnings) =
+            optimize_located(&AstNode::Program(vec![node]), &spans, source, level)?;
+        warnings.extend(item_warnings);
+
+        let program = program.compile_located(namer, &spans, &mut types)?;
+        let (item_codes, _) = optimize_bytecodes(&program, level);
+        if item_codes.is_empty() {
+            continue;
+        }
+
+        let (first_line, _) = span.location(source);
+        let last_line = first_line
+            + source[span.start..span.end]
+                .trim_end()
+                .matches('\n')
+                .count();
+        debug.lines.push(LineEntry {
+            code: codes.len(),
+            first_line,
+            last_line,
+        });
+        codes.extend(item_codes);
+    }
+
+    Ok((codes, warnings, debug))
+}
@@ -0,0 +1,218 @@
+use super::debug::DebugInfo;
+use super::object::Object;
+use super::operand::{Operand, OperandKind};
+use super::section::{find_sections, Section};
+use super::value::Value;
+use super::{ByteCode, OpCode};
+
+/// 注释开始的列，指令较长时注释紧跟在指令之后
+const COMMENT_COLUMN: usize = 40;
+
+/// 每一级 Section 嵌套的缩进
+const INDENT: &str = "    ";
+
+/// 反汇编字节码
+///
+/// 输出的每一行为一条指令，以指令的下标开头；`MakeSection` 与 `EndMakeSection` 写作 `.section` 与 `.end`，
+/// 其间的指令按嵌套深度缩进。参数按种类书写：常量为字面量，名字为标识符，Section 为 `@` 加上名字；
+/// 注释中给出常量的类型、Section 的范围以及跳转的目标地址。输出可以被 `.basm` 汇编器重新汇编。
+///
+/// ```text
+/// ; 5 instructions, 1 section
+/// 0000:  .section @00000000            ; 0001..0002
+/// 0001:      Push 1                    ; int
+/// 0002:  .end                          ; @00000000
+/// 0003:  Push true                     ; bool
+/// 0004:  JumpIf @00000000              ; -> 0000
+/// ```
+pub fn disassemble(codes: &[ByteCode]) -> String {
+    Disassembler { codes, debug: None }.render()
+}
+
+/// 反汇编字节码，并在每段指令之前以注释的形式插入生成它们的源代码行
+pub fn disassemble_with_source(codes: &[ByteCode], debug: &DebugInfo) -> String {
+    Disassembler {
+        codes,
+        debug: Some(debug),
+    }
+    .render()
+}
+
+/// 反汇编目标文件，`source_lines` 为 `true` 且目标文件包含调试信息时插入源代码行
+pub fn disassemble_object(object: &Object, source_lines: bool) -> String {
+    match &object.debug {
+        Some(debug) if source_lines => disassemble_with_source(&object.codes, debug),
+        _ => disassemble(&object.codes),
+    }
+}
+
+struct Disassembler<'a> {
+    codes: &'a [ByteCode],
+    debug: Option<&'a DebugInfo>,
+}
+
+impl Disassembler<'_> {
+    fn render(&self) -> String {
+        let sections = find_sections(self.codes);
+        let width = self.codes.len().saturating_sub(1).to_string().len().max(4);
+        let mut output = format!(
+            "; {} instruction{}, {} section{}\n",
+            self.codes.len(),
+            plural(self.codes.len()),
+            sections.len(),
+            plural(sections.len())
+        );
+
+        let address = |index: usize| format!("{:0width$}", index, width = width);
+        let mut open: Vec<String> = vec![];
+
+        for (index, code) in self.codes.iter().enumerate() {
+            if code.op == OpCode::EndMakeSection {
+                open.pop();
+            }
+            let indent = INDENT.repeat(open.len());
+
+            if let Some(entry) = self.debug.and_then(|debug| debug.lines_at(index)) {
+                let source = &self.debug.unwrap().source;
+                let number_width = entry.last_line.to_string().len();
+                for line in entry.first_line..=entry.last_line {
+                    let text = source.lines().nth(line - 1).unwrap_or("");
+                    output.push_str(
+                        format!(
+                            "{}   {}; {:>w$} | {}",
+                            " ".repeat(width),
+                            indent,
+                            line,
+                            text,
+                            w = number_width
+                        )
+                        .trim_end(),
+                    );
+                    output.push('\n');
+                }
+            }
+
+            let (text, comment) = match code.op {
+                OpCode::MakeSection => {
+                    let comment = sections
+                        .iter()
+                        .find(|section| section.start == index)
+                        .map_or("unterminated section".to_string(), |section| {
+                            format!("{}..{}", address(section.start + 1), address(section.end))
+                        });
+                    (format!(".section{}", operands(code)), Some(comment))
+                }
+                OpCode::EndMakeSection => {
+                    let comment = match sections.iter().find(|section| section.end == index) {
+                        Some(section) => format!("@{}", word(&section.name)),
+                        None => "unmatched end of section".to_string(),
+                    };
+                    (format!(".end{}", operands(code)), Some(comment))
+                }
+                _ => (instruction(code), self.comment(code, &sections, &address)),
+            };
+
+            let line = format!("{}:  {}{}", address(index), indent, text);
+            match comment {
+                Some(comment) => output.push_str(&format!(
+                    "{:<column$} ; {}\n",
+                    line,
+                    comment,
+                    column = COMMENT_COLUMN
+                )),
+                None => output.push_str(&format!("{}\n", line)),
+            }
+
+            if code.op == OpCode::MakeSection {
+                open.push(code.name().unwrap_or_default().to_string());
+            }
+        }
+
+        output
+    }
+
+    fn comment(
+        &self,
+        code: &ByteCode,
+        sections: &[Section],
+        address: &dyn Fn(usize) -> String,
+    ) -> Option<String> {
+        match code.op.arity().kind()? {
+            OperandKind::Constant => Some(
+                code.operands()
+                    .iter()
+                    .filter_map(|operand| operand.value())
+                    .map(|value| value.type_name())
+                    .collect::<Vec<&str>>()
+                    .join(", "),
+            ),
+            OperandKind::Section => {
+                let targets: Vec<String> = code
+                    .targets()
+                    .into_iter()
+                    .map(|name| {
+                        sections
+                            .iter()
+                            .find(|section| section.name == name)
+                            .map_or("undefined section".to_string(), |section| {
+                                address(section.start)
+                            })
+                    })
+                    .collect();
+                (!targets.is_empty()).then(|| format!("-> {}", targets.join(", ")))
+            }
+            OperandKind::Name => None,
+        }
+    }
+}
+
+/// 书写一条指令，与反汇编的输出中的写法相同
+pub fn instruction(code: &ByteCode) -> String {
+    format!("{}{}", code.op.name(), operands(code))
+}
+
+/// 按种类书写参数，每个参数之前有一个空格
+fn operands(code: &ByteCode) -> String {
+    code.operands()
+        .iter()
+        .map(|operand| {
+            let arg = operand.to_string();
+            match operand {
+                Operand::Section(name) => format!(" @{}", word(name)),
+                Operand::Constant(Value::Str(_)) if is_string_literal(&arg) => format!(" {}", arg),
+                _ => format!(" {}", word(&arg)),
+            }
+        })
+        .collect()
+}
+
+/// 判断参数能否不加引号地书写
+pub fn is_word(arg: &str) -> bool {
+    !arg.is_empty()
+        && !arg.contains(|c: char| c.is_whitespace() || c == ';' || c == '"')
+        && !arg.starts_with(['@', '.'])
+        && !arg.ends_with(':')
+}
+
+/// 判断参数是否为字符串字面量
+fn is_string_literal(arg: &str) -> bool {
+    arg.len() >= 2
+        && arg.starts_with('"')
+        && arg.ends_with('"')
+        && !arg[1..arg.len() - 1].contains(['"', '\n'])
+}
+
+/// 书写一个名字，不能直接书写时加上引号
+fn word(arg: &str) -> String {
+    match is_word(arg) {
+        true => arg.to_string(),
+        false => format!("\"{}\"", arg),
+    }
+}
+
+fn plural(count: usize) -> &'static str {
+    match count {
+        1 => "",
+        _ => "s",
+    }
+}
@@ -0,0 +1,238 @@
+//! 以 Graphviz 的 DOT 格式输出抽象语法树与控制流图，由 `--emit dot` 使用
+//!
+//! 输出是纯文本，用 `dot -Tsvg` 等工具渲染。
+
+use std::fmt::Write;
+
+use super::disasm::instruction;
+use super::section::{find_sections, top_level_indices, Section};
+use super::{ByteCode, OpCode};
+use crate::parser::ast::AstNode;
+
+/// 将抽象语法树输出为 DOT 格式的有向图
+///
+/// 每个节点对应一个 `AstNode`，表达式以运算符、常量以字面量、标识符以名字为标签，其余的节点以关键字为标签。
+/// 子节点按 `AstNode::children` 的顺序从左到右排列。
+///
+/// ```text
+/// digraph ast {
+///     ordering=out;
+///     node [fontname="monospace"];
+///     n0 [label="Program"];
+///     n1 [label="+"];
+///     n0 -> n1;
+///     ...
+/// }
+/// ```
+pub fn ast_to_dot(ast: &AstNode) -> String {
+    let mut output =
+        String::from("digraph ast {\n    ordering=out;\n    node [fontname=\"monospace\"];\n");
+    let mut count = 0;
+    ast_node(ast, &mut count, &mut output);
+    output.push_str("}\n");
+    output
+}
+
+/// 输出节点与其子树，返回节点的编号
+fn ast_node(node: &AstNode, count: &mut usize, output: &mut String) -> usize {
+    let id = *count;
+    *count += 1;
+
+    let (label, shape) = match node {
+        AstNode::Program(_) => ("Program".to_string(), None),
+        AstNode::Block(_) => ("Block".to_string(), None),
+        AstNode::Constant(literal) => (literal.clone(), Some("box")),
+        AstNode::Identifier(name) => (name.clone(), Some("box")),
+        AstNode::Expr(_, Some(op), _) => (op.as_raw().to_string(), Some("circle")),
+        // `format_ast` 为赋值语句的值与 if 的条件加上的包装节点
+        AstNode::Expr(_, None, _) => ("Expr".to_string(), None),
+        AstNode::Assign(_, _, _) => ("let".to_string(), None),
+        AstNode::SetValue(_, _) => ("=".to_string(), None),
+        AstNode::ReturnBlock(_) => ("rtb".to_string(), None),
+        AstNode::If(_, _, _, _) => ("if".to_string(), None),
+        AstNode::Elif(_, _) => ("elif".to_string(), None),
+        AstNode::Else(_) => ("else".to_string(), None),
+        AstNode::Error(code) => (format!("error: {}", code), Some("octagon")),
+        AstNode::Empty => ("empty".to_string(), None),
+    };
+    let shape = shape.map_or(String::new(), |shape| format!(", shape={}", shape));
+    let _ = writeln!(output, "    n{} [label={}{}];", id, quote(&label), shape);
+
+    for child in node.children() {
+        let child = ast_node(child, count, output);
+        let _ = writeln!(output, "    n{} -> n{};", id, child);
+    }
+
+    id
+}
+
+/// 将字节码的控制流图输出为 DOT 格式的有向图
+///
+/// 顶层代码与每个 Section 的指令被分为基本块，基本块在跳转与 `Return` 之后结束。
+/// 每个 Section 是一个子图，以一个点作为出口；顶层代码以 `start` 开始、`end` 结束。边的种类如下：
+///
+/// - `jump`：`Jump` 进入目标 Section；
+/// - `true` 与 `false`：`JumpIf` 进入目标 Section 或继续执行，`JumpIfElse` 进入两个 Section 之一；
+/// - `return`（虚线）：Section 结束后回到跳转之后的指令；
+/// - `Return` 指令到所在 Section 出口的边也标为 `return`；
+/// - 其余的边为顺序执行。
+pub fn cfg_to_dot(codes: &[ByteCode]) -> String {
+    let sections = find_sections(codes);
+    let mut output = String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+
+    let main = Region {
+        section: None,
+        blocks: basic_blocks(codes, &top_level_indices(codes, 0, codes.len())),
+        exit: "end".to_string(),
+    };
+    let regions: Vec<Region> = std::iter::once(main)
+        .chain(sections.iter().map(|section| Region {
+            section: Some(section),
+            blocks: basic_blocks(codes, &section.body(codes)),
+            exit: format!("exit{}", section.start),
+        }))
+        .collect();
+
+    output.push_str("    start [shape=oval];\n    end [shape=oval];\n");
+    let _ = writeln!(output, "    start -> {};", regions[0].entry());
+
+    for region in &regions {
+        let indent = match region.section {
+            Some(section) => {
+                let _ = writeln!(
+                    output,
+                    "    subgraph {} {{\n        label={};\n        {} [shape=point];",
+                    quote(&format!("cluster_{}", section.name)),
+                    quote(&format!("@{}", section.name)),
+                    region.exit
+                );
+                "        "
+            }
+            None => "    ",
+        };
+        for block in &region.blocks {
+            let label: String = block
+                .iter()
+                .map(|&index| format!("{:04}: {}\\l", index, escape(&instruction(&codes[index]))))
+                .collect();
+            let _ = writeln!(output, "{}b{} [label=\"{}\"];", indent, block[0], label);
+        }
+        if region.section.is_some() {
+            output.push_str("    }\n");
+        }
+    }
+
+    for region in &regions {
+        for (position, block) in region.blocks.iter().enumerate() {
+            let id = format!("b{}", block[0]);
+            let next = region
+                .blocks
+                .get(position + 1)
+                .map_or(region.exit.clone(), |block| format!("b{}", block[0]));
+            let last = &codes[*block.last().unwrap()];
+            let targets: Vec<&Region> = last
+                .targets()
+                .into_iter()
+                .filter_map(|name| {
+                    regions
+                        .iter()
+                        .find(|region| region.section.is_some_and(|section| section.name == name))
+                })
+                .collect();
+
+            match (&last.op, targets.as_slice()) {
+                (OpCode::Jump, [target]) => {
+                    let _ = writeln!(output, "    {} -> {} [label=\"jump\"];", id, target.entry());
+                    let _ = writeln!(
+                        output,
+                        "    {} -> {} [label=\"return\", style=dashed];",
+                        target.exit, next
+                    );
+                }
+                (OpCode::JumpIf, [target]) => {
+                    let _ = writeln!(output, "    {} -> {} [label=\"true\"];", id, target.entry());
+                    let _ = writeln!(output, "    {} -> {} [label=\"false\"];", id, next);
+                    let _ = writeln!(
+                        output,
+                        "    {} -> {} [label=\"return\", style=dashed];",
+                        target.exit, next
+                    );
+                }
+                (OpCode::JumpIfElse, [then, otherwise]) => {
+                    let _ = writeln!(output, "    {} -> {} [label=\"true\"];", id, then.entry());
+                    let _ = writeln!(
+                        output,
+                        "    {} -> {} [label=\"false\"];",
+                        id,
+                        otherwise.entry()
+                    );
+                    for target in [then, otherwise] {
+                        let _ = writeln!(
+                            output,
+                            "    {} -> {} [label=\"return\", style=dashed];",
+                            target.exit, next
+                        );
+                    }
+                }
+                (OpCode::Return, _) => {
+                    let _ = writeln!(output, "    {} -> {} [label=\"return\"];", id, region.exit);
+                }
+                _ => {
+                    let _ = writeln!(output, "    {} -> {};", id, next);
+                }
+            }
+        }
+    }
+
+    output.push_str("}\n");
+    output
+}
+
+/// 顶层代码或一个 Section 的基本块
+struct Region<'a> {
+    /// 顶层代码为 `None`
+    section: Option<&'a Section>,
+    /// 每个基本块为其中的指令下标
+    blocks: Vec<Vec<usize>>,
+    /// 出口节点的编号
+    exit: String,
+}
+
+impl Region<'_> {
+    /// 入口节点的编号，没有指令时为出口
+    fn entry(&self) -> String {
+        self.blocks
+            .first()
+            .map_or(self.exit.clone(), |block| format!("b{}", block[0]))
+    }
+}
+
+/// 将指令下标分为基本块，每个基本块在控制流指令之后结束
+fn basic_blocks(codes: &[ByteCode], indices: &[usize]) -> Vec<Vec<usize>> {
+    let mut blocks: Vec<Vec<usize>> = vec![];
+    let mut current: Vec<usize> = vec![];
+
+    for &index in indices {
+        current.push(index);
+        if matches!(
+            codes[index].op,
+            OpCode::Jump | OpCode::JumpIf | OpCode::JumpIfElse | OpCode::Return
+        ) {
+            blocks.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+
+    blocks
+}
+
+/// 转义 DOT 字符串中的反斜杠与引号
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", escape(text))
+}
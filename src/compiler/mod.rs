@@ -1,24 +1,164 @@
-use crate::BinaryOp;
-use rand::Rng;
+pub mod asm;
+pub mod c;
+pub mod debug;
+pub mod disasm;
+pub mod dot;
+pub mod object;
+pub mod operand;
+pub mod register;
+pub mod section;
+pub mod value;
+pub mod verify;
+pub mod wat;
 
-use crate::ast::AstNode;
+use crate::parser::BinaryOp;
+
+use crate::analysis::types::{infer, Type, TypeEnv};
+use crate::ir::{lower, lower_with};
+use crate::parser::ast::AstNode;
+use crate::parser::errors::{Label, ParserError};
+use crate::parser::serialize::SpanTree;
+use crate::parser::span::Span;
+use operand::{Operand, OperandError};
+use section::SectionNamer;
 
 use thiserror::Error;
 
+/// 编译错误
+///
+/// 每种错误有一个稳定的代码（见 `code`），`hare explain <代码>` 输出对它的详细解释。
 #[derive(Error, Debug)]
-pub(crate) enum CompilerError {
-    #[error("Compile error: {0}")]
-    CompileError(String),
+pub enum CompilerError {
+    #[error("Compile error: Invalid constant: {0}")]
+    InvalidConstant(String),
+    #[error("Division by zero in constant expression: {0}")]
+    DivisionByZero(String),
+    #[error("Invalid operand: {0}")]
+    InvalidOperand(#[from] OperandError),
+    /// 后端不支持的语言结构，包含后端的名字与结构的描述
+    #[error("The {0} backend does not support {1}")]
+    Unsupported(&'static str, String),
+    /// 变量在赋值之前被读取，只有静态确定变量类型的后端会报告
+    #[error("Compile error: Undefined variable: {0}")]
+    UndefinedVariable(String),
+    /// 语法树中有解析错误留下的 `AstNode::Error`
+    #[error("Compile error: Cannot compile code with syntax errors: {0}")]
+    SyntaxErrors(String),
+    #[error("{0}")]
+    ParserError(Box<ParserError>),
+    /// `rtb` 不在任何块中
+    #[error("Compile error: `rtb` can only be used inside a block: {0}")]
+    ReturnOutsideBlock(String),
+    /// 块被用作值，但其中没有 `rtb`
+    #[error("Compile error: Block is used as a value but never returns one with `rtb`: {0}")]
+    BlockWithoutValue(String),
+    /// if 被用作值，但没有 else 分支
+    #[error("Compile error: `if` is used as a value but has no `else` branch: {0}")]
+    IfWithoutElse(String),
+    /// if 被用作值，但分支的值的类型不同
+    #[error("Compile error: Branches of `if` produce incompatible types `{0}` and `{1}`: {2}")]
+    IncompatibleBranches(Type, Type, String),
+    /// 以下为内部错误：语法树或中间表示的结构不合法
+    #[error("Compile error: Expected an identifier, found: {0}")]
+    ExpectedIdentifier(String),
+    #[error("Compile error: Expected a block, found: {0}")]
+    ExpectedBlock(String),
+    #[error("Compile error: Unsupported expression: {0}")]
+    ExpectedExpression(String),
+    #[error("Compile error: Unexpected node in elif branches: {0}")]
+    UnexpectedElif(String),
+    #[error("Compile error: Unexpected node in else branch: {0}")]
+    UnexpectedElse(String),
+    #[error("Compile error: Failed to compile right side of expression")]
+    MissingOperand,
+    #[error("Compile error: Temporaries {0} are not on the top of the stack")]
+    TempsNotOnStack(String),
+    #[error("Compile error: Temporary {0} is used before it is defined")]
+    UndefinedTemp(String),
+    /// 寄存器后端需要的寄存器多于可用的寄存器，包含需要与可用的数量
+    #[error("Compile error: At least {0} registers are required, found: {1}")]
+    TooFewRegisters(usize, usize),
 }
 
-/// 操作码枚举，用于表示字节码中的操作
+impl CompilerError {
+    /// 返回错误的代码，在机器可读的输出与 `hare explain` 中使用；解析错误返回其自身的代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompilerError::InvalidConstant(_) => "E0100",
+            CompilerError::DivisionByZero(_) => "E0101",
+            CompilerError::InvalidOperand(_) => "E0102",
+            CompilerError::Unsupported(_, _) => "E0103",
+            CompilerError::UndefinedVariable(_) => "E0104",
+            CompilerError::SyntaxErrors(_) => "E0105",
+            CompilerError::ParserError(err) => err.code(),
+            CompilerError::ExpectedIdentifier(_) => "E0106",
+            CompilerError::ExpectedBlock(_) => "E0107",
+            CompilerError::ExpectedExpression(_) => "E0108",
+            CompilerError::UnexpectedElif(_) => "E0109",
+            CompilerError::UnexpectedElse(_) => "E0110",
+            CompilerError::MissingOperand => "E0111",
+            CompilerError::TempsNotOnStack(_) => "E0112",
+            CompilerError::UndefinedTemp(_) => "E0113",
+            CompilerError::TooFewRegisters(_, _) => "E0114",
+            CompilerError::ReturnOutsideBlock(_) => "E0115",
+            CompilerError::BlockWithoutValue(_) => "E0116",
+            CompilerError::IfWithoutElse(_) => "E0117",
+            CompilerError::IncompatibleBranches(_, _, _) => "E0118",
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CompilerWarning {
+    #[error("Unreachable branch: `{0}` can never run")]
+    UnreachableBranch(String),
+}
+
+impl CompilerWarning {
+    /// 返回警告的代码，与 `CompilerError::code` 相同
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompilerWarning::UnreachableBranch(_) => "W0001",
+        }
+    }
+}
+
+/// 附带源代码位置的编译期错误或警告
+///
+/// `span` 为出错的节点的区间，没有区间树时为 `Span::default()`；`labels` 为与之相关的其他位置，
+/// 如死分支所在的 if。
 #[derive(Debug)]
+pub struct Located<T> {
+    pub value: T,
+    pub span: Span,
+    pub labels: Vec<Label>,
+}
+
+impl<T> Located<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Self {
+            value,
+            span,
+            labels: vec![],
+        }
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+}
+
+/// 操作码枚举，用于表示字节码中的操作
+#[derive(Debug, Clone, PartialEq)]
 pub enum OpCode {
     // Stack
     /// 将一个或多个元素压入栈中
     Push,
     /// 取出栈顶的元素
     Pop,
+    /// 复制栈顶的元素，然后压入栈中
+    Dup,
     // Arithmetic
     /// 将栈顶的头两个元素取出相加，然后压入栈中
     Add,
@@ -48,8 +188,10 @@ pub enum OpCode {
     // Flow
     /// 无条件跳转到指定的 Section，将该 Section 的返回值压入栈中
     Jump,
-    /// 当栈顶的元素为 true 时跳转到指定的 Section，将该 Section 的返回值压入栈中
+    /// 取出栈顶的元素，当其为 true 时跳转到指定的 Section，将该 Section 的返回值压入栈中
     JumpIf,
+    /// 取出栈顶的元素，为 true 时跳转到 args[0] 指定的 Section，否则跳转到 args[1] 指定的 Section，将该 Section 的返回值压入栈中
+    JumpIfElse,
     // Heap
     /// 从堆中取出一个元素
     LoadName,
@@ -65,64 +207,97 @@ pub enum OpCode {
     Return,
 }
 
+/// 一条指令，由 `ByteCode::new` 创建，参数的个数与种类总是符合操作码的要求
+#[derive(Debug, Clone, PartialEq)]
 pub struct ByteCode {
     pub op: OpCode,
-    pub args: Vec<String>,
+    operands: Vec<Operand>,
 }
 
-impl ByteCode {
-    pub fn new(op: OpCode, args: Vec<String>) -> Self {
-        Self { op, args }
+impl AstNode {
+    /// 将抽象语法树编译为字节码，Section 的名字由一个新的 `SectionNamer` 按顺序生成
+    pub fn compile(&self) -> Result<Vec<ByteCode>, CompilerError> {
+        self.compile_with(&mut SectionNamer::new())
     }
-}
 
-pub fn get_random_name() -> String {
-    let mut rng = rand::thread_rng();
-    let mut name = String::new();
-    for _ in 0..8 {
-        name.push(rng.gen_range(0..10).to_string().chars().next().unwrap());
+    /// 将抽象语法树编译为字节码，使用 `namer` 生成 Section 的名字
+    ///
+    /// 抽象语法树先被降低为中间表示，再由中间表示生成字节码。
+    pub fn compile_with(&self, namer: &mut SectionNamer) -> Result<Vec<ByteCode>, CompilerError> {
+        lower(self)?.emit_with(namer)
     }
-    name
-}
 
-impl AstNode {
-    pub fn compile(&self) -> Result<Vec<ByteCode>, CompilerError> {
-        let mut bytecode: Vec<ByteCode> = Vec::new();
+    /// 与 `compile_with` 相同，`spans` 为与抽象语法树对应的区间树，`types` 为之前的代码中变量的类型，
+    /// 见 `lower_with`
+    ///
+    /// 错误附带出错的节点的区间，由中间表示生成字节码时的错误附带整个抽象语法树的区间。
+    pub fn compile_located(
+        &self,
+        namer: &mut SectionNamer,
+        spans: &SpanTree,
+        types: &mut TypeEnv,
+    ) -> Result<Vec<ByteCode>, Located<CompilerError>> {
+        lower_with(self, spans, types)?
+            .emit_with(namer)
+            .map_err(|err| Located::new(err, spans.span))
+    }
 
-        match self {
-            AstNode::Program(nodes) => {
-                for node in nodes {
-                    bytecode.extend(node.compile()?);
-                }
-            }
-            AstNode::Expr(left, op, right) => {
-                let left_bytecode = left.compile()?;
-
-                if op.is_some() {
-                    let right_bytecode = right
-                        .clone()
-                        .ok_or(CompilerError::CompileError(
-                            "Failed to compile right side of expression".to_string(),
-                        ))?
-                        .compile()?;
-
-                    bytecode.extend(left_bytecode);
-                    bytecode.extend(right_bytecode);
-                    bytecode.push(ByteCode::new(op.clone().unwrap().to_opcode(), vec![]));
-                } else {
-                    bytecode.extend(left_bytecode);
+    /// 检查作为值的 if：必须有 else 分支，每个分支都以 `rtb` 给出值，且值的类型相同
+    ///
+    /// 值的类型由 `infer` 推断，`lookup` 返回变量的类型，类型无法确定的分支不会被比较。
+    pub(crate) fn check_if_value(
+        &self,
+        lookup: &dyn Fn(&str) -> Type,
+    ) -> Result<(), CompilerError> {
+        let if_branches = self
+            .if_branches()
+            .ok_or_else(|| CompilerError::ExpectedBlock(self.as_code()))?;
+        // 错误信息中只给出 if 的条件，完整的代码可能很长
+        let describe = format!("if {}", if_branches.branches[0].0.as_code());
+        let otherwise = if_branches
+            .otherwise
+            .ok_or_else(|| CompilerError::IfWithoutElse(describe.clone()))?;
+
+        let mut known: Option<Type> = None;
+        for block in if_branches
+            .branches
+            .iter()
+            .map(|(_, block)| *block)
+            .chain([otherwise])
+        {
+            let value = block
+                .block_value()
+                .ok_or_else(|| CompilerError::BlockWithoutValue(block.as_code()))?;
+            match (known, infer(value, lookup)) {
+                (_, Type::Unknown) => {}
+                (Some(ty), branch) if ty.unify(&branch).is_none() => {
+                    return Err(CompilerError::IncompatibleBranches(ty, branch, describe))
                 }
+                (_, branch) => known = Some(branch),
             }
-            AstNode::Identifier(name) => {
-                bytecode.push(ByteCode::new(OpCode::LoadName, vec![name.clone()]));
-            }
-            AstNode::Constant(name) => {
-                bytecode.push(ByteCode::new(OpCode::Push, vec![name.clone()]));
-            }
-            _ => {}
         }
 
-        Ok(bytecode)
+        Ok(())
+    }
+}
+
+impl OpCode {
+    /// 返回与算术或比较操作码对应的二元操作符，其它操作码返回 `None`
+    pub fn to_binary_op(&self) -> Option<BinaryOp> {
+        match self {
+            OpCode::Add => Some(BinaryOp::Add),
+            OpCode::Sub => Some(BinaryOp::Sub),
+            OpCode::Mul => Some(BinaryOp::Mul),
+            OpCode::Div => Some(BinaryOp::Div),
+            OpCode::Mod => Some(BinaryOp::Mod),
+            OpCode::Eq => Some(BinaryOp::Eq),
+            OpCode::Neq => Some(BinaryOp::Neq),
+            OpCode::Gt => Some(BinaryOp::Gt),
+            OpCode::Lt => Some(BinaryOp::Lt),
+            OpCode::Gte => Some(BinaryOp::Gte),
+            OpCode::Lte => Some(BinaryOp::Lte),
+            _ => None,
+        }
     }
 }
 
@@ -144,8 +319,14 @@ impl BinaryOp {
     }
 }
 
-pub fn print_bytecodes(codes: &Vec<ByteCode>) {
-    for bytecode in codes {
-        println!("{:?} {:?}", bytecode.op, bytecode.args);
-    }
+pub fn print_bytecodes(codes: &[ByteCode]) {
+    print!("{}", format_bytecodes(codes));
+}
+
+/// 将字节码格式化为文本，每行一条指令，格式与 `print_bytecodes` 的输出相同
+pub fn format_bytecodes(codes: &[ByteCode]) -> String {
+    codes
+        .iter()
+        .map(|bytecode| format!("{:?} {:?}\n", bytecode.op, bytecode.args()))
+        .collect()
 }
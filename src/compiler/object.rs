@@ -0,0 +1,453 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use super::debug::{DebugInfo, LineEntry};
+use super::operand::{Operand, OperandError};
+use super::section::{find_sections, Section};
+use super::value::Value;
+use super::verify::{stack_usage, verify, VerifyError};
+use super::{ByteCode, OpCode};
+
+/// 目标文件开头的魔数
+pub const MAGIC: &[u8; 4] = b"HARE";
+
+/// 目标文件格式的版本
+pub const VERSION: u16 = 4;
+
+/// 目标文件的扩展名
+pub const EXTENSION: &str = "hbc";
+
+/// 标志位：目标文件包含调试信息
+const FLAG_DEBUG: u8 = 1;
+
+/// 表示栈深度没有上限或未知
+const UNKNOWN_DEPTH: u32 = u32::MAX;
+
+/// 常量池中常量的类型标记
+const CONSTANT_FLOAT: u8 = 0;
+const CONSTANT_BOOL: u8 = 1;
+const CONSTANT_STR: u8 = 2;
+
+/// 指令参数的种类标记
+const OPERAND_INT: u8 = 0;
+const OPERAND_CONSTANT: u8 = 1;
+const OPERAND_NAME: u8 = 2;
+const OPERAND_SECTION: u8 = 3;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ObjectError {
+    #[error("Not a Hare bytecode file")]
+    BadMagic,
+    #[error("Unsupported bytecode file version: {0}")]
+    UnsupportedVersion(u16),
+    #[error("Unexpected end of bytecode file at byte {0}")]
+    UnexpectedEnd(usize),
+    #[error("Invalid opcode {0} at byte {1}")]
+    InvalidOpCode(u8, usize),
+    #[error("Invalid UTF-8 string at byte {0}")]
+    InvalidString(usize),
+    #[error("Invalid tag {0} at byte {1}")]
+    InvalidTag(u8, usize),
+    #[error("Index {0} out of range at byte {1}")]
+    InvalidIndex(usize, usize),
+    #[error("Invalid instruction at byte {0}: {1}")]
+    InvalidInstruction(usize, OperandError),
+    #[error("Trailing data at byte {0}")]
+    TrailingData(usize),
+    #[error("Invalid bytecode: {0}")]
+    Invalid(VerifyError),
+    #[error("Section table entry {0} does not match the bytecode")]
+    SectionMismatch(usize),
+    #[error("Stored maximum stack depth of the program does not match the bytecode")]
+    StackMismatch,
+    #[error("Stored maximum stack depth of section `{0}` does not match the bytecode")]
+    SectionStackMismatch(String),
+}
+
+/// 序列化的字节码，即 `hare` 输出的目标文件
+///
+/// 文件格式（整数均为小端序，字符串为 `u32` 长度加上 UTF-8 内容）：
+///
+/// ```text
+/// "HARE" u16:版本 u8:标志 u32:最大栈深度
+/// u32:常量数 { u8:类型 值 }                 类型 0: f64 1: u8 布尔值 2: 字符串
+/// u32:名字数 { 字符串 }
+/// u32:指令数 { u8:操作码 u32:参数个数 { u8:种类 值 } }
+///                                           种类 0: i128 立即数 1: u32 常量下标
+///                                                2: u32 名字下标 3: u32 Section 名字的下标
+/// u32:Section 数 { 字符串:名字 u32:MakeSection 的下标 u32:EndMakeSection 的下标 u32:嵌套深度
+///                  u32:最大栈深度 }
+/// 有调试信息时：字符串:源代码 u32:项数 { u32:指令下标 u32:第一行 u32:最后一行 }
+/// ```
+///
+/// 栈深度没有上限（可能无限递归）或字节码未通过校验时，最大栈深度记为 `u32::MAX`。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Object {
+    pub codes: Vec<ByteCode>,
+    /// 执行整个程序时操作数栈的最大深度，含义与 `StackUsage::program` 相同
+    pub max_stack: Option<usize>,
+    /// Section 的元数据，按 `MakeSection` 出现的顺序排列
+    pub sections: Vec<SectionInfo>,
+    pub debug: Option<DebugInfo>,
+}
+
+/// 目标文件中一个 Section 的元数据
+#[derive(Debug, Clone, PartialEq)]
+pub struct SectionInfo {
+    pub section: Section,
+    /// Section 被调用时操作数栈的最大深度，含义与 `StackUsage::sections` 相同
+    pub max_stack: Option<usize>,
+}
+
+impl Object {
+    /// 由字节码创建目标文件，Section 的元数据与栈深度由字节码计算得到
+    pub fn new(codes: Vec<ByteCode>) -> Self {
+        let usage = stack_usage(&codes).unwrap_or_default();
+        let sections = find_sections(&codes)
+            .into_iter()
+            .map(|section| SectionInfo {
+                max_stack: usage.sections.get(&section.name).copied().flatten(),
+                section,
+            })
+            .collect();
+
+        Self {
+            codes,
+            max_stack: usage.program,
+            sections,
+            debug: None,
+        }
+    }
+
+    pub fn with_debug(mut self, debug: DebugInfo) -> Self {
+        self.debug = Some(debug);
+        self
+    }
+
+    /// 序列化为字节
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer { bytes: vec![] };
+
+        writer.bytes.extend(MAGIC);
+        writer.bytes.extend(VERSION.to_le_bytes());
+        writer
+            .bytes
+            .push(if self.debug.is_some() { FLAG_DEBUG } else { 0 });
+        writer.depth(self.max_stack);
+
+        let pool = Pool::new(&self.codes);
+        writer.len(pool.constants.len());
+        for value in &pool.constants {
+            match value {
+                Value::Float(float) => {
+                    writer.bytes.push(CONSTANT_FLOAT);
+                    writer.bytes.extend(float.to_le_bytes());
+                }
+                Value::Bool(boolean) => {
+                    writer.bytes.push(CONSTANT_BOOL);
+                    writer.bytes.push(*boolean as u8);
+                }
+                Value::Str(string) => {
+                    writer.bytes.push(CONSTANT_STR);
+                    writer.string(string);
+                }
+                Value::Int(_) => unreachable!("integers are immediate operands"),
+            }
+        }
+        writer.len(pool.names.len());
+        for name in &pool.names {
+            writer.string(name);
+        }
+
+        writer.len(self.codes.len());
+        for code in &self.codes {
+            writer.bytes.push(code.op.code());
+            writer.len(code.operands().len());
+            for operand in code.operands() {
+                match operand {
+                    Operand::Int(int) => {
+                        writer.bytes.push(OPERAND_INT);
+                        writer.bytes.extend(int.to_le_bytes());
+                    }
+                    Operand::Constant(value) => {
+                        writer.bytes.push(OPERAND_CONSTANT);
+                        writer.len(pool.constant_index[&value.to_literal()]);
+                    }
+                    Operand::Name(name) => {
+                        writer.bytes.push(OPERAND_NAME);
+                        writer.len(pool.name_index[name]);
+                    }
+                    Operand::Section(name) => {
+                        writer.bytes.push(OPERAND_SECTION);
+                        writer.len(pool.name_index[name]);
+                    }
+                }
+            }
+        }
+
+        writer.len(self.sections.len());
+        for info in &self.sections {
+            writer.string(&info.section.name);
+            writer.len(info.section.start);
+            writer.len(info.section.end);
+            writer.len(info.section.depth);
+            writer.depth(info.max_stack);
+        }
+
+        if let Some(debug) = &self.debug {
+            writer.string(&debug.source);
+            writer.len(debug.lines.len());
+            for entry in &debug.lines {
+                writer.len(entry.code);
+                writer.len(entry.first_line);
+                writer.len(entry.last_line);
+            }
+        }
+
+        writer.bytes
+    }
+
+    /// 从字节反序列化并校验字节码，返回的目标文件可以直接执行
+    ///
+    /// 字节码不合法时返回第一个校验错误；文件中的 Section 表与由字节码重新计算的结果不一致时
+    /// 返回第一个不一致的表项的下标；记录的最大栈深度与 [`stack_usage`] 的结果不一致时同样拒绝。
+    pub fn load(bytes: &[u8]) -> Result<Object, ObjectError> {
+        let object = Object::from_bytes(bytes)?;
+        verify(&object.codes).map_err(|errors| ObjectError::Invalid(errors[0].clone()))?;
+
+        let sections = find_sections(&object.codes);
+        let count = object.sections.len().max(sections.len());
+        if let Some(index) = (0..count).find(|&index| {
+            object.sections.get(index).map(|info| &info.section) != sections.get(index)
+        }) {
+            return Err(ObjectError::SectionMismatch(index));
+        }
+
+        // 校验通过的字节码一定能计算栈深度
+        let usage = stack_usage(&object.codes).unwrap_or_default();
+        if object.max_stack != usage.program {
+            return Err(ObjectError::StackMismatch);
+        }
+        for info in &object.sections {
+            if usage.sections.get(&info.section.name).copied().flatten() != info.max_stack {
+                return Err(ObjectError::SectionStackMismatch(info.section.name.clone()));
+            }
+        }
+
+        Ok(object)
+    }
+
+    /// 从字节反序列化，不校验字节码
+    pub fn from_bytes(bytes: &[u8]) -> Result<Object, ObjectError> {
+        let mut reader = Reader { bytes, pos: 0 };
+
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(ObjectError::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.take(2)?.try_into().unwrap());
+        if version != VERSION {
+            return Err(ObjectError::UnsupportedVersion(version));
+        }
+        let flags = reader.take(1)?[0];
+        let max_stack = reader.depth()?;
+
+        let mut constants: Vec<Value> = vec![];
+        for _ in 0..reader.len()? {
+            let pos = reader.pos;
+            constants.push(match reader.take(1)?[0] {
+                CONSTANT_FLOAT => {
+                    Value::Float(f64::from_le_bytes(reader.take(8)?.try_into().unwrap()))
+                }
+                CONSTANT_BOOL => Value::Bool(reader.take(1)?[0] != 0),
+                CONSTANT_STR => Value::Str(reader.string()?),
+                tag => return Err(ObjectError::InvalidTag(tag, pos)),
+            });
+        }
+        let names = (0..reader.len()?)
+            .map(|_| reader.string())
+            .collect::<Result<Vec<String>, ObjectError>>()?;
+
+        let mut codes: Vec<ByteCode> = vec![];
+        for _ in 0..reader.len()? {
+            let pos = reader.pos;
+            let byte = reader.take(1)?[0];
+            let op = OpCode::from_code(byte).ok_or(ObjectError::InvalidOpCode(byte, pos))?;
+            let operands = (0..reader.len()?)
+                .map(|_| reader.operand(&constants, &names))
+                .collect::<Result<Vec<Operand>, ObjectError>>()?;
+            let code = ByteCode::new(op, operands)
+                .map_err(|err| ObjectError::InvalidInstruction(pos, err))?;
+            codes.push(code);
+        }
+
+        let mut sections: Vec<SectionInfo> = vec![];
+        for _ in 0..reader.len()? {
+            sections.push(SectionInfo {
+                section: Section {
+                    name: reader.string()?,
+                    start: reader.len()?,
+                    end: reader.len()?,
+                    depth: reader.len()?,
+                },
+                max_stack: reader.depth()?,
+            });
+        }
+
+        let debug = match flags & FLAG_DEBUG {
+            0 => None,
+            _ => {
+                let source = reader.string()?;
+                let lines = (0..reader.len()?)
+                    .map(|_| {
+                        Ok(LineEntry {
+                            code: reader.len()?,
+                            first_line: reader.len()?,
+                            last_line: reader.len()?,
+                        })
+                    })
+                    .collect::<Result<Vec<LineEntry>, ObjectError>>()?;
+                Some(DebugInfo { source, lines })
+            }
+        };
+
+        if reader.pos != bytes.len() {
+            return Err(ObjectError::TrailingData(reader.pos));
+        }
+
+        Ok(Object {
+            codes,
+            max_stack,
+            sections,
+            debug,
+        })
+    }
+}
+
+/// 判断字节是否为目标文件的内容
+pub fn is_object(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// 写入目标文件时收集的常量池与名字表，相同的常量或名字只出现一次
+struct Pool {
+    constants: Vec<Value>,
+    /// 以常量的字面量为键
+    constant_index: HashMap<String, usize>,
+    names: Vec<String>,
+    name_index: HashMap<String, usize>,
+}
+
+impl Pool {
+    fn new(codes: &[ByteCode]) -> Self {
+        let mut pool = Pool {
+            constants: vec![],
+            constant_index: HashMap::new(),
+            names: vec![],
+            name_index: HashMap::new(),
+        };
+
+        for operand in codes.iter().flat_map(ByteCode::operands) {
+            match operand {
+                Operand::Int(_) => {}
+                Operand::Constant(value) => {
+                    if !pool.constant_index.contains_key(&value.to_literal()) {
+                        pool.constant_index
+                            .insert(value.to_literal(), pool.constants.len());
+                        pool.constants.push(value.clone());
+                    }
+                }
+                Operand::Name(name) | Operand::Section(name) => {
+                    if !pool.name_index.contains_key(name) {
+                        pool.name_index.insert(name.clone(), pool.names.len());
+                        pool.names.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        pool
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn len(&mut self, len: usize) {
+        self.bytes.extend((len as u32).to_le_bytes());
+    }
+
+    fn string(&mut self, string: &str) {
+        self.len(string.len());
+        self.bytes.extend(string.as_bytes());
+    }
+
+    fn depth(&mut self, depth: Option<usize>) {
+        match depth {
+            Some(depth) => self.len(depth),
+            None => self.bytes.extend(UNKNOWN_DEPTH.to_le_bytes()),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], ObjectError> {
+        let end = self
+            .pos
+            .checked_add(count)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(ObjectError::UnexpectedEnd(self.pos))?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn len(&mut self) -> Result<usize, ObjectError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+
+    fn string(&mut self) -> Result<String, ObjectError> {
+        let pos = self.pos;
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| ObjectError::InvalidString(pos))
+    }
+
+    fn operand(&mut self, constants: &[Value], names: &[String]) -> Result<Operand, ObjectError> {
+        let pos = self.pos;
+        let tag = self.take(1)?[0];
+        if tag == OPERAND_INT {
+            let int = i128::from_le_bytes(self.take(16)?.try_into().unwrap());
+            return Ok(Operand::Int(int));
+        }
+
+        let index = self.len()?;
+        let name = || {
+            names
+                .get(index)
+                .cloned()
+                .ok_or(ObjectError::InvalidIndex(index, pos + 1))
+        };
+        match tag {
+            OPERAND_CONSTANT => constants
+                .get(index)
+                .cloned()
+                .map(Operand::Constant)
+                .ok_or(ObjectError::InvalidIndex(index, pos + 1)),
+            OPERAND_NAME => name().map(Operand::Name),
+            OPERAND_SECTION => name().map(Operand::Section),
+            _ => Err(ObjectError::InvalidTag(tag, pos)),
+        }
+    }
+
+    fn depth(&mut self) -> Result<Option<usize>, ObjectError> {
+        let depth = self.len()?;
+        Ok((depth != UNKNOWN_DEPTH as usize).then_some(depth))
+    }
+}
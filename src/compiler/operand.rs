@@ -0,0 +1,297 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use super::value::Value;
+use super::{ByteCode, OpCode};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum OperandError {
+    #[error("{0:?} expects {1}, found {2} operands")]
+    WrongArity(OpCode, String, usize),
+    #[error("operand `{1}` of {0:?} must be {2}")]
+    WrongKind(OpCode, String, String),
+}
+
+/// 指令的参数
+///
+/// 参数自身带有类型，虚拟机执行时无需再解析文本。写入目标文件时，非整数常量存放在常量池中，
+/// 名字与 Section 的名字存放在名字表中，指令以下标引用它们；整数常量作为立即数直接编码在指令中。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    /// 整数常量，作为立即数编码
+    Int(i128),
+    /// 浮点数、布尔值或字符串常量，通过常量池引用
+    Constant(Value),
+    /// 堆中的名字，通过名字表引用
+    Name(String),
+    /// Section 的名字，通过名字表引用
+    Section(String),
+}
+
+/// 指令参数的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperandKind {
+    /// 常量的字面量，格式与 `Value::from_literal` 一致
+    Constant,
+    /// 堆中的名字
+    Name,
+    /// Section 的名字
+    Section,
+}
+
+/// 指令接受的参数个数与种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    /// 没有参数
+    None,
+    /// 恰好一个参数
+    One(OperandKind),
+    /// 恰好两个参数
+    Two(OperandKind),
+    /// 至少一个参数
+    Many(OperandKind),
+}
+
+impl Arity {
+    /// 返回参数的种类，没有参数时返回 `None`
+    pub fn kind(&self) -> Option<OperandKind> {
+        match self {
+            Arity::None => None,
+            Arity::One(kind) | Arity::Two(kind) | Arity::Many(kind) => Some(*kind),
+        }
+    }
+
+    /// 判断参数个数是否符合要求
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Arity::None => count == 0,
+            Arity::One(_) => count == 1,
+            Arity::Two(_) => count == 2,
+            Arity::Many(_) => count >= 1,
+        }
+    }
+
+    /// 返回参数要求的描述，例如 `at least one constant`
+    pub fn describe(&self) -> String {
+        match self {
+            Arity::None => "no operands".to_string(),
+            Arity::One(kind) => format!("one {}", kind.describe()),
+            Arity::Two(kind) => format!("two {}s", kind.describe()),
+            Arity::Many(kind) => format!("at least one {}", kind.describe()),
+        }
+    }
+}
+
+impl OperandKind {
+    /// 返回参数种类的描述
+    pub fn describe(&self) -> &'static str {
+        match self {
+            OperandKind::Constant => "constant",
+            OperandKind::Name => "name",
+            OperandKind::Section => "section label",
+        }
+    }
+}
+
+impl Operand {
+    /// 由常量的值构造参数，整数常量成为立即数
+    pub fn constant(value: Value) -> Self {
+        match value {
+            Value::Int(int) => Operand::Int(int),
+            value => Operand::Constant(value),
+        }
+    }
+
+    /// 将参数的文本形式解析为 `kind` 种类的参数，常量的字面量无法识别时返回 `None`
+    pub fn parse(kind: OperandKind, text: &str) -> Option<Self> {
+        match kind {
+            OperandKind::Constant => Value::from_literal(text).map(Operand::constant),
+            OperandKind::Name => Some(Operand::Name(text.to_string())),
+            OperandKind::Section => Some(Operand::Section(text.to_string())),
+        }
+    }
+
+    /// 返回参数的种类
+    pub fn kind(&self) -> OperandKind {
+        match self {
+            Operand::Int(_) | Operand::Constant(_) => OperandKind::Constant,
+            Operand::Name(_) => OperandKind::Name,
+            Operand::Section(_) => OperandKind::Section,
+        }
+    }
+
+    /// 返回常量参数的值，其它参数返回 `None`
+    pub fn value(&self) -> Option<Value> {
+        match self {
+            Operand::Int(int) => Some(Value::Int(*int)),
+            Operand::Constant(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    /// 返回名字或 Section 的名字，常量返回 `None`
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Operand::Name(name) | Operand::Section(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+/// 参数的文本形式：常量为字面量，名字与 Section 为名字本身
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Int(int) => write!(f, "{}", int),
+            Operand::Constant(value) => write!(f, "{}", value.to_literal()),
+            Operand::Name(name) | Operand::Section(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl ByteCode {
+    /// 创建一条指令，检查参数的个数与种类是否符合操作码的要求
+    pub fn new(op: OpCode, operands: Vec<Operand>) -> Result<Self, OperandError> {
+        let arity = op.arity();
+        if !arity.accepts(operands.len()) {
+            return Err(OperandError::WrongArity(
+                op,
+                arity.describe(),
+                operands.len(),
+            ));
+        }
+
+        let mut operands = operands;
+        for operand in &mut operands {
+            // 有参数时操作码一定接受参数
+            let kind = arity.kind().unwrap();
+            if operand.kind() != kind {
+                return Err(OperandError::WrongKind(
+                    op,
+                    operand.to_string(),
+                    kind.describe().to_string(),
+                ));
+            }
+            if let Operand::Constant(Value::Int(int)) = operand {
+                *operand = Operand::Int(*int);
+            }
+        }
+
+        Ok(Self { op, operands })
+    }
+
+    /// 由参数的文本形式创建一条指令，参数按操作码要求的种类解析
+    pub fn from_args(op: OpCode, args: &[&str]) -> Result<Self, OperandError> {
+        let arity = op.arity();
+        if !arity.accepts(args.len()) {
+            return Err(OperandError::WrongArity(op, arity.describe(), args.len()));
+        }
+
+        let operands = match arity.kind() {
+            Some(kind) => args
+                .iter()
+                .map(|arg| {
+                    Operand::parse(kind, arg).ok_or_else(|| {
+                        OperandError::WrongKind(
+                            op.clone(),
+                            arg.to_string(),
+                            kind.describe().to_string(),
+                        )
+                    })
+                })
+                .collect::<Result<Vec<Operand>, OperandError>>()?,
+            None => vec![],
+        };
+
+        ByteCode::new(op, operands)
+    }
+
+    /// 返回指令的参数
+    pub fn operands(&self) -> &[Operand] {
+        &self.operands
+    }
+
+    /// 返回参数的文本形式
+    pub fn args(&self) -> Vec<String> {
+        self.operands.iter().map(Operand::to_string).collect()
+    }
+
+    /// 返回 `LoadName` 与 `StoreName` 的名字，或跳转与 `MakeSection` 的 Section 的名字
+    pub fn name(&self) -> Option<&str> {
+        match self.operands.as_slice() {
+            [operand] => operand.name(),
+            _ => None,
+        }
+    }
+
+    /// 返回跳转指令的目标 Section 的名字，其他指令返回空列表
+    pub fn targets(&self) -> Vec<&str> {
+        match self.op {
+            OpCode::Jump | OpCode::JumpIf | OpCode::JumpIfElse => {
+                self.operands.iter().filter_map(Operand::name).collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl OpCode {
+    /// 所有的操作码，按声明的顺序排列，下标即为序列化时的编号
+    pub const ALL: &'static [OpCode] = &[
+        OpCode::Push,
+        OpCode::Pop,
+        OpCode::Dup,
+        OpCode::Add,
+        OpCode::Sub,
+        OpCode::Mul,
+        OpCode::Div,
+        OpCode::Mod,
+        OpCode::Neg,
+        OpCode::Eq,
+        OpCode::Neq,
+        OpCode::Gt,
+        OpCode::Lt,
+        OpCode::Gte,
+        OpCode::Lte,
+        OpCode::Jump,
+        OpCode::JumpIf,
+        OpCode::JumpIfElse,
+        OpCode::LoadName,
+        OpCode::StoreName,
+        OpCode::MakeSection,
+        OpCode::EndMakeSection,
+        OpCode::Return,
+    ];
+
+    /// 返回操作码接受的参数
+    pub fn arity(&self) -> Arity {
+        match self {
+            OpCode::Push => Arity::Many(OperandKind::Constant),
+            OpCode::LoadName | OpCode::StoreName => Arity::One(OperandKind::Name),
+            OpCode::Jump | OpCode::JumpIf | OpCode::MakeSection => Arity::One(OperandKind::Section),
+            OpCode::JumpIfElse => Arity::Two(OperandKind::Section),
+            _ => Arity::None,
+        }
+    }
+
+    /// 返回操作码的名字，与 `{:?}` 的输出相同
+    pub fn name(&self) -> String {
+        format!("{:?}", self)
+    }
+
+    /// 由名字得到操作码
+    pub fn from_name(name: &str) -> Option<OpCode> {
+        OpCode::ALL.iter().find(|op| op.name() == name).cloned()
+    }
+
+    /// 返回操作码的编号
+    pub fn code(&self) -> u8 {
+        OpCode::ALL.iter().position(|op| op == self).unwrap() as u8
+    }
+
+    /// 由编号得到操作码
+    pub fn from_code(code: u8) -> Option<OpCode> {
+        OpCode::ALL.get(code as usize).cloned()
+    }
+}
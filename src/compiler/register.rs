@@ -0,0 +1,128 @@
+//! 基于寄存器的三地址指令集
+//!
+//! 与栈式的 `OpCode` 不同，每条指令直接指明读取与写入的寄存器。寄存器的数量是有限的，
+//! 放不下的值被溢出到溢出槽中，使用前再重新载入寄存器。跳转的目标是指令的下标。
+
+use std::fmt;
+
+use crate::parser::BinaryOp;
+
+use super::value::Value;
+
+/// 默认的寄存器数量
+pub const DEFAULT_REGISTERS: usize = 16;
+
+/// 寄存器，在输出中显示为 `rn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Reg(pub usize);
+
+/// 溢出槽，在输出中显示为 `sn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Slot(pub usize);
+
+/// 寄存器指令
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegisterInstr {
+    /// `rd = const value`，将常量写入寄存器
+    Const(Reg, Value),
+    /// `rd = load name`，从堆中读取变量
+    Load(Reg, String),
+    /// `store name, rs`，将寄存器的值存入堆中
+    Store(String, Reg),
+    /// `rd = rs`，复制寄存器的值
+    Move(Reg, Reg),
+    /// `rd = op ra, rb`，二元运算，先读取两个源寄存器再写入目标寄存器
+    Binary(Reg, BinaryOp, Reg, Reg),
+    /// `spill sn, rs`，将寄存器的值保存到溢出槽中
+    Spill(Slot, Reg),
+    /// `rd = reload sn`，将溢出槽中的值载入寄存器
+    Reload(Reg, Slot),
+    /// `yield rs`，将寄存器的值作为程序的结果输出，与栈式字节码留在操作数栈上的值对应
+    Yield(Reg),
+    /// `jump target`，跳转到下标为 `target` 的指令
+    Jump(usize),
+    /// `jumpifnot rs, target`，寄存器的值为 false 时跳转到下标为 `target` 的指令
+    JumpIfNot(Reg, usize),
+    /// `halt`，结束程序
+    Halt,
+}
+
+/// 寄存器指令组成的程序
+#[derive(Debug, Clone, PartialEq)]
+pub struct RegisterProgram {
+    pub code: Vec<RegisterInstr>,
+    /// 使用的寄存器数量
+    pub registers: usize,
+    /// 使用的溢出槽数量
+    pub slots: usize,
+}
+
+impl RegisterInstr {
+    /// 返回指令写入的寄存器
+    pub fn def(&self) -> Option<Reg> {
+        match self {
+            RegisterInstr::Const(reg, _)
+            | RegisterInstr::Load(reg, _)
+            | RegisterInstr::Move(reg, _)
+            | RegisterInstr::Binary(reg, _, _, _)
+            | RegisterInstr::Reload(reg, _) => Some(*reg),
+            _ => None,
+        }
+    }
+
+    /// 返回指令读取的寄存器，按操作数顺序排列
+    pub fn uses(&self) -> Vec<Reg> {
+        match self {
+            RegisterInstr::Binary(_, _, lhs, rhs) => vec![*lhs, *rhs],
+            RegisterInstr::Store(_, reg)
+            | RegisterInstr::Move(_, reg)
+            | RegisterInstr::Spill(_, reg)
+            | RegisterInstr::Yield(reg)
+            | RegisterInstr::JumpIfNot(reg, _) => vec![*reg],
+            _ => vec![],
+        }
+    }
+}
+
+impl fmt::Display for Reg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "r{}", self.0)
+    }
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s{}", self.0)
+    }
+}
+
+impl fmt::Display for RegisterInstr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegisterInstr::Const(reg, value) => write!(f, "{} = const {}", reg, value.to_literal()),
+            RegisterInstr::Load(reg, name) => write!(f, "{} = load {}", reg, name),
+            RegisterInstr::Store(name, reg) => write!(f, "store {}, {}", name, reg),
+            RegisterInstr::Move(reg, source) => write!(f, "{} = {}", reg, source),
+            RegisterInstr::Binary(reg, op, lhs, rhs) => {
+                write!(f, "{} = {} {}, {}", reg, op.as_raw(), lhs, rhs)
+            }
+            RegisterInstr::Spill(slot, reg) => write!(f, "spill {}, {}", slot, reg),
+            RegisterInstr::Reload(reg, slot) => write!(f, "{} = reload {}", reg, slot),
+            RegisterInstr::Yield(reg) => write!(f, "yield {}", reg),
+            RegisterInstr::Jump(target) => write!(f, "jump {:04}", target),
+            RegisterInstr::JumpIfNot(reg, target) => write!(f, "jumpifnot {}, {:04}", reg, target),
+            RegisterInstr::Halt => write!(f, "halt"),
+        }
+    }
+}
+
+impl fmt::Display for RegisterProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "; registers: {}, slots: {}", self.registers, self.slots)?;
+        for (index, instr) in self.code.iter().enumerate() {
+            writeln!(f, "{:04}: {}", index, instr)?;
+        }
+
+        Ok(())
+    }
+}
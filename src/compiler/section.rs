@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::{ByteCode, OpCode};
+
+/// Section 名字的位数
+const NAME_DIGITS: usize = 8;
+
+/// Section 名字生成器
+///
+/// 同一个生成器生成的名字在整个编译单元内唯一。默认按计数器顺序生成 `00000000`、`00000001`……，
+/// 因此同一份代码的编译结果总是相同的；也可以通过 `seeded` 使用一个种子生成伪随机的名字，用于模糊测试。
+#[derive(Debug)]
+pub struct SectionNamer {
+    counter: usize,
+    rng: Option<StdRng>,
+    used: HashSet<String>,
+}
+
+impl SectionNamer {
+    /// 创建一个按计数器顺序生成名字的生成器
+    pub fn new() -> Self {
+        Self {
+            counter: 0,
+            rng: None,
+            used: HashSet::new(),
+        }
+    }
+
+    /// 创建一个使用种子生成伪随机名字的生成器，相同的种子总是生成相同的名字序列
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            counter: 0,
+            rng: Some(StdRng::seed_from_u64(seed)),
+            used: HashSet::new(),
+        }
+    }
+
+    /// 生成下一个名字
+    pub fn next_name(&mut self) -> String {
+        loop {
+            let name = match self.rng.as_mut() {
+                Some(rng) => (0..NAME_DIGITS)
+                    .map(|_| char::from(b'0' + rng.gen_range(0..10u8)))
+                    .collect(),
+                None => {
+                    self.counter += 1;
+                    format!("{:0width$}", self.counter - 1, width = NAME_DIGITS)
+                }
+            };
+
+            if self.used.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+}
+
+impl Default for SectionNamer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 字节码中的一个 Section 定义
+///
+/// `start` 为 `MakeSection` 指令的下标，`end` 为与之匹配的 `EndMakeSection` 指令的下标。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub start: usize,
+    pub end: usize,
+    /// 嵌套深度，最外层的 Section 为 0
+    pub depth: usize,
+}
+
+impl Section {
+    /// 返回 Section 的直接指令的下标，不包含嵌套 Section 的定义
+    pub fn body(&self, codes: &[ByteCode]) -> Vec<usize> {
+        top_level_indices(codes, self.start + 1, self.end)
+    }
+}
+
+/// 找出字节码中所有的 Section 定义，按 `MakeSection` 出现的顺序排列
+///
+/// 不匹配的 `MakeSection` 或 `EndMakeSection` 会被忽略。
+pub fn find_sections(codes: &[ByteCode]) -> Vec<Section> {
+    let mut sections: Vec<Section> = vec![];
+    let mut open: Vec<usize> = vec![];
+
+    for (index, code) in codes.iter().enumerate() {
+        match code.op {
+            OpCode::MakeSection => {
+                open.push(sections.len());
+                sections.push(Section {
+                    name: code.name().unwrap_or_default().to_string(),
+                    start: index,
+                    end: usize::MAX,
+                    depth: open.len() - 1,
+                });
+            }
+            OpCode::EndMakeSection => {
+                if let Some(section) = open.pop() {
+                    sections[section].end = index;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    sections.retain(|section| section.end != usize::MAX);
+    sections
+}
+
+/// 返回 `codes[start..end]` 中不属于任何嵌套 Section 定义的指令下标
+pub fn top_level_indices(codes: &[ByteCode], start: usize, end: usize) -> Vec<usize> {
+    let mut indices: Vec<usize> = vec![];
+    let mut depth = 0usize;
+
+    for (index, code) in codes.iter().enumerate().take(end).skip(start) {
+        match code.op {
+            OpCode::MakeSection => depth += 1,
+            OpCode::EndMakeSection => depth = depth.saturating_sub(1),
+            _ if depth == 0 => indices.push(index),
+            _ => {}
+        }
+    }
+
+    indices
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+
+use thiserror::Error;
+
+use crate::parser::BinaryOp;
+
+/// 表示运行时的值
+///
+/// 常量折叠与虚拟机共用这里定义的运算语义，确保编译期求值的结果与运行期一致。
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// 整数
+    Int(i128),
+    /// 浮点数
+    Float(f64),
+    /// 布尔值
+    Bool(bool),
+    /// 字符串，不包含两侧的引号
+    Str(String),
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ValueError {
+    #[error("Division by zero: {0}")]
+    DivisionByZero(String),
+    #[error("Integer overflow: {0}")]
+    Overflow(String),
+    #[error("Type error: {0}")]
+    TypeError(String),
+}
+
+impl Value {
+    /// 从常量节点的字面量解析出值
+    ///
+    /// 字面量的格式与 `grammar.pest` 中的 `constant` 规则一致，无法识别时返回 `None`。
+    pub fn from_literal(literal: &str) -> Option<Value> {
+        if literal.len() >= 2 && literal.starts_with('"') && literal.ends_with('"') {
+            return Some(Value::Str(literal[1..literal.len() - 1].to_string()));
+        }
+
+        match literal {
+            "true" => return Some(Value::Bool(true)),
+            "false" => return Some(Value::Bool(false)),
+            _ => {}
+        }
+
+        if let Ok(int) = literal.parse::<i128>() {
+            return Some(Value::Int(int));
+        }
+
+        literal.parse::<f64>().ok().map(Value::Float)
+    }
+
+    /// 返回值的字面量表示，可以再被 `from_literal` 解析回相同的值
+    pub fn to_literal(&self) -> String {
+        match self {
+            Value::Int(int) => int.to_string(),
+            Value::Float(float) => format!("{:?}", float),
+            Value::Bool(boolean) => boolean.to_string(),
+            Value::Str(string) => format!("\"{}\"", string),
+        }
+    }
+
+    /// 返回值的类型名
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "str",
+        }
+    }
+
+    /// 对两个值进行二元运算
+    ///
+    /// 整数之间的运算保持为整数，除法向零取整，取模结果的符号与被除数相同；
+    /// 整数与浮点数混合运算时整数会被提升为浮点数；字符串支持拼接与比较；
+    /// 布尔值只支持相等性比较。除数为零时返回 `ValueError::DivisionByZero`。
+    pub fn binary(&self, op: &BinaryOp, rhs: &Value) -> Result<Value, ValueError> {
+        let describe = || format!("{} {} {}", self.to_literal(), op.as_raw(), rhs.to_literal());
+
+        match op {
+            BinaryOp::Eq => return Ok(Value::Bool(self.equals(rhs))),
+            BinaryOp::Neq => return Ok(Value::Bool(!self.equals(rhs))),
+            _ => {}
+        }
+
+        match (self, rhs) {
+            (Value::Int(a), Value::Int(b)) => {
+                let (a, b) = (*a, *b);
+                let result = match op {
+                    BinaryOp::Add => a.checked_add(b),
+                    BinaryOp::Sub => a.checked_sub(b),
+                    BinaryOp::Mul => a.checked_mul(b),
+                    BinaryOp::Div | BinaryOp::Mod if b == 0 => {
+                        return Err(ValueError::DivisionByZero(describe()))
+                    }
+                    BinaryOp::Div => a.checked_div(b),
+                    BinaryOp::Mod => a.checked_rem(b),
+                    BinaryOp::Gt => return Ok(Value::Bool(a > b)),
+                    BinaryOp::Gte => return Ok(Value::Bool(a >= b)),
+                    BinaryOp::Lt => return Ok(Value::Bool(a < b)),
+                    BinaryOp::Lte => return Ok(Value::Bool(a <= b)),
+                    BinaryOp::Eq | BinaryOp::Neq => unreachable!(),
+                };
+
+                result
+                    .map(Value::Int)
+                    .ok_or_else(|| ValueError::Overflow(describe()))
+            }
+            (Value::Int(_) | Value::Float(_), Value::Int(_) | Value::Float(_)) => {
+                let (a, b) = (self.as_float(), rhs.as_float());
+                match op {
+                    BinaryOp::Add => Ok(Value::Float(a + b)),
+                    BinaryOp::Sub => Ok(Value::Float(a - b)),
+                    BinaryOp::Mul => Ok(Value::Float(a * b)),
+                    BinaryOp::Div | BinaryOp::Mod if b == 0.0 => {
+                        Err(ValueError::DivisionByZero(describe()))
+                    }
+                    BinaryOp::Div => Ok(Value::Float(a / b)),
+                    BinaryOp::Mod => Ok(Value::Float(a % b)),
+                    BinaryOp::Gt => Ok(Value::Bool(a > b)),
+                    BinaryOp::Gte => Ok(Value::Bool(a >= b)),
+                    BinaryOp::Lt => Ok(Value::Bool(a < b)),
+                    BinaryOp::Lte => Ok(Value::Bool(a <= b)),
+                    BinaryOp::Eq | BinaryOp::Neq => unreachable!(),
+                }
+            }
+            (Value::Str(a), Value::Str(b)) => match op {
+                BinaryOp::Add => Ok(Value::Str(format!("{}{}", a, b))),
+                BinaryOp::Gt => Ok(Value::Bool(a > b)),
+                BinaryOp::Gte => Ok(Value::Bool(a >= b)),
+                BinaryOp::Lt => Ok(Value::Bool(a < b)),
+                BinaryOp::Lte => Ok(Value::Bool(a <= b)),
+                _ => Err(ValueError::TypeError(describe())),
+            },
+            _ => Err(ValueError::TypeError(describe())),
+        }
+    }
+
+    /// 对值取反，数字取相反数，布尔值取逻辑非
+    pub fn neg(&self) -> Result<Value, ValueError> {
+        match self {
+            Value::Int(int) => int
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| ValueError::Overflow(format!("-{}", int))),
+            Value::Float(float) => Ok(Value::Float(-float)),
+            Value::Bool(boolean) => Ok(Value::Bool(!boolean)),
+            Value::Str(_) => Err(ValueError::TypeError(format!("-{}", self.to_literal()))),
+        }
+    }
+
+    /// 比较两个值是否相等，整数与浮点数按数值比较，不同类型的值总是不相等
+    fn equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) => {
+                self.as_float() == other.as_float()
+            }
+            _ => self == other,
+        }
+    }
+
+    fn as_float(&self) -> f64 {
+        match self {
+            Value::Int(int) => *int as f64,
+            Value::Float(float) => *float,
+            _ => f64::NAN,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_literal())
+    }
+}
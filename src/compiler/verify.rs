@@ -0,0 +1,301 @@
+//! 字节码校验器
+//!
+//! 参数的个数与种类在创建指令时已由 `ByteCode::new` 检查。校验通过的字节码还满足以下条件，
+//! 虚拟机执行时无需再做相应的检查：
+//!
+//! - `MakeSection` 与 `EndMakeSection` 一一匹配，Section 的名字互不相同；
+//! - 跳转的每个目标 Section 都在跳转之前、在同一层或外层的代码中定义，执行跳转时一定已被创建；
+//! - `Return` 只出现在 Section 中；
+//! - 在任何执行路径上，每条指令执行前栈中都有足够的值。Section 中的指令只能使用 Section
+//!   自己压入栈中的值，不能取出调用者的值。
+//!
+//! 栈深度的检查同时得出程序与每个 Section 使用的最大栈深度，虚拟机可以据此预先分配栈。
+
+use std::collections::{HashMap, HashSet};
+
+use thiserror::Error;
+
+use super::section::top_level_indices;
+use super::{ByteCode, OpCode};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    #[error("instruction {0:04}: EndMakeSection without a matching MakeSection")]
+    UnmatchedEnd(usize),
+    #[error("instruction {0:04}: section @{1} is never closed")]
+    UnclosedSection(usize, String),
+    #[error("instruction {0:04}: section @{1} is already defined")]
+    DuplicateSection(usize, String),
+    #[error("instruction {0:04}: section @{1} is not defined before the jump")]
+    UndefinedSection(usize, String),
+    #[error("instruction {0:04}: Return outside of a section")]
+    ReturnOutsideSection(usize),
+    #[error("instruction {0:04}: stack underflow at {1:?}")]
+    StackUnderflow(usize, OpCode),
+}
+
+impl VerifyError {
+    /// 返回出错的指令的下标
+    pub fn index(&self) -> usize {
+        match self {
+            VerifyError::UnmatchedEnd(index)
+            | VerifyError::UnclosedSection(index, _)
+            | VerifyError::DuplicateSection(index, _)
+            | VerifyError::UndefinedSection(index, _)
+            | VerifyError::ReturnOutsideSection(index)
+            | VerifyError::StackUnderflow(index, _) => *index,
+        }
+    }
+}
+
+/// 校验字节码
+///
+/// # 返回值
+///
+/// 字节码合法时返回 `Ok(())`，否则返回所有的错误，按指令的下标排列。
+pub fn verify(codes: &[ByteCode]) -> Result<(), Vec<VerifyError>> {
+    stack_usage(codes).map(|_| ())
+}
+
+/// 操作数栈的最大深度
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StackUsage {
+    /// 执行整个程序时栈的最大深度；调用了可能无限递归的 Section 时为 `None`
+    pub program: Option<usize>,
+    /// 以名字为键，每个 Section 被调用时相对于调用时栈顶的最大深度，包含其中调用的 Section
+    pub sections: HashMap<String, Option<usize>>,
+}
+
+/// 校验字节码，并通过抽象解释计算操作数栈的最大深度
+///
+/// 跳转时被调用的 Section 从调用者当前的栈顶开始使用栈，因此调用处的深度加上 Section 的最大深度
+/// 计入调用者的最大深度。
+pub fn stack_usage(codes: &[ByteCode]) -> Result<StackUsage, Vec<VerifyError>> {
+    let mut verifier = Verifier {
+        codes,
+        errors: vec![],
+        sections: vec![],
+        targets: HashMap::new(),
+        frames: vec![],
+    };
+
+    verifier.check_structure();
+    let usage = verifier.check_stack();
+
+    let mut errors = verifier.errors;
+    match errors.is_empty() {
+        true => Ok(usage),
+        false => {
+            errors.sort_by_key(VerifyError::index);
+            Err(errors)
+        }
+    }
+}
+
+/// Section 的分析状态
+#[derive(Debug, Clone, Copy)]
+enum Call {
+    /// 正在分析，递归调用的结果未知
+    Pending,
+    Done(Frame),
+}
+
+/// 一段代码的分析结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Frame {
+    /// 执行 `Return` 时压入调用者栈中的值的个数的范围
+    result: Depth,
+    /// 栈的最大深度，可能无限递归时为 `None`
+    max_stack: Option<usize>,
+}
+
+/// 栈深度在所有执行路径上的范围
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Depth {
+    min: usize,
+    max: usize,
+}
+
+impl Depth {
+    fn exact(depth: usize) -> Self {
+        Self {
+            min: depth,
+            max: depth,
+        }
+    }
+}
+
+struct Verifier<'a> {
+    codes: &'a [ByteCode],
+    errors: Vec<VerifyError>,
+    /// Section 的 `MakeSection` 与 `EndMakeSection` 的下标，未闭合的 Section 结束于字节码的末尾
+    sections: Vec<(usize, usize)>,
+    /// 跳转指令的下标到目标 Section 的下标，按参数的顺序排列
+    targets: HashMap<usize, Vec<usize>>,
+    frames: Vec<Option<Call>>,
+}
+
+impl Verifier<'_> {
+    /// 检查参数、Section 的嵌套、跳转的目标以及 `Return` 的位置
+    fn check_structure(&mut self) {
+        let mut open: Vec<usize> = vec![];
+        let mut names: HashSet<&str> = HashSet::new();
+        // 每一层代码中已经定义的 Section，最外层在前
+        let mut scopes: Vec<Vec<(&str, usize)>> = vec![vec![]];
+
+        for (index, code) in self.codes.iter().enumerate() {
+            let name = code.name().unwrap_or_default();
+
+            match code.op {
+                OpCode::MakeSection => {
+                    if !names.insert(name) {
+                        self.errors
+                            .push(VerifyError::DuplicateSection(index, name.to_string()));
+                    }
+                    scopes.last_mut().unwrap().push((name, self.sections.len()));
+                    scopes.push(vec![]);
+                    open.push(self.sections.len());
+                    self.sections.push((index, self.codes.len()));
+                }
+                OpCode::EndMakeSection => match open.pop() {
+                    Some(section) => {
+                        scopes.pop();
+                        self.sections[section].1 = index;
+                    }
+                    None => self.errors.push(VerifyError::UnmatchedEnd(index)),
+                },
+                OpCode::Jump | OpCode::JumpIf | OpCode::JumpIfElse => {
+                    for name in code.targets() {
+                        let target = scopes
+                            .iter()
+                            .rev()
+                            .flat_map(|scope| scope.iter().rev())
+                            .find(|(defined, _)| *defined == name);
+                        match target {
+                            Some((_, section)) => {
+                                self.targets.entry(index).or_default().push(*section);
+                            }
+                            None => self
+                                .errors
+                                .push(VerifyError::UndefinedSection(index, name.to_string())),
+                        }
+                    }
+                }
+                OpCode::Return if open.is_empty() => {
+                    self.errors.push(VerifyError::ReturnOutsideSection(index));
+                }
+                _ => {}
+            }
+        }
+
+        for section in open {
+            let (start, _) = self.sections[section];
+            let name = self.codes[start].name().unwrap_or_default();
+            self.errors
+                .push(VerifyError::UnclosedSection(start, name.to_string()));
+        }
+    }
+
+    /// 对最外层的代码与每个 Section 进行抽象解释，检查栈深度
+    fn check_stack(&mut self) -> StackUsage {
+        self.frames = vec![None; self.sections.len()];
+
+        let program = self.run(&top_level_indices(self.codes, 0, self.codes.len()));
+        let sections = (0..self.sections.len())
+            .map(|section| {
+                let name = self.codes[self.sections[section].0].name();
+                let name = name.unwrap_or_default().to_string();
+                (name, self.frame(section).max_stack)
+            })
+            .collect();
+
+        StackUsage {
+            program: program.max_stack,
+            sections,
+        }
+    }
+
+    /// 分析一个 Section，每个 Section 只分析一次
+    fn frame(&mut self, section: usize) -> Frame {
+        match self.frames[section] {
+            Some(Call::Done(frame)) => return frame,
+            // 递归调用：结果可能是任意的，栈的深度没有上限
+            Some(Call::Pending) => {
+                return Frame {
+                    result: Depth { min: 0, max: 1 },
+                    max_stack: None,
+                }
+            }
+            None => {}
+        }
+
+        self.frames[section] = Some(Call::Pending);
+        let (start, end) = self.sections[section];
+        let frame = self.run(&top_level_indices(self.codes, start + 1, end));
+        self.frames[section] = Some(Call::Done(frame));
+        frame
+    }
+
+    /// 从空栈开始执行 `indices` 中的指令
+    fn run(&mut self, indices: &[usize]) -> Frame {
+        let mut depth = Depth::exact(0);
+        let mut max_stack = Some(0);
+
+        for &index in indices {
+            let code = &self.codes[index];
+            let (pops, pushes) = match code.op {
+                OpCode::Push => (0, code.operands().len()),
+                OpCode::Pop | OpCode::StoreName | OpCode::JumpIf | OpCode::JumpIfElse => (1, 0),
+                OpCode::Dup => (1, 2),
+                OpCode::Neg => (1, 1),
+                OpCode::LoadName => (0, 1),
+                OpCode::Jump | OpCode::MakeSection | OpCode::EndMakeSection => (0, 0),
+                OpCode::Return => {
+                    let result = Depth {
+                        min: depth.min.min(1),
+                        max: depth.max.min(1),
+                    };
+                    return Frame { result, max_stack };
+                }
+                _ => (2, 1),
+            };
+
+            if depth.min < pops {
+                self.errors
+                    .push(VerifyError::StackUnderflow(index, code.op.clone()));
+                depth.min = pops;
+                depth.max = depth.max.max(pops);
+            }
+            depth.min = depth.min - pops + pushes;
+            depth.max = depth.max - pops + pushes;
+
+            if let Some(targets) = self.targets.get(&index).cloned() {
+                let frames: Vec<Frame> = targets.iter().map(|&target| self.frame(target)).collect();
+                for frame in &frames {
+                    max_stack = max_stack
+                        .zip(frame.max_stack)
+                        .map(|(max, callee)| max.max(depth.max + callee));
+                }
+                // 条件跳转可能不进入 Section，`JumpIfElse` 进入两个 Section 之一
+                if code.op != OpCode::JumpIf {
+                    depth.min += frames
+                        .iter()
+                        .map(|frame| frame.result.min)
+                        .min()
+                        .unwrap_or(0);
+                }
+                depth.max += frames
+                    .iter()
+                    .map(|frame| frame.result.max)
+                    .max()
+                    .unwrap_or(0);
+            }
+            max_stack = max_stack.map(|max| max.max(depth.max));
+        }
+
+        Frame {
+            result: Depth::exact(0),
+            max_stack,
+        }
+    }
+}
@@ -0,0 +1,497 @@
+//! 将抽象语法树编译为 WebAssembly 文本格式（WAT）
+//!
+//! 生成的模块导出一个没有参数与返回值的 `main` 函数，程序顶层的表达式语句的值通过导入的
+//! `hare.yield_int`、`hare.yield_float` 与 `hare.yield_bool` 交给宿主，与栈式字节码留在操作数栈上的值对应。
+//!
+//! Wasm 是静态类型的，因此每个变量对应 `main` 中的一个局部变量，类型由第一次赋值决定，之后不能改变：
+//!
+//! - `int` 对应 `i64`，溢出时回绕，而不是像虚拟机那样报错；
+//! - `float` 对应 `f64`，除数为零时与整数一样陷入（trap）；
+//! - `bool` 对应 `i32`。
+//!
+//! 局部变量有默认值，所以只在 if 的某个分支中赋值的变量在分支之后总是可读的。
+//! 作为值的块与 if 被生成为带结果类型的 `block` 与 `if`，`rtb` 用 `br` 带着值跳出，结果类型由块中的 `rtb` 决定。
+//! 字符串、类型不同的块值以及无法静态确定类型的运算都不被支持，会产生指明相应语法结构的编译错误。
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::analysis::types::{infer, Type};
+use crate::parser::ast::AstNode;
+use crate::parser::BinaryOp;
+
+use super::value::Value;
+use super::CompilerError;
+
+/// 后端的名字，用于错误信息
+const BACKEND: &str = "wasm";
+
+/// 浮点数除法与取模使用的临时局部变量
+const SCRATCH: [&str; 2] = ["$__lhs", "$__rhs"];
+
+/// 将抽象语法树编译为 WAT 模块
+pub fn compile_wat(ast: &AstNode) -> Result<String, CompilerError> {
+    let mut writer = WatWriter::default();
+
+    match ast {
+        AstNode::Program(nodes) => {
+            for node in nodes {
+                writer.statement(node, true)?;
+            }
+        }
+        node => writer.statement(node, true)?,
+    }
+
+    Ok(writer.finish())
+}
+
+#[derive(Default)]
+struct WatWriter {
+    /// 局部变量的名字与类型，按声明的顺序排列
+    locals: Vec<(String, Type)>,
+    /// 变量名到 `locals` 中的下标
+    indices: HashMap<String, usize>,
+    body: Vec<String>,
+    labels: usize,
+    depth: usize,
+    /// `rtb` 跳转到的块
+    returns: Option<Target>,
+    /// 用到的临时局部变量，只声明这些变量
+    scratch: BTreeSet<&'static str>,
+}
+
+/// `rtb` 跳转到的 Wasm 块或 if
+struct Target {
+    label: String,
+    /// 作为值时 `rtb` 把值带出块，否则值被丢弃
+    value: bool,
+    /// 块中已经生成的 `rtb` 的值的类型
+    ty: Option<Type>,
+}
+
+impl WatWriter {
+    fn finish(self) -> String {
+        let mut output = String::from("(module\n");
+        for (name, ty) in [("int", "i64"), ("float", "f64"), ("bool", "i32")] {
+            output.push_str(&format!(
+                "  (import \"hare\" \"yield_{0}\" (func $yield_{0} (param {1})))\n",
+                name, ty
+            ));
+        }
+
+        output.push_str("  (func $main (export \"main\")\n");
+        for (index, (_, ty)) in self.locals.iter().enumerate() {
+            output.push_str(&format!(
+                "    (local {} {})\n",
+                self.local(index),
+                value_type(*ty)
+            ));
+        }
+        for scratch in &self.scratch {
+            output.push_str(&format!("    (local {} f64)\n", scratch));
+        }
+        for line in &self.body {
+            output.push_str(&format!("    {}\n", line));
+        }
+        output.push_str("  )\n)\n");
+        output
+    }
+
+    /// 返回局部变量在 WAT 中的名字，名字不是 ASCII 标识符时使用下标
+    fn local(&self, index: usize) -> String {
+        let name = &self.locals[index].0;
+        match name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            true => format!("${}", name),
+            false => format!("$__{}", index),
+        }
+    }
+
+    fn emit(&mut self, instr: impl Into<String>) {
+        let instr = instr.into();
+        self.body
+            .push(format!("{}{}", "  ".repeat(self.depth), instr));
+    }
+
+    fn statement(&mut self, node: &AstNode, top_level: bool) -> Result<(), CompilerError> {
+        match node {
+            AstNode::Expr(_, _, _) | AstNode::Identifier(_) | AstNode::Constant(_) => {
+                let ty = self.expr(node)?;
+                match top_level {
+                    true => self.emit(format!("call $yield_{}", ty.name())),
+                    false => self.emit("drop"),
+                }
+            }
+            AstNode::Assign(identifier, _, value) | AstNode::SetValue(identifier, value) => {
+                let name = match identifier.as_ref() {
+                    AstNode::Identifier(name) => name,
+                    node => return Err(CompilerError::ExpectedIdentifier(node.as_code())),
+                };
+
+                let ty = self.expr(value)?;
+                let index = match self.indices.get(name) {
+                    Some(&index) if self.locals[index].1 != ty => {
+                        return Err(unsupported(format!(
+                            "changing the type of variable `{}` from {} to {}",
+                            name, self.locals[index].1, ty
+                        )))
+                    }
+                    Some(&index) => index,
+                    None => {
+                        self.locals.push((name.clone(), ty));
+                        self.indices.insert(name.clone(), self.locals.len() - 1);
+                        self.locals.len() - 1
+                    }
+                };
+                self.emit(format!("local.set {}", self.local(index)));
+            }
+            AstNode::Block(_) => self.block(node)?,
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                self.if_chain(cond, block, elif_nodes, else_node.as_deref(), None)?;
+            }
+            AstNode::ReturnBlock(value) => {
+                let Some(target) = &self.returns else {
+                    return Err(CompilerError::ReturnOutsideBlock(node.as_code()));
+                };
+                let (label, is_value, expected) = (target.label.clone(), target.value, target.ty);
+
+                let ty = self.expr(value)?;
+                match (is_value, expected) {
+                    (false, _) => self.emit("drop"),
+                    (true, Some(expected)) if expected != ty => {
+                        return Err(unsupported(format!(
+                            "block values of types {} and {} in `{}`",
+                            expected,
+                            ty,
+                            node.as_code()
+                        )))
+                    }
+                    (true, _) => self.returns.as_mut().unwrap().ty = Some(ty),
+                }
+                self.emit(format!("br {}", label));
+            }
+            AstNode::Error(code) => return Err(CompilerError::SyntaxErrors(code.clone())),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// 生成块中的语句，直到块中的 `rtb` 为止
+    fn statements(&mut self, nodes: &[AstNode]) -> Result<(), CompilerError> {
+        for node in nodes {
+            self.statement(node, false)?;
+            if matches!(node, AstNode::ReturnBlock(_)) {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 生成语句块，不在作为值的块中、且含有 `rtb` 的块被包在一个 `block` 中，`rtb` 的值被丢弃
+    fn block(&mut self, block: &AstNode) -> Result<(), CompilerError> {
+        let nodes = match block {
+            AstNode::Block(nodes) => nodes,
+            node => return Err(CompilerError::ExpectedBlock(node.as_code())),
+        };
+        if self.returns.is_some() || !block.contains_return() {
+            return self.statements(nodes);
+        }
+
+        let label = self.new_label();
+        self.emit(format!("block {}", label));
+        self.depth += 1;
+        self.returns = Some(Target {
+            label,
+            value: false,
+            ty: None,
+        });
+        let written = self.statements(nodes);
+        self.returns = None;
+        self.depth -= 1;
+        written?;
+
+        self.emit("end");
+        Ok(())
+    }
+
+    /// 生成作为值的块中的语句，块中的 `rtb` 带着值跳出 `target`，返回 `target` 中记录的值的类型
+    fn value_block(&mut self, block: &AstNode, target: Target) -> Result<Target, CompilerError> {
+        let nodes = match block {
+            AstNode::Block(nodes) => nodes,
+            node => return Err(CompilerError::ExpectedBlock(node.as_code())),
+        };
+        if block.block_value().is_none() {
+            return Err(CompilerError::BlockWithoutValue(block.as_code()));
+        }
+
+        let returns = self.returns.replace(target);
+        self.depth += 1;
+        let written = self.statements(nodes);
+        self.depth -= 1;
+        let target = std::mem::replace(&mut self.returns, returns).unwrap();
+        written.map(|()| target)
+    }
+
+    /// 生成 if 语句，elif 分支被生成为嵌套在 else 中的 if
+    ///
+    /// 作为值时每个分支都是作为值的块，分支中的 `rtb` 带着值跳出 `value`，返回记录了值的类型的 `value`。
+    fn if_chain(
+        &mut self,
+        cond: &AstNode,
+        block: &AstNode,
+        elif_nodes: &[AstNode],
+        else_node: Option<&AstNode>,
+        value: Option<Target>,
+    ) -> Result<Option<Target>, CompilerError> {
+        let ty = self.expr(cond)?;
+        if ty != Type::Bool {
+            return Err(unsupported(format!(
+                "{} condition `{}`",
+                ty,
+                cond.as_code()
+            )));
+        }
+
+        self.emit("if");
+        let mut value = self.branch(block, value)?;
+
+        match (elif_nodes.split_first(), else_node) {
+            (Some((AstNode::Elif(cond, block), rest)), _) => {
+                self.emit("else");
+                self.depth += 1;
+                value = self.if_chain(cond, block, rest, else_node, value)?;
+                self.depth -= 1;
+            }
+            (Some((node, _)), _) => return Err(CompilerError::UnexpectedElif(node.as_code())),
+            (None, Some(AstNode::Else(block))) => {
+                self.emit("else");
+                value = self.branch(block, value)?;
+            }
+            (None, Some(node)) => return Err(CompilerError::UnexpectedElse(node.as_code())),
+            (None, None) => {}
+        }
+
+        self.emit("end");
+        Ok(value)
+    }
+
+    /// 生成 if 的一个分支，作为值时返回记录了值的类型的 `value`
+    fn branch(
+        &mut self,
+        block: &AstNode,
+        value: Option<Target>,
+    ) -> Result<Option<Target>, CompilerError> {
+        match value {
+            Some(target) => self.value_block(block, target).map(Some),
+            None => {
+                self.depth += 1;
+                let written = self.statement(block, false);
+                self.depth -= 1;
+                written.map(|()| None)
+            }
+        }
+    }
+
+    /// 生成带结果类型的 `block`，`body` 生成块中的指令，其中的 `rtb` 带着值跳出这个块
+    fn value(
+        &mut self,
+        body: impl FnOnce(&mut Self, Target) -> Result<Target, CompilerError>,
+    ) -> Result<Type, CompilerError> {
+        let label = self.new_label();
+        let header = self.body.len();
+        self.emit(format!("block {}", label));
+
+        let target = Target {
+            label,
+            value: true,
+            ty: None,
+        };
+        // 作为值的块与 if 的每个分支都以 `rtb` 结束，因此值的类型总是已知的
+        let ty = body(self, target)?.ty.unwrap_or(Type::Unknown);
+        self.body[header].push_str(&format!(" (result {})", value_type(ty)));
+        self.emit("end");
+        Ok(ty)
+    }
+
+    fn new_label(&mut self) -> String {
+        self.labels += 1;
+        format!("$__b{}", self.labels - 1)
+    }
+
+    /// 生成表达式，将值留在 Wasm 的栈上，返回值的类型
+    fn expr(&mut self, node: &AstNode) -> Result<Type, CompilerError> {
+        match node {
+            AstNode::Expr(left, Some(op), Some(right)) => {
+                let lhs = self.expr(left)?;
+                let rhs = infer(right, &|name| self.type_of(name));
+                let float = matches!(
+                    (lhs, rhs),
+                    (Type::Float, Type::Int | Type::Float) | (Type::Int, Type::Float)
+                );
+
+                if float && lhs == Type::Int {
+                    self.emit("f64.convert_i64_s");
+                }
+                let rhs = self.expr(right)?;
+                if float && rhs == Type::Int {
+                    self.emit("f64.convert_i64_s");
+                }
+
+                self.binary(node, op, lhs, rhs, float)
+            }
+            AstNode::Expr(left, None, _) => self.expr(left),
+            AstNode::Identifier(name) => match self.indices.get(name) {
+                Some(&index) => {
+                    self.emit(format!("local.get {}", self.local(index)));
+                    Ok(self.locals[index].1)
+                }
+                None => Err(CompilerError::UndefinedVariable(name.clone())),
+            },
+            AstNode::Constant(literal) => match Value::from_literal(literal) {
+                Some(Value::Int(int)) => {
+                    let int = i64::try_from(int).map_err(|_| {
+                        unsupported(format!("integer constant `{}` outside of i64", literal))
+                    })?;
+                    self.emit(format!("i64.const {}", int));
+                    Ok(Type::Int)
+                }
+                Some(Value::Float(float)) => {
+                    self.emit(format!("f64.const {:?}", float));
+                    Ok(Type::Float)
+                }
+                Some(Value::Bool(boolean)) => {
+                    self.emit(format!("i32.const {}", boolean as i32));
+                    Ok(Type::Bool)
+                }
+                Some(Value::Str(_)) => Err(unsupported(format!("string constant `{}`", literal))),
+                None => Err(CompilerError::InvalidConstant(literal.clone())),
+            },
+            AstNode::Block(_) => self.value(|writer, target| writer.value_block(node, target)),
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                node.check_if_value(&|name| self.type_of(name))?;
+                self.value(|writer, target| {
+                    writer.depth += 1;
+                    let value = writer.if_chain(
+                        cond,
+                        block,
+                        elif_nodes,
+                        else_node.as_deref(),
+                        Some(target),
+                    );
+                    // 每个分支都已经用 `br` 跳出块，if 之后不会被执行
+                    writer.emit("unreachable");
+                    writer.depth -= 1;
+                    value.map(Option::unwrap)
+                })
+            }
+            node => Err(unsupported(format!("expression `{}`", node.as_code()))),
+        }
+    }
+
+    /// 生成二元运算，两个操作数已经在栈上，需要时都已被转换为浮点数
+    fn binary(
+        &mut self,
+        node: &AstNode,
+        op: &BinaryOp,
+        lhs: Type,
+        rhs: Type,
+        float: bool,
+    ) -> Result<Type, CompilerError> {
+        let (prefix, signed) = match (lhs, rhs) {
+            _ if float => ("f64", ""),
+            (Type::Int, Type::Int) => ("i64", "_s"),
+            (Type::Bool, Type::Bool) if matches!(op, BinaryOp::Eq | BinaryOp::Neq) => ("i32", ""),
+            _ => {
+                return Err(unsupported(format!(
+                    "operator `{}` on {} and {} in `{}`",
+                    op.as_raw(),
+                    lhs,
+                    rhs,
+                    node.as_code()
+                )))
+            }
+        };
+
+        let instr = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Sub => "sub",
+            BinaryOp::Mul => "mul",
+            BinaryOp::Div if float => return Ok(self.float_division(false)),
+            BinaryOp::Mod if float => return Ok(self.float_division(true)),
+            BinaryOp::Div => "div_s",
+            BinaryOp::Mod => "rem_s",
+            BinaryOp::Eq => "eq",
+            BinaryOp::Neq => "ne",
+            BinaryOp::Gt => "gt",
+            BinaryOp::Gte => "ge",
+            BinaryOp::Lt => "lt",
+            BinaryOp::Lte => "le",
+        };
+        let signed = match op {
+            BinaryOp::Gt | BinaryOp::Gte | BinaryOp::Lt | BinaryOp::Lte => signed,
+            _ => "",
+        };
+        self.emit(format!("{}.{}{}", prefix, instr, signed));
+
+        Ok(lhs.binary(op, &rhs))
+    }
+
+    /// 生成浮点数的除法或取模，除数为零时陷入
+    ///
+    /// Wasm 没有浮点数取模指令，`a % b` 被计算为 `a - trunc(a / b) * b`，结果的符号与被除数相同。
+    fn float_division(&mut self, modulo: bool) -> Type {
+        let [lhs, rhs] = SCRATCH;
+
+        self.scratch.insert(rhs);
+        self.emit(format!("local.set {}", rhs));
+        if modulo {
+            self.scratch.insert(lhs);
+            self.emit(format!("local.set {}", lhs));
+        }
+        self.emit(format!("local.get {}", rhs));
+        self.emit("f64.const 0");
+        self.emit("f64.eq");
+        self.emit("if");
+        self.emit("  unreachable");
+        self.emit("end");
+
+        if modulo {
+            for instr in [
+                format!("local.get {}", lhs),
+                format!("local.get {}", lhs),
+                format!("local.get {}", rhs),
+                "f64.div".to_string(),
+                "f64.trunc".to_string(),
+                format!("local.get {}", rhs),
+                "f64.mul".to_string(),
+                "f64.sub".to_string(),
+            ] {
+                self.emit(instr);
+            }
+        } else {
+            self.emit(format!("local.get {}", rhs));
+            self.emit("f64.div");
+        }
+
+        Type::Float
+    }
+
+    fn type_of(&self, name: &str) -> Type {
+        self.indices
+            .get(name)
+            .map_or(Type::Unknown, |index| self.locals[*index].1)
+    }
+}
+
+/// 返回类型对应的 Wasm 值类型
+fn value_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Float => "f64",
+        Type::Bool => "i32",
+        _ => "i64",
+    }
+}
+
+fn unsupported(construct: String) -> CompilerError {
+    CompilerError::Unsupported(BACKEND, construct)
+}
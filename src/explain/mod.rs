@@ -0,0 +1,26 @@
+//! 错误代码的详细解释，由 `hare explain <代码>` 输出
+//!
+//! 每个代码对应 `codes` 目录中的一个 Markdown 文件，第一行是标题。以 `error` 标记的代码块会产生该错误，
+//! 以 `warning` 标记的代码块会产生该警告，以 `ok` 标记的代码块是没有错误的代码，测试会检查代码块与说明是否一致。
+
+macro_rules! explanations {
+    ($($code:literal),* $(,)?) => {
+        &[$(($code, include_str!(concat!("codes/", $code, ".md")))),*]
+    };
+}
+
+/// 所有的错误代码与对应的解释，按代码排列
+pub const EXPLANATIONS: &[(&str, &str)] = explanations![
+    "E0001", "E0002", "E0003", "E0004", "E0005", "E0006", "E0007", "E0008", "E0009", "E0010",
+    "E0011", "E0012", "E0013", "E0014", "E0015", "E0016", "E0017", "E0100", "E0101", "E0102",
+    "E0103", "E0104", "E0105", "E0106", "E0107", "E0108", "E0109", "E0110", "E0111", "E0112",
+    "E0113", "E0114", "E0115", "E0116", "E0117", "E0118", "W0001",
+];
+
+/// 返回错误代码的解释，代码不区分大小写
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(candidate, _)| candidate.eq_ignore_ascii_case(code))
+        .map(|(_, text)| *text)
+}
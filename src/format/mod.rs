@@ -0,0 +1,226 @@
+use crate::parser::errors::ParserError;
+use crate::parser::parse;
+
+/// 每一级缩进的空格数
+pub const INDENT_WIDTH: usize = 4;
+
+/// 格式化时使用的标记种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    /// 关键字、标识符与数字
+    Word,
+    Str,
+    Operator,
+    Colon,
+    Semicolon,
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    Comment,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+    /// 该标记之前的换行符数量
+    newlines: usize,
+}
+
+impl Token<'_> {
+    fn is_keyword(&self, keywords: &[&str]) -> bool {
+        self.kind == Kind::Word && keywords.contains(&self.text)
+    }
+}
+
+/// 格式化源代码
+///
+/// 格式化只调整标记之间的空白，保留所有注释，不会改变程序的含义：
+///
+/// - 每条语句占一行，`;` 之后、`{` 之后与 `}` 前后换行，紧跟在 `}` 之后的 `elif` 与 `else` 不换行；
+/// - 块中的代码缩进 `INDENT_WIDTH` 个空格；
+/// - 二元操作符与 `=` 两侧各有一个空格，`:` 之后有一个空格，括号内侧没有空格；
+/// - 没有 `;` 的语句之间保留原有的换行，语句之间最多保留一个空行。
+///
+/// # 返回值
+///
+/// 返回格式化后的代码；代码有语法错误时不进行格式化，返回位置最靠前的错误。
+pub fn format(input: &str) -> Result<String, ParserError> {
+    parse(input)?;
+
+    let mut formatter = Formatter {
+        output: String::new(),
+        depth: 0,
+        line_start: true,
+    };
+    let mut previous: Option<Token> = None;
+
+    for token in tokenize(input) {
+        if token.kind == Kind::CloseBrace {
+            formatter.depth = formatter.depth.saturating_sub(1);
+        }
+
+        if let Some(previous) = previous {
+            if breaks_line(&previous, &token) {
+                formatter.newline();
+                let blank = token.newlines >= 2
+                    && previous.kind != Kind::OpenBrace
+                    && token.kind != Kind::CloseBrace;
+                if blank {
+                    formatter.output.push('\n');
+                }
+            } else if !formatter.line_start && spaced(&previous, &token) {
+                formatter.output.push(' ');
+            }
+        }
+        formatter.write(token.text);
+
+        if token.kind == Kind::OpenBrace {
+            formatter.depth += 1;
+        }
+        previous = Some(token);
+    }
+
+    formatter.newline();
+    Ok(formatter.output)
+}
+
+struct Formatter {
+    output: String,
+    depth: usize,
+    line_start: bool,
+}
+
+impl Formatter {
+    fn write(&mut self, text: &str) {
+        if self.line_start {
+            self.output.push_str(&" ".repeat(self.depth * INDENT_WIDTH));
+            self.line_start = false;
+        }
+        self.output.push_str(text);
+    }
+
+    fn newline(&mut self) {
+        if !self.line_start {
+            self.output.push('\n');
+            self.line_start = true;
+        }
+    }
+}
+
+/// 判断两个相邻的标记之间是否换行
+fn breaks_line(previous: &Token, token: &Token) -> bool {
+    if token.kind == Kind::Comment {
+        return token.newlines > 0;
+    }
+    if token.kind == Kind::CloseBrace {
+        return true;
+    }
+    if token.kind == Kind::Semicolon {
+        return previous.kind == Kind::Comment;
+    }
+
+    match previous.kind {
+        Kind::Semicolon | Kind::OpenBrace | Kind::Comment => true,
+        // 作为操作数的块之后的操作符与括号不换行
+        Kind::CloseBrace => {
+            !token.is_keyword(&["elif", "else"])
+                && !matches!(token.kind, Kind::Operator | Kind::CloseParen)
+        }
+        // 没有 `;` 的语句之间的换行，关键字之后的换行不是语句的结束
+        Kind::Word | Kind::Str | Kind::CloseParen => {
+            token.newlines > 0
+                && matches!(token.kind, Kind::Word | Kind::Str | Kind::OpenParen)
+                && !previous.is_keyword(&["let", "rtb", "if", "elif", "else"])
+                && !token.is_keyword(&["elif", "else"])
+        }
+        _ => false,
+    }
+}
+
+/// 判断同一行中两个相邻的标记之间是否有空格
+fn spaced(previous: &Token, token: &Token) -> bool {
+    !matches!(token.kind, Kind::Semicolon | Kind::Colon | Kind::CloseParen)
+        && previous.kind != Kind::OpenParen
+}
+
+/// 将源代码切分为标记
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut newlines = 0;
+    let mut pos = 0;
+
+    while let Some(c) = input[pos..].chars().next() {
+        let start = pos;
+        let kind = match c {
+            '\n' => {
+                newlines += 1;
+                pos += 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                pos += c.len_utf8();
+                continue;
+            }
+            '#' => {
+                pos = input[pos..]
+                    .find('\n')
+                    .map_or(input.len(), |index| pos + index);
+                Kind::Comment
+            }
+            '"' => {
+                pos = input[pos + 1..]
+                    .find(['"', '\n'])
+                    .map_or(input.len(), |index| pos + 1 + index + 1);
+                Kind::Str
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' => {
+                pos = input[pos..]
+                    .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                    .map_or(input.len(), |index| pos + index);
+                Kind::Word
+            }
+            ':' => {
+                pos += 1;
+                Kind::Colon
+            }
+            ';' => {
+                pos += 1;
+                Kind::Semicolon
+            }
+            '(' => {
+                pos += 1;
+                Kind::OpenParen
+            }
+            ')' => {
+                pos += 1;
+                Kind::CloseParen
+            }
+            '{' => {
+                pos += 1;
+                Kind::OpenBrace
+            }
+            '}' => {
+                pos += 1;
+                Kind::CloseBrace
+            }
+            c => {
+                let two = ["==", "!=", ">=", "<="]
+                    .into_iter()
+                    .any(|op| input[pos..].starts_with(op));
+                pos += if two { 2 } else { c.len_utf8() };
+                Kind::Operator
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            text: input[start..pos].trim_end(),
+            newlines,
+        });
+        newlines = 0;
+    }
+
+    tokens
+}
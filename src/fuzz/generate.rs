@@ -0,0 +1,374 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// 语言的关键字，生成的标识符会避开它们
+const KEYWORDS: &[&str] = &["let", "rtb", "if", "elif", "else", "true", "false"];
+
+/// 生成的程序中常用的变量名，使得赋值与读取能够相互对应
+const NAMES: &[&str] = &["a", "b", "x", "y", "value", "count", "_tmp", "n1"];
+
+/// 二元操作符，对应 `bin_op` 规则的各个分支
+const BIN_OPS: &[&str] = &["+", "-", "*", "/", "%", "==", "!=", ">=", "<=", ">", "<"];
+
+/// 类型注解中使用的类型名
+const TYPES: &[&str] = &["int", "float", "bool", "str"];
+
+/// 结构感知的程序生成器
+///
+/// 按照 `src/grammar.pest` 中的规则生成语法正确的 Blue Arch 程序，每个方法对应语法中的一条规则。
+/// 生成的程序一定能被解析，但不保证能通过编译或运行（例如可能包含除数为零的常量表达式）。
+/// 相同的种子总是生成相同的程序。
+#[derive(Debug)]
+pub struct Generator {
+    rng: StdRng,
+    max_depth: usize,
+}
+
+impl Generator {
+    /// 使用种子创建生成器
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            max_depth: 4,
+        }
+    }
+
+    /// 使用任意字节作为种子创建生成器，用于模糊测试
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let seed = data.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+        Self::new(seed)
+    }
+
+    /// 设置块、if 语句与括号表达式的最大嵌套深度
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// `program = _{ SOI ~ ( block | statement )* ~ EOI }`
+    pub fn program(&mut self) -> String {
+        let mut output = self.whitespace();
+        let count = self.rng.gen_range(0..8);
+
+        for _ in 0..count {
+            if self.max_depth > 0 && self.rng.gen_bool(0.1) {
+                output.push_str(&self.block(self.max_depth - 1));
+            } else {
+                output.push_str(&self.statement(self.max_depth, false));
+            }
+            output.push_str(&self.whitespace());
+        }
+
+        output
+    }
+
+    /// `block = { "{" ~ statement* ~ "}" }`
+    fn block(&mut self, depth: usize) -> String {
+        let mut output = String::from("{");
+        output.push_str(&self.whitespace());
+
+        for _ in 0..self.rng.gen_range(0..4) {
+            output.push_str(&self.statement(depth, true));
+            output.push_str(&self.whitespace());
+        }
+
+        output.push('}');
+        output
+    }
+
+    /// `statement = { ( assign_statement | set_value_statement | return_block_statement | if_statement | expr ) ~ ";"? }`
+    ///
+    /// `rtb` 语句只在块中生成。
+    fn statement(&mut self, depth: usize, in_block: bool) -> String {
+        let mut output = match self.rng.gen_range(0..10) {
+            0..=2 => self.assign_statement(depth),
+            3..=4 => self.set_value_statement(depth),
+            5 if in_block => self.return_block_statement(depth),
+            6..=7 if depth > 0 => self.if_statement(depth - 1),
+            _ => {
+                // 以 `if` 开头的表达式语句会被解析为 if 语句，因此作为值的 if 被放在括号中
+                let term = self.term(depth);
+                let term = match term.starts_with("if ") {
+                    true => format!("({})", term),
+                    false => term,
+                };
+                self.chain(term, depth)
+            }
+        };
+
+        if self.rng.gen_bool(0.85) {
+            output.push(';');
+        }
+        output
+    }
+
+    /// `assign_statement = { "let " ~ ident ~ ( ":" ~ ident)? ~ "=" ~ expr }`
+    fn assign_statement(&mut self, depth: usize) -> String {
+        let mut output = format!("let {}{}", self.whitespace(), self.ident());
+
+        if self.rng.gen_bool(0.3) {
+            let annotation = TYPES[self.rng.gen_range(0..TYPES.len())];
+            output.push_str(&format!(
+                "{}:{}{}",
+                self.whitespace(),
+                self.whitespace(),
+                annotation
+            ));
+        }
+
+        output.push_str(&format!(
+            "{}={}{}",
+            self.whitespace(),
+            self.whitespace(),
+            self.expr(depth)
+        ));
+        output
+    }
+
+    /// `set_value_statement = { ident ~ "=" ~ expr }`
+    fn set_value_statement(&mut self, depth: usize) -> String {
+        format!(
+            "{}{}={}{}",
+            self.ident(),
+            self.whitespace(),
+            self.whitespace(),
+            self.expr(depth)
+        )
+    }
+
+    /// `return_block_statement = { "rtb " ~ expr }`
+    fn return_block_statement(&mut self, depth: usize) -> String {
+        format!("rtb {}{}", self.whitespace(), self.expr(depth))
+    }
+
+    /// `if_statement = { "if " ~ expr ~ block ~ elif_statement* ~ else_statement? }`
+    fn if_statement(&mut self, depth: usize) -> String {
+        let mut output = format!(
+            "if {}{}{}{}",
+            self.whitespace(),
+            self.expr(depth),
+            self.whitespace(),
+            self.block(depth)
+        );
+
+        for _ in 0..self.rng.gen_range(0..3) {
+            output.push_str(&self.whitespace());
+            output.push_str(&self.elif_statement(depth));
+        }
+
+        if self.rng.gen_bool(0.5) {
+            output.push_str(&self.whitespace());
+            output.push_str(&self.else_statement(depth));
+        }
+
+        output
+    }
+
+    /// `elif_statement = { "elif " ~ expr ~ block }`
+    fn elif_statement(&mut self, depth: usize) -> String {
+        format!(
+            "elif {}{}{}{}",
+            self.whitespace(),
+            self.expr(depth),
+            self.whitespace(),
+            self.block(depth)
+        )
+    }
+
+    /// `else_statement = { "else " ~ block }`
+    fn else_statement(&mut self, depth: usize) -> String {
+        format!("else {}{}", self.whitespace(), self.block(depth))
+    }
+
+    /// `expr = { term ~ (bin_op ~ term)* }`
+    fn expr(&mut self, depth: usize) -> String {
+        let term = self.term(depth);
+        self.chain(term, depth)
+    }
+
+    /// 在第一个操作数 `output` 之后生成 `(bin_op ~ term)*`
+    fn chain(&mut self, mut output: String, depth: usize) -> String {
+        for _ in 0..self.rng.gen_range(0..4) {
+            let op = BIN_OPS[self.rng.gen_range(0..BIN_OPS.len())];
+            output.push_str(&format!(
+                "{}{}{}{}",
+                self.whitespace(),
+                op,
+                self.whitespace(),
+                self.term(depth)
+            ));
+        }
+
+        output
+    }
+
+    /// `term = _{ ( "(" ~ expr ~ ")" ) | block | if_statement | constant | ident }`
+    ///
+    /// 作为值的块与 if 出现得较少，使得大部分生成的程序仍然短小。
+    fn term(&mut self, depth: usize) -> String {
+        if depth > 0 && self.rng.gen_bool(0.02) {
+            return match self.rng.gen_bool(0.5) {
+                true => self.value_block(depth - 1, None),
+                false => self.if_value(depth - 1),
+            };
+        }
+
+        match self.rng.gen_range(0..10) {
+            0..=1 if depth > 0 => format!("({})", self.expr(depth - 1)),
+            0..=5 => self.constant(),
+            _ => self.ident(),
+        }
+    }
+
+    /// 作为值的块，最后总是以 `rtb` 给出值
+    ///
+    /// `kind` 不为 `None` 时值是该种类的常量（见 `constant_of`），并且之前的语句中不直接包含 `rtb`，
+    /// 否则值是任意的表达式。
+    fn value_block(&mut self, depth: usize, kind: Option<usize>) -> String {
+        let mut output = String::from("{");
+        output.push_str(&self.whitespace());
+
+        for _ in 0..self.rng.gen_range(0..2) {
+            output.push_str(&self.statement(depth, kind.is_none()));
+            output.push_str(&self.whitespace());
+        }
+
+        match kind {
+            Some(kind) => {
+                let value = self.constant_of(kind);
+                output.push_str(&format!("rtb {}{}", self.whitespace(), value));
+            }
+            None => output.push_str(&self.return_block_statement(depth)),
+        }
+        output.push(';');
+        output.push_str(&self.whitespace());
+        output.push('}');
+        output
+    }
+
+    /// 作为值的 if，总是有 else 分支，每个分支都是作为值的块
+    ///
+    /// 各个分支的值是同一种类的常量，使得大部分生成的 if 能够通过分支类型的检查。
+    fn if_value(&mut self, depth: usize) -> String {
+        let kind = Some(self.rng.gen_range(0..4));
+        let mut output = format!(
+            "if {}{}{}{}",
+            self.whitespace(),
+            self.expr(depth),
+            self.whitespace(),
+            self.value_block(depth, kind)
+        );
+
+        for _ in 0..self.rng.gen_range(0..2) {
+            output.push_str(&format!(
+                "{}elif {}{}{}{}",
+                self.whitespace(),
+                self.whitespace(),
+                self.expr(depth),
+                self.whitespace(),
+                self.value_block(depth, kind)
+            ));
+        }
+
+        output.push_str(&format!(
+            "{}else {}{}",
+            self.whitespace(),
+            self.whitespace(),
+            self.value_block(depth, kind)
+        ));
+        output
+    }
+
+    /// `ident = @{ (LETTER | "_" | NUMBER)+ }`
+    ///
+    /// 标识符不以数字开头，也不以 `true` 或 `false` 开头，否则会被解析为常量。
+    fn ident(&mut self) -> String {
+        if self.rng.gen_bool(0.7) {
+            return NAMES[self.rng.gen_range(0..NAMES.len())].to_string();
+        }
+
+        loop {
+            let first = self.pick("abcdeghijklmnopqrsuvwxyzABCDEFGHIJKLMNOPQRSUVWXYZ_é变");
+            let rest: String = (0..self.rng.gen_range(0..6))
+                .map(|_| self.pick("abcdefghijklmnopqrstuvwxyz_0123456789"))
+                .collect();
+            let ident = format!("{}{}", first, rest);
+
+            if !KEYWORDS.contains(&ident.as_str()) {
+                return ident;
+            }
+        }
+    }
+
+    /// `constant = { float | int | string | boolean }`
+    fn constant(&mut self) -> String {
+        let kind = match self.rng.gen_range(0..10) {
+            0..=4 => 0,
+            5..=6 => 1,
+            7..=8 => 2,
+            _ => 3,
+        };
+        self.constant_of(kind)
+    }
+
+    /// 生成指定种类的常量：`0` 为整数，`1` 为浮点数，`2` 为字符串，其余为布尔值
+    fn constant_of(&mut self, kind: usize) -> String {
+        match kind {
+            0 => self.int(),
+            1 => self.float(),
+            2 => self.string(),
+            _ => self.boolean(),
+        }
+    }
+
+    /// `int = @{NUMBER+}`
+    fn int(&mut self) -> String {
+        match self.rng.gen_range(0..10) {
+            0 => self.rng.gen_range(0..=i64::MAX).to_string(),
+            _ => self.rng.gen_range(0..100).to_string(),
+        }
+    }
+
+    /// `float = @{(NUMBER)+ ~ "." ~ (NUMBER)+ | "0."}`
+    fn float(&mut self) -> String {
+        format!(
+            "{}.{}",
+            self.rng.gen_range(0..100),
+            self.rng.gen_range(0..100)
+        )
+    }
+
+    /// `string = @{"\"" ~ LETTER* ~ "\""}`
+    fn string(&mut self) -> String {
+        let content: String = (0..self.rng.gen_range(0..6))
+            .map(|_| self.pick("abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZß中"))
+            .collect();
+        format!("\"{}\"", content)
+    }
+
+    /// `boolean = @{"true" | "false"}`
+    fn boolean(&mut self) -> String {
+        self.rng.gen_bool(0.5).to_string()
+    }
+
+    /// `WHITESPACE = _{ " " | "\n" | "\t" }` 与 `COMMENT = _{ "#" ~ (!"\n" ~ ANY)* }`
+    ///
+    /// 总是至少生成一个空白字符，避免相邻的标记粘连在一起。
+    fn whitespace(&mut self) -> String {
+        match self.rng.gen_range(0..20) {
+            0 => format!(" # {}\n", self.ident()),
+            1 => "\n".to_string(),
+            2 => "\t".to_string(),
+            3 => "  ".to_string(),
+            _ => " ".to_string(),
+        }
+    }
+
+    /// 从字符集中随机选择一个字符
+    fn pick(&mut self, chars: &str) -> char {
+        let chars: Vec<char> = chars.chars().collect();
+        chars[self.rng.gen_range(0..chars.len())]
+    }
+}
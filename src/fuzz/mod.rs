@@ -0,0 +1,43 @@
+pub mod generate;
+
+use thiserror::Error;
+
+use crate::compiler::{ByteCode, CompilerError};
+use crate::optimizer::{optimize, optimize_bytecodes};
+use crate::parser::errors::ParserError;
+use crate::parser::parse;
+
+pub use generate::Generator;
+
+/// 模糊测试中允许出现的错误
+///
+/// 模糊测试的约定是：任意输入要么得到一个此类型的错误，要么成功得到字节码，绝不能发生 panic。
+#[derive(Error, Debug)]
+pub enum FuzzError {
+    #[error("{0}")]
+    ParserError(#[from] ParserError),
+    #[error("{0}")]
+    CompilerError(#[from] CompilerError),
+}
+
+/// 模糊测试的入口：将任意字节作为源代码解析并编译
+///
+/// 不是合法 UTF-8 的输入会按有损方式转换，使得字节级的变异也能触达解析器。
+pub fn fuzz_bytes(data: &[u8]) -> Result<Vec<ByteCode>, FuzzError> {
+    parse_and_compile(&String::from_utf8_lossy(data))
+}
+
+/// 解析并编译源代码，同时检查各个优化等级的编译结果
+///
+/// 返回未经优化的字节码。
+pub fn parse_and_compile(input: &str) -> Result<Vec<ByteCode>, FuzzError> {
+    let ast = parse(input)?;
+    let codes = ast.compile()?;
+
+    for level in 1..=2 {
+        let (optimized, _) = optimize(&ast, level)?;
+        optimize_bytecodes(&optimized.compile()?, level);
+    }
+
+    Ok(codes)
+}
@@ -0,0 +1,241 @@
+mod register;
+
+use std::collections::{BTreeMap, HashMap};
+
+use thiserror::Error;
+
+use crate::compiler::operand::Operand;
+use crate::compiler::value::{Value, ValueError};
+use crate::compiler::{ByteCode, OpCode};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RuntimeError {
+    #[error("Stack underflow at {0:?}")]
+    StackUnderflow(OpCode),
+    #[error("Undefined name: {0}")]
+    UndefinedName(String),
+    #[error("Undefined section: {0}")]
+    UndefinedSection(String),
+    #[error("Condition must be a bool, found: {0}")]
+    InvalidCondition(String),
+    #[error("Unbalanced section: {0}")]
+    UnbalancedSection(String),
+    #[error("{0} is read before it is written")]
+    Uninitialized(String),
+    #[error("{0}")]
+    ValueError(#[from] ValueError),
+}
+
+/// Section 执行结束的方式
+enum Flow {
+    /// 执行到了 Section 的末尾
+    End,
+    /// 执行了 `Return` 指令
+    Return,
+}
+
+/// 字节码的参考解释器
+///
+/// 解释器按照 `OpCode` 文档中描述的语义执行字节码，用于测试编译器与优化器的输出，
+/// 也可以通过 `run_registers` 执行寄存器指令，两种指令共用同一个堆。
+/// Section 被调用时会在操作数栈上记录一个栈帧，`Return` 时若栈帧中有值，则栈顶的值作为
+/// Section 的返回值被压入调用者的栈中；执行到 Section 末尾时栈帧中的值会被全部丢弃。
+#[derive(Debug, Default)]
+pub struct Interpreter {
+    stack: Vec<Value>,
+    names: HashMap<String, Value>,
+    sections: HashMap<String, Vec<ByteCode>>,
+    /// 操作数栈曾经达到的最大深度
+    max_stack: usize,
+    /// 每个 Section 被调用时相对于调用时栈顶曾经达到的最大深度
+    section_max_stack: HashMap<String, usize>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 执行一段字节码，状态会在多次调用之间保留
+    pub fn run(&mut self, codes: &[ByteCode]) -> Result<(), RuntimeError> {
+        self.execute(codes).map(|_| ())
+    }
+
+    /// 返回操作数栈，栈底在前
+    pub fn stack(&self) -> &[Value] {
+        &self.stack
+    }
+
+    /// 返回操作数栈曾经达到的最大深度
+    pub fn max_stack(&self) -> usize {
+        self.max_stack
+    }
+
+    /// 返回 Section 被调用时相对于调用时栈顶曾经达到的最大深度，Section 未被调用过时返回 `None`
+    pub fn section_max_stack(&self, name: &str) -> Option<usize> {
+        self.section_max_stack.get(name).copied()
+    }
+
+    /// 返回堆中名为 `name` 的值
+    pub fn name(&self, name: &str) -> Option<&Value> {
+        self.names.get(name)
+    }
+
+    /// 返回程序可观察的结果：操作数栈中的值（每行一个，栈底在前），以及按名字排序的堆中的值
+    pub fn output(&self) -> String {
+        let mut output = String::new();
+
+        for value in &self.stack {
+            output.push_str(&format!("{}\n", value));
+        }
+
+        let names: BTreeMap<&String, &Value> = self.names.iter().collect();
+        for (name, value) in names {
+            output.push_str(&format!("{} = {}\n", name, value));
+        }
+
+        output
+    }
+
+    fn execute(&mut self, codes: &[ByteCode]) -> Result<Flow, RuntimeError> {
+        let mut pc = 0;
+
+        while pc < codes.len() {
+            let code = &codes[pc];
+            pc += 1;
+
+            match code.op {
+                OpCode::Push => {
+                    self.stack
+                        .extend(code.operands().iter().filter_map(Operand::value));
+                }
+                OpCode::Pop => {
+                    self.pop(&code.op)?;
+                }
+                OpCode::Dup => {
+                    let value = self.pop(&code.op)?;
+                    self.stack.push(value.clone());
+                    self.stack.push(value);
+                }
+                OpCode::Add
+                | OpCode::Sub
+                | OpCode::Mul
+                | OpCode::Div
+                | OpCode::Mod
+                | OpCode::Eq
+                | OpCode::Neq
+                | OpCode::Gt
+                | OpCode::Lt
+                | OpCode::Gte
+                | OpCode::Lte => {
+                    let rhs = self.pop(&code.op)?;
+                    let lhs = self.pop(&code.op)?;
+                    let op = code.op.to_binary_op().unwrap();
+                    self.stack.push(lhs.binary(&op, &rhs)?);
+                }
+                OpCode::Neg => {
+                    let value = self.pop(&code.op)?;
+                    self.stack.push(value.neg()?);
+                }
+                OpCode::Jump => {
+                    self.call(self.operand(code))?;
+                }
+                OpCode::JumpIf => match self.pop(&code.op)? {
+                    Value::Bool(true) => self.call(self.operand(code))?,
+                    Value::Bool(false) => {}
+                    value => return Err(RuntimeError::InvalidCondition(value.to_literal())),
+                },
+                OpCode::JumpIfElse => {
+                    let targets = code.targets();
+                    match self.pop(&code.op)? {
+                        Value::Bool(true) => self.call(targets[0])?,
+                        Value::Bool(false) => self.call(targets[1])?,
+                        value => return Err(RuntimeError::InvalidCondition(value.to_literal())),
+                    }
+                }
+                OpCode::LoadName => {
+                    let name = self.operand(code);
+                    let value = self
+                        .names
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| RuntimeError::UndefinedName(name.to_string()))?;
+                    self.stack.push(value);
+                }
+                OpCode::StoreName => {
+                    let name = self.operand(code).to_string();
+                    let value = self.pop(&code.op)?;
+                    self.names.insert(name, value);
+                }
+                OpCode::MakeSection => {
+                    let name = self.operand(code).to_string();
+                    let start = pc;
+                    let mut depth = 1;
+
+                    while depth > 0 {
+                        let code = codes
+                            .get(pc)
+                            .ok_or_else(|| RuntimeError::UnbalancedSection(name.clone()))?;
+                        match code.op {
+                            OpCode::MakeSection => depth += 1,
+                            OpCode::EndMakeSection => depth -= 1,
+                            _ => {}
+                        }
+                        pc += 1;
+                    }
+
+                    self.sections.insert(name, codes[start..pc - 1].to_vec());
+                }
+                OpCode::EndMakeSection => {
+                    return Err(RuntimeError::UnbalancedSection(
+                        "EndMakeSection without MakeSection".to_string(),
+                    ))
+                }
+                OpCode::Return => return Ok(Flow::Return),
+            }
+
+            self.max_stack = self.max_stack.max(self.stack.len());
+        }
+
+        Ok(Flow::End)
+    }
+
+    /// 调用名为 `name` 的 Section
+    fn call(&mut self, name: &str) -> Result<(), RuntimeError> {
+        let section = self
+            .sections
+            .get(name)
+            .cloned()
+            .ok_or_else(|| RuntimeError::UndefinedSection(name.to_string()))?;
+
+        let base = self.stack.len();
+        // 单独记录 Section 执行期间的最大深度，之后再与调用者的合并
+        let outer = std::mem::replace(&mut self.max_stack, base);
+        let flow = self.execute(&section);
+        let max = self.section_max_stack.entry(name.to_string()).or_insert(0);
+        *max = (*max).max(self.max_stack - base);
+        self.max_stack = self.max_stack.max(outer);
+
+        let flow = flow?;
+        let value = match flow {
+            Flow::Return if self.stack.len() > base => self.stack.pop(),
+            _ => None,
+        };
+
+        self.stack.truncate(base);
+        self.stack.extend(value);
+        Ok(())
+    }
+
+    fn pop(&mut self, op: &OpCode) -> Result<Value, RuntimeError> {
+        self.stack
+            .pop()
+            .ok_or_else(|| RuntimeError::StackUnderflow(op.clone()))
+    }
+
+    /// 返回名字或 Section 的名字，`ByteCode::new` 保证了这些指令恰好有一个这样的参数
+    fn operand<'a>(&self, code: &'a ByteCode) -> &'a str {
+        code.name()
+            .expect("operands are checked by `ByteCode::new`")
+    }
+}
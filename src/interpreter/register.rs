@@ -0,0 +1,88 @@
+use crate::compiler::register::{Reg, RegisterInstr, RegisterProgram};
+use crate::compiler::value::Value;
+
+use super::{Interpreter, RuntimeError};
+
+impl Interpreter {
+    /// 执行寄存器指令
+    ///
+    /// `yield` 输出的值被压入操作数栈，因此与相同程序的栈式字节码有相同的 `output`。
+    /// 寄存器与溢出槽只在一次执行中有效，堆会在多次调用之间保留。
+    pub fn run_registers(&mut self, program: &RegisterProgram) -> Result<(), RuntimeError> {
+        let mut registers: Vec<Option<Value>> = vec![None; program.registers];
+        let mut slots: Vec<Option<Value>> = vec![None; program.slots];
+        let mut pc = 0;
+
+        while let Some(instr) = program.code.get(pc) {
+            pc += 1;
+
+            match instr {
+                RegisterInstr::Const(reg, value) => write(&mut registers, *reg, value.clone()),
+                RegisterInstr::Load(reg, name) => {
+                    let value = self
+                        .names
+                        .get(name)
+                        .cloned()
+                        .ok_or_else(|| RuntimeError::UndefinedName(name.to_string()))?;
+                    write(&mut registers, *reg, value);
+                }
+                RegisterInstr::Store(name, reg) => {
+                    let value = read(&registers, *reg)?.clone();
+                    self.names.insert(name.clone(), value);
+                }
+                RegisterInstr::Move(reg, source) => {
+                    let value = read(&registers, *source)?.clone();
+                    write(&mut registers, *reg, value);
+                }
+                RegisterInstr::Binary(reg, op, lhs, rhs) => {
+                    let value = read(&registers, *lhs)?.binary(op, read(&registers, *rhs)?)?;
+                    write(&mut registers, *reg, value);
+                }
+                RegisterInstr::Spill(slot, reg) => {
+                    let value = read(&registers, *reg)?.clone();
+                    if slots.len() <= slot.0 {
+                        slots.resize(slot.0 + 1, None);
+                    }
+                    slots[slot.0] = Some(value);
+                }
+                RegisterInstr::Reload(reg, slot) => {
+                    let value = slots
+                        .get(slot.0)
+                        .cloned()
+                        .flatten()
+                        .ok_or_else(|| RuntimeError::Uninitialized(slot.to_string()))?;
+                    write(&mut registers, *reg, value);
+                }
+                RegisterInstr::Yield(reg) => {
+                    let value = read(&registers, *reg)?.clone();
+                    self.stack.push(value);
+                }
+                RegisterInstr::Jump(target) => pc = *target,
+                RegisterInstr::JumpIfNot(reg, target) => match read(&registers, *reg)? {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => pc = *target,
+                    value => return Err(RuntimeError::InvalidCondition(value.to_literal())),
+                },
+                RegisterInstr::Halt => break,
+            }
+
+            self.max_stack = self.max_stack.max(self.stack.len());
+        }
+
+        Ok(())
+    }
+}
+
+fn read(registers: &[Option<Value>], reg: Reg) -> Result<&Value, RuntimeError> {
+    registers
+        .get(reg.0)
+        .and_then(Option::as_ref)
+        .ok_or_else(|| RuntimeError::Uninitialized(reg.to_string()))
+}
+
+fn write(registers: &mut Vec<Option<Value>>, reg: Reg, value: Value) {
+    if registers.len() <= reg.0 {
+        registers.resize(reg.0 + 1, None);
+    }
+    registers[reg.0] = Some(value);
+}
@@ -0,0 +1,383 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::compiler::operand::Operand;
+use crate::compiler::section::SectionNamer;
+use crate::compiler::value::Value;
+use crate::compiler::{ByteCode, CompilerError, OpCode};
+
+use super::{BlockId, Instr, IrProgram, Temp, Terminator};
+
+impl IrProgram {
+    /// 将中间表示生成为字节码
+    ///
+    /// 条件分支被还原为 Section：两个分支各自生成一个 Section，直到它们的汇合块为止，汇合块之后的代码继续生成在当前位置。
+    /// 两个分支都以 `Copy` 将值写入同一个临时变量时，该值作为 Section 的返回值由 `JumpIfElse` 留在操作数栈上。
+    /// 只在定义它的 Section 中被使用一次的临时变量直接保存在操作数栈上，其它临时变量以 `%n` 为名存入堆中。
+    pub fn emit(&self) -> Result<Vec<ByteCode>, CompilerError> {
+        self.emit_with(&mut SectionNamer::new())
+    }
+
+    /// 将中间表示生成为字节码，使用 `namer` 生成 Section 的名字
+    pub fn emit_with(&self, namer: &mut SectionNamer) -> Result<Vec<ByteCode>, CompilerError> {
+        let mut emitter = Emitter::new(self, namer);
+        let mut bytecode: Vec<ByteCode> = vec![];
+        emitter.emit_region(self.entry, None, &mut bytecode)?;
+        Ok(bytecode)
+    }
+}
+
+struct Emitter<'a> {
+    program: &'a IrProgram,
+    namer: &'a mut SectionNamer,
+    post_dominators: HashMap<BlockId, Option<BlockId>>,
+    /// 由 `Copy` 定义的临时变量，即块与作为值的 if 的结果，它们作为 Section 的返回值留在操作数栈上
+    results: HashSet<Temp>,
+    /// 临时变量被使用的次数
+    uses: HashMap<Temp, usize>,
+    /// 需要存入堆中的临时变量
+    named: HashSet<Temp>,
+    /// 当前基本块中保存在操作数栈上的临时变量
+    stack: Vec<Temp>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(program: &'a IrProgram, namer: &'a mut SectionNamer) -> Self {
+        let post_dominators = program.immediate_post_dominators();
+        let frames = frames(program, &post_dominators);
+        let mut uses: HashMap<Temp, usize> = HashMap::new();
+        let mut definitions: HashMap<Temp, BlockId> = HashMap::new();
+        let mut results: HashSet<Temp> = HashSet::new();
+        let mut named: HashSet<Temp> = HashSet::new();
+
+        for block in &program.blocks {
+            for instr in &block.instrs {
+                if let Some(temp) = instr.def() {
+                    definitions.insert(temp, block.id);
+                }
+                if let Instr::Copy(temp, _) = instr {
+                    results.insert(*temp);
+                }
+            }
+        }
+
+        for block in &program.blocks {
+            let used = block
+                .instrs
+                .iter()
+                .flat_map(|instr| instr.uses())
+                .chain(block.terminator.uses());
+
+            for temp in used {
+                *uses.entry(temp).or_default() += 1;
+                // 在其它 Section 中定义的值不在当前的栈帧中
+                let frame = definitions.get(&temp).and_then(|block| frames.get(block));
+                if !results.contains(&temp) && frame != frames.get(&block.id) {
+                    named.insert(temp);
+                }
+            }
+        }
+
+        named.extend(
+            uses.iter()
+                .filter(|(_, count)| **count > 1)
+                .map(|(temp, _)| *temp),
+        );
+
+        Self {
+            program,
+            namer,
+            post_dominators,
+            results,
+            uses,
+            named,
+            stack: vec![],
+        }
+    }
+
+    fn emit_region(
+        &mut self,
+        start: BlockId,
+        stop: Option<BlockId>,
+        bytecode: &mut Vec<ByteCode>,
+    ) -> Result<(), CompilerError> {
+        let mut current = Some(start);
+
+        while let Some(block_id) = current {
+            if Some(block_id) == stop {
+                break;
+            }
+
+            let block = self.program.block(block_id);
+
+            // 分支的 Section 定义在条件的计算之前
+            let split = match &block.terminator {
+                Terminator::Branch(cond, _, _) => condition_start(&block.instrs, *cond),
+                _ => block.instrs.len(),
+            };
+
+            for instr in &block.instrs[..split] {
+                self.emit_instr(instr, bytecode)?;
+            }
+
+            // 条件分支的汇合块，以及分支作为值时的结果
+            let join = self.post_dominators.get(&block_id).copied().flatten();
+            let result = match &block.terminator {
+                Terminator::Branch(_, then_block, _) => self.region_result(*then_block, join),
+                _ => None,
+            };
+
+            let mut sections: Option<(String, Option<String>)> = None;
+            if let Terminator::Branch(_, then_block, else_block) = &block.terminator {
+                let then_name = self.namer.next_name();
+                self.emit_section(&then_name, *then_block, join, result, bytecode)?;
+
+                let else_name = if Some(*else_block) != join {
+                    let else_name = self.namer.next_name();
+                    self.emit_section(&else_name, *else_block, join, result, bytecode)?;
+                    Some(else_name)
+                } else {
+                    None
+                };
+
+                sections = Some((then_name, else_name));
+            }
+
+            for instr in &block.instrs[split..] {
+                self.emit_instr(instr, bytecode)?;
+            }
+
+            current = match &block.terminator {
+                Terminator::Jump(target) => Some(*target),
+                Terminator::Branch(cond, _, _) => {
+                    self.operands(&[*cond], bytecode)?;
+                    let (then_name, else_name) = sections.take().unwrap();
+
+                    match (else_name, result) {
+                        // 进入的分支的返回值被留在栈上
+                        (Some(else_name), Some(result)) => {
+                            bytecode.push(ByteCode::new(
+                                OpCode::JumpIfElse,
+                                vec![Operand::Section(then_name), Operand::Section(else_name)],
+                            )?);
+                            self.stack.push(result);
+                        }
+                        // 条件只计算一次：复制一份，先取反判断是否进入 else Section，再判断是否进入 then Section
+                        (Some(else_name), None) => {
+                            bytecode.push(ByteCode::new(OpCode::Dup, vec![])?);
+                            bytecode.push(ByteCode::new(OpCode::Neg, vec![])?);
+                            bytecode.push(ByteCode::new(
+                                OpCode::JumpIf,
+                                vec![Operand::Section(else_name)],
+                            )?);
+                            bytecode.push(ByteCode::new(
+                                OpCode::JumpIf,
+                                vec![Operand::Section(then_name)],
+                            )?);
+                        }
+                        (None, _) => bytecode.push(ByteCode::new(
+                            OpCode::JumpIf,
+                            vec![Operand::Section(then_name)],
+                        )?),
+                    }
+                    join
+                }
+                Terminator::Return(Some(temp)) => {
+                    self.operands(&[*temp], bytecode)?;
+                    bytecode.push(ByteCode::new(OpCode::Return, vec![])?);
+                    None
+                }
+                Terminator::Return(None) => None,
+            };
+        }
+
+        Ok(())
+    }
+
+    /// 返回从 `start` 开始、到 `stop` 为止的区域的结果，即区域的最后一条指令以 `Copy` 定义的临时变量
+    ///
+    /// 区域以条件分支结尾时，其结果是该分支的结果。
+    fn region_result(&self, start: BlockId, stop: Option<BlockId>) -> Option<Temp> {
+        let mut current = start;
+
+        loop {
+            let block = self.program.block(current);
+            let next = match &block.terminator {
+                Terminator::Jump(target) => Some(*target),
+                Terminator::Branch(_, _, _) => {
+                    self.post_dominators.get(&current).copied().flatten()
+                }
+                Terminator::Return(_) => None,
+            };
+
+            match next {
+                Some(next) if Some(next) != stop => current = next,
+                _ => {
+                    return match (&block.terminator, block.instrs.last()) {
+                        (Terminator::Branch(_, then_block, _), _) => {
+                            self.region_result(*then_block, next)
+                        }
+                        (_, Some(Instr::Copy(temp, _))) if self.results.contains(temp) => {
+                            Some(*temp)
+                        }
+                        _ => None,
+                    };
+                }
+            }
+        }
+    }
+
+    /// 生成一个 Section，`result` 不为 `None` 时它以 `Return` 返回该临时变量的值
+    fn emit_section(
+        &mut self,
+        name: &str,
+        start: BlockId,
+        stop: Option<BlockId>,
+        result: Option<Temp>,
+        bytecode: &mut Vec<ByteCode>,
+    ) -> Result<(), CompilerError> {
+        // Section 拥有独立的栈帧
+        let stack = std::mem::take(&mut self.stack);
+
+        bytecode.push(ByteCode::new(
+            OpCode::MakeSection,
+            vec![Operand::Section(name.to_string())],
+        )?);
+        self.emit_region(start, stop, bytecode)?;
+        if let Some(result) = result {
+            self.operands(&[result], bytecode)?;
+            bytecode.push(ByteCode::new(OpCode::Return, vec![])?);
+        }
+        bytecode.push(ByteCode::new(OpCode::EndMakeSection, vec![])?);
+
+        self.stack = stack;
+        Ok(())
+    }
+
+    fn emit_instr(
+        &mut self,
+        instr: &Instr,
+        bytecode: &mut Vec<ByteCode>,
+    ) -> Result<(), CompilerError> {
+        self.operands(&instr.uses(), bytecode)?;
+
+        match instr {
+            Instr::Const(_, literal) => {
+                let value = Value::from_literal(literal)
+                    .ok_or_else(|| CompilerError::InvalidConstant(literal.clone()))?;
+                bytecode.push(ByteCode::new(OpCode::Push, vec![Operand::constant(value)])?);
+            }
+            Instr::Load(_, name) => {
+                bytecode.push(ByteCode::new(
+                    OpCode::LoadName,
+                    vec![Operand::Name(name.clone())],
+                )?);
+            }
+            Instr::Store(name, _) => {
+                bytecode.push(ByteCode::new(
+                    OpCode::StoreName,
+                    vec![Operand::Name(name.clone())],
+                )?);
+            }
+            Instr::Binary(_, op, _, _) => {
+                bytecode.push(ByteCode::new(op.to_opcode(), vec![])?);
+            }
+            // 值已经在操作数栈上，作为被定义的临时变量继续跟踪，由 Section 末尾的 `Return` 返回
+            Instr::Copy(_, _) => {}
+            // 值留在操作数栈上，不再被跟踪
+            Instr::Yield(_) => {}
+        }
+
+        if let Some(temp) = instr.def() {
+            if self.named.contains(&temp) {
+                bytecode.push(ByteCode::new(
+                    OpCode::StoreName,
+                    vec![Operand::Name(temp.to_string())],
+                )?);
+            } else if self.uses.get(&temp).copied().unwrap_or(0) == 0 {
+                bytecode.push(ByteCode::new(OpCode::Pop, vec![])?);
+            } else {
+                self.stack.push(temp);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 准备指令的操作数：存入堆中的临时变量被加载到栈顶，并检查所有操作数按顺序位于栈顶，然后将它们出栈
+    fn operands(
+        &mut self,
+        temps: &[Temp],
+        bytecode: &mut Vec<ByteCode>,
+    ) -> Result<(), CompilerError> {
+        for temp in temps {
+            if self.named.contains(temp) {
+                bytecode.push(ByteCode::new(
+                    OpCode::LoadName,
+                    vec![Operand::Name(temp.to_string())],
+                )?);
+                self.stack.push(*temp);
+            }
+        }
+
+        if !self.stack.ends_with(temps) {
+            return Err(CompilerError::TempsNotOnStack(format!("{:?}", temps)));
+        }
+
+        self.stack.truncate(self.stack.len() - temps.len());
+        Ok(())
+    }
+}
+
+/// 返回计算条件 `cond` 的第一条指令的下标，即条件表达式在基本块中开始的位置
+fn condition_start(instrs: &[Instr], cond: Temp) -> usize {
+    let mut pending: Vec<Temp> = vec![cond];
+    let mut start = instrs.len();
+
+    while let Some(temp) = pending.pop() {
+        if let Some(index) = instrs.iter().position(|instr| instr.def() == Some(temp)) {
+            start = start.min(index);
+            pending.extend(instrs[index].uses());
+        }
+    }
+
+    start
+}
+
+/// 计算每个基本块被生成在哪个栈帧中：条件分支的每个分支都是一个新的 Section，拥有独立的栈帧
+fn frames(
+    program: &IrProgram,
+    post_dominators: &HashMap<BlockId, Option<BlockId>>,
+) -> HashMap<BlockId, usize> {
+    let mut frames: HashMap<BlockId, usize> = HashMap::new();
+    // 待处理的区域：(起点, 终点, 栈帧)
+    let mut regions: Vec<(BlockId, Option<BlockId>, usize)> = vec![(program.entry, None, 0)];
+    let mut count = 1;
+
+    while let Some((start, stop, frame)) = regions.pop() {
+        let mut current = Some(start);
+
+        while let Some(block) = current {
+            if Some(block) == stop {
+                break;
+            }
+            frames.insert(block, frame);
+
+            current = match &program.block(block).terminator {
+                Terminator::Jump(target) => Some(*target),
+                Terminator::Branch(_, then_block, else_block) => {
+                    let join = post_dominators.get(&block).copied().flatten();
+                    regions.push((*then_block, join, count));
+                    count += 1;
+                    if Some(*else_block) != join {
+                        regions.push((*else_block, join, count));
+                        count += 1;
+                    }
+                    join
+                }
+                Terminator::Return(_) => None,
+            };
+        }
+    }
+
+    frames
+}
@@ -0,0 +1,351 @@
+use crate::analysis::types::{infer, TypeEnv};
+use crate::compiler::{CompilerError, Located};
+use crate::parser::ast::AstNode;
+use crate::parser::serialize::SpanTree;
+
+use super::{BasicBlock, BlockId, Instr, IrProgram, Temp, Terminator};
+
+/// 将抽象语法树降低为中间表示
+///
+/// 程序顶层的表达式语句的值通过 `yield` 留在操作数栈上，块中表达式语句的值会被丢弃。
+/// if 语句的每个分支被降低为独立的基本块，所有分支最终跳转到同一个汇合块。
+/// 作为值的块与作为值的 if 的分支中的 `rtb` 将值复制到块的结果中，然后跳转到块之后的基本块；
+/// 作为语句的块与 if 语句的分支中的 `rtb` 因此会提前结束外层的作为值的块。不在作为值的块中时，
+/// `rtb` 结束最外层的作为语句的块，值被丢弃。`rtb` 之后的语句不会被执行，因此不被降低。
+pub fn lower(ast: &AstNode) -> Result<IrProgram, CompilerError> {
+    lower_with(ast, &SpanTree::default(), &mut TypeEnv::new()).map_err(|err| err.value)
+}
+
+/// 将抽象语法树降低为中间表示，`spans` 为与 `ast` 对应的区间树，错误附带出错的节点的区间；
+/// `types` 为之前的代码中变量的类型，降低之后包含这段代码中的赋值
+///
+/// 用于逐项编译的程序，使得作为值的 if 的检查能够看到之前的项中变量的类型。
+pub fn lower_with(
+    ast: &AstNode,
+    spans: &SpanTree,
+    types: &mut TypeEnv,
+) -> Result<IrProgram, Located<CompilerError>> {
+    let mut builder = Builder::new(std::mem::take(types));
+
+    match ast {
+        AstNode::Program(nodes) => {
+            for (index, node) in nodes.iter().enumerate() {
+                builder.lower_statement(node, spans.child(index), true)?;
+            }
+        }
+        node => builder.lower_statement(node, spans, true)?,
+    }
+
+    builder.terminate(Terminator::Return(None));
+    *types = std::mem::take(&mut builder.types);
+    Ok(builder.finish())
+}
+
+/// 降低的结果，错误附带出错的节点的区间
+type Lowered<T> = Result<T, Located<CompilerError>>;
+
+struct Builder {
+    blocks: Vec<(Vec<Instr>, Option<Terminator>)>,
+    current: BlockId,
+    temps: usize,
+    /// `rtb` 的目标：值被复制到的临时变量（值不被使用时为 `None`）与块之后的基本块
+    returns: Option<(Option<Temp>, BlockId)>,
+    /// 变量的类型，用于检查作为值的 if 的分支
+    types: TypeEnv,
+}
+
+impl Builder {
+    fn new(types: TypeEnv) -> Self {
+        Self {
+            blocks: vec![(vec![], None)],
+            current: BlockId(0),
+            temps: 0,
+            returns: None,
+            types,
+        }
+    }
+
+    fn finish(self) -> IrProgram {
+        let blocks = self
+            .blocks
+            .into_iter()
+            .enumerate()
+            .map(|(id, (instrs, terminator))| BasicBlock {
+                id: BlockId(id),
+                instrs,
+                terminator: terminator.unwrap_or(Terminator::Return(None)),
+            })
+            .collect();
+
+        IrProgram {
+            blocks,
+            entry: BlockId(0),
+            temps: self.temps,
+        }
+    }
+
+    fn new_temp(&mut self) -> Temp {
+        self.temps += 1;
+        Temp(self.temps - 1)
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push((vec![], None));
+        BlockId(self.blocks.len() - 1)
+    }
+
+    fn emit(&mut self, instr: Instr) {
+        self.blocks[self.current.0].0.push(instr);
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        self.blocks[self.current.0].1 = Some(terminator);
+    }
+
+    /// 当前基本块还没有终结指令时跳转到 `target`，已经被 `rtb` 结束的基本块不受影响
+    fn jump(&mut self, target: BlockId) {
+        if self.blocks[self.current.0].1.is_none() {
+            self.terminate(Terminator::Jump(target));
+        }
+    }
+
+    fn lower_statement(
+        &mut self,
+        node: &AstNode,
+        spans: &SpanTree,
+        top_level: bool,
+    ) -> Lowered<()> {
+        let located = |err: CompilerError| Located::new(err, spans.span);
+
+        match node {
+            AstNode::Expr(_, _, _) | AstNode::Identifier(_) | AstNode::Constant(_) => {
+                let temp = self.lower_expr(node, spans)?;
+                if top_level {
+                    self.emit(Instr::Yield(temp));
+                }
+            }
+            AstNode::Assign(identifier, _, value) | AstNode::SetValue(identifier, value) => {
+                // 值总是最后一个子节点
+                let temp = self.lower_expr(value, spans.child(node.children().len() - 1))?;
+                match identifier.as_ref() {
+                    AstNode::Identifier(name) => {
+                        let ty = infer(value, &|name| self.types.lookup(name));
+                        self.types.assign(name, ty);
+                        self.emit(Instr::Store(name.clone(), temp));
+                    }
+                    node => {
+                        return Err(Located::new(
+                            CompilerError::ExpectedIdentifier(node.as_code()),
+                            spans.child(0).span,
+                        ))
+                    }
+                }
+            }
+            // 不在作为值的块中、且含有 `rtb` 的块需要一个 `rtb` 可以跳转到的基本块
+            AstNode::Block(nodes) if self.returns.is_none() && node.contains_return() => {
+                let exit = self.new_block();
+                self.returns = Some((None, exit));
+                self.types.enter();
+                let lowered = self.lower_statements(nodes, spans);
+                self.types.leave();
+                self.returns = None;
+                lowered?;
+
+                self.jump(exit);
+                self.current = exit;
+            }
+            AstNode::Block(nodes) => self.lower_statements(nodes, spans)?,
+            AstNode::ReturnBlock(value) => {
+                let (result, exit) = self
+                    .returns
+                    .ok_or_else(|| located(CompilerError::ReturnOutsideBlock(node.as_code())))?;
+                let value = self.lower_expr(value, spans.child(0))?;
+                if let Some(result) = result {
+                    self.emit(Instr::Copy(result, value));
+                }
+                self.terminate(Terminator::Jump(exit));
+            }
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                self.lower_if(cond, block, elif_nodes, else_node.as_deref(), spans, None)?;
+            }
+            AstNode::Error(code) => return Err(located(CompilerError::SyntaxErrors(code.clone()))),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// 降低块中的语句，直到当前基本块被 `rtb` 结束
+    fn lower_statements(&mut self, nodes: &[AstNode], spans: &SpanTree) -> Lowered<()> {
+        for (index, node) in nodes.iter().enumerate() {
+            self.lower_statement(node, spans.child(index), false)?;
+            if self.blocks[self.current.0].1.is_some() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 降低作为值的块，块中的 `rtb` 将值复制到 `result` 中并跳转到 `exit`
+    fn lower_value_block(
+        &mut self,
+        block: &AstNode,
+        spans: &SpanTree,
+        result: Temp,
+        exit: BlockId,
+    ) -> Lowered<()> {
+        let located = |err: CompilerError| Located::new(err, spans.span);
+        let nodes = match block {
+            AstNode::Block(nodes) => nodes,
+            node => return Err(located(CompilerError::ExpectedBlock(node.as_code()))),
+        };
+        // 块的最后总是以 `rtb` 结束，因此每条路径都会给出值
+        if block.block_value().is_none() {
+            return Err(located(CompilerError::BlockWithoutValue(block.as_code())));
+        }
+
+        let returns = self.returns.replace((Some(result), exit));
+        self.types.enter();
+        let lowered = self.lower_statements(nodes, spans);
+        self.types.leave();
+        self.returns = returns;
+        lowered
+    }
+
+    /// 降低 if，作为值时每个分支的值被复制到 `result` 中
+    ///
+    /// 分支的条件与块的区间树由 `spans` 中对应的子节点给出，与 `AstNode::children` 的顺序相同。
+    fn lower_if(
+        &mut self,
+        cond: &AstNode,
+        block: &AstNode,
+        elif_nodes: &[AstNode],
+        else_node: Option<&AstNode>,
+        spans: &SpanTree,
+        result: Option<Temp>,
+    ) -> Lowered<()> {
+        let mut branches: Vec<(&AstNode, &SpanTree, &AstNode, &SpanTree)> =
+            vec![(cond, spans.child(0), block, spans.child(1))];
+        for (index, node) in elif_nodes.iter().enumerate() {
+            let elif_spans = spans.child(2 + index);
+            match node {
+                AstNode::Elif(cond, block) => {
+                    branches.push((cond, elif_spans.child(0), block, elif_spans.child(1)))
+                }
+                node => {
+                    return Err(Located::new(
+                        CompilerError::UnexpectedElif(node.as_code()),
+                        elif_spans.span,
+                    ))
+                }
+            }
+        }
+        let else_spans = spans.child(2 + elif_nodes.len());
+        let else_block = match else_node {
+            Some(AstNode::Else(block)) => Some((block.as_ref(), else_spans.child(0))),
+            Some(node) => {
+                return Err(Located::new(
+                    CompilerError::UnexpectedElse(node.as_code()),
+                    else_spans.span,
+                ))
+            }
+            None => None,
+        };
+
+        let join = self.new_block();
+        let count = branches.len();
+        // 出错时整个降低过程都会终止，因此不需要在出错时离开条件执行的代码
+        self.types.enter();
+
+        for (index, (cond, cond_spans, block, block_spans)) in branches.into_iter().enumerate() {
+            let cond = self.lower_expr(cond, cond_spans)?;
+            let then_block = self.new_block();
+            let next_block = if index + 1 < count || else_block.is_some() {
+                self.new_block()
+            } else {
+                join
+            };
+
+            self.terminate(Terminator::Branch(cond, then_block, next_block));
+
+            self.current = then_block;
+            self.lower_branch(block, block_spans, result, join)?;
+            self.jump(join);
+
+            self.current = next_block;
+        }
+
+        if let Some((block, block_spans)) = else_block {
+            self.lower_branch(block, block_spans, result, join)?;
+            self.jump(join);
+            self.current = join;
+        }
+
+        self.types.leave();
+        Ok(())
+    }
+
+    fn lower_branch(
+        &mut self,
+        block: &AstNode,
+        spans: &SpanTree,
+        result: Option<Temp>,
+        join: BlockId,
+    ) -> Lowered<()> {
+        match result {
+            Some(result) => self.lower_value_block(block, spans, result, join),
+            None => self.lower_statement(block, spans, false),
+        }
+    }
+
+    fn lower_expr(&mut self, node: &AstNode, spans: &SpanTree) -> Lowered<Temp> {
+        let located = |err: CompilerError| Located::new(err, spans.span);
+
+        match node {
+            AstNode::Expr(left, Some(op), Some(right)) => {
+                let lhs = self.lower_expr(left, spans.child(0))?;
+                let rhs = self.lower_expr(right, spans.child(1))?;
+                let temp = self.new_temp();
+                self.emit(Instr::Binary(temp, op.clone(), lhs, rhs));
+                Ok(temp)
+            }
+            AstNode::Expr(_, Some(_), None) => Err(located(CompilerError::MissingOperand)),
+            // `format_ast` 加上的包装节点与被包装的节点共用区间树
+            AstNode::Expr(left, None, _) => self.lower_expr(left, spans),
+            AstNode::Identifier(name) => {
+                let temp = self.new_temp();
+                self.emit(Instr::Load(temp, name.clone()));
+                Ok(temp)
+            }
+            AstNode::Constant(literal) => {
+                let temp = self.new_temp();
+                self.emit(Instr::Const(temp, literal.clone()));
+                Ok(temp)
+            }
+            AstNode::Block(_) => {
+                let result = self.new_temp();
+                let exit = self.new_block();
+                self.lower_value_block(node, spans, result, exit)?;
+                self.current = exit;
+                Ok(result)
+            }
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                let result = self.new_temp();
+                self.lower_if(
+                    cond,
+                    block,
+                    elif_nodes,
+                    else_node.as_deref(),
+                    spans,
+                    Some(result),
+                )?;
+                // 分支中的赋值已经被记录，变量的类型与分支中的 `rtb` 执行时一致或者更不确定
+                node.check_if_value(&|name| self.types.lookup(name))
+                    .map_err(located)?;
+                Ok(result)
+            }
+            node => Err(located(CompilerError::ExpectedExpression(node.as_code()))),
+        }
+    }
+}
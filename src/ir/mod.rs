@@ -0,0 +1,231 @@
+pub mod emit;
+pub mod lower;
+pub mod regalloc;
+pub mod register;
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+
+use crate::parser::BinaryOp;
+
+pub use lower::{lower, lower_with};
+
+/// 虚拟临时变量，在输出中显示为 `%n`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Temp(pub usize);
+
+/// 基本块的编号，在输出中显示为 `bbn`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub usize);
+
+/// 基本块中的指令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    /// `%t = const literal`，将常量的字面量赋值给临时变量
+    Const(Temp, String),
+    /// `%t = load name`，从堆中读取变量
+    Load(Temp, String),
+    /// `store name, %t`，将临时变量的值存入堆中
+    Store(String, Temp),
+    /// `%t = %s`，复制临时变量，作为值的 if 的每个分支以此将值写入同一个临时变量
+    Copy(Temp, Temp),
+    /// `%t = op %a, %b`，二元运算
+    Binary(Temp, BinaryOp, Temp, Temp),
+    /// `yield %t`，将临时变量的值作为程序的结果留在操作数栈上，仅用于程序顶层的表达式语句
+    Yield(Temp),
+}
+
+/// 基本块的终结指令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    /// 无条件跳转到目标基本块
+    Jump(BlockId),
+    /// 条件为 true 时跳转到第一个基本块，否则跳转到第二个基本块
+    Branch(Temp, BlockId, BlockId),
+    /// 结束程序，可以带有一个返回值
+    Return(Option<Temp>),
+}
+
+/// 基本块：一串顺序执行的指令，以一条终结指令结尾
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub id: BlockId,
+    pub instrs: Vec<Instr>,
+    pub terminator: Terminator,
+}
+
+/// 中间表示形式的程序，由若干基本块组成
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrProgram {
+    /// 基本块列表，下标与 `BlockId` 一致
+    pub blocks: Vec<BasicBlock>,
+    /// 入口基本块
+    pub entry: BlockId,
+    /// 临时变量的数量
+    pub temps: usize,
+}
+
+impl Instr {
+    /// 返回指令定义的临时变量
+    pub fn def(&self) -> Option<Temp> {
+        match self {
+            Instr::Const(temp, _)
+            | Instr::Load(temp, _)
+            | Instr::Copy(temp, _)
+            | Instr::Binary(temp, _, _, _) => Some(*temp),
+            Instr::Store(_, _) | Instr::Yield(_) => None,
+        }
+    }
+
+    /// 返回指令使用的临时变量，按操作数顺序排列
+    pub fn uses(&self) -> Vec<Temp> {
+        match self {
+            Instr::Binary(_, _, lhs, rhs) => vec![*lhs, *rhs],
+            Instr::Store(_, temp) | Instr::Copy(_, temp) | Instr::Yield(temp) => vec![*temp],
+            Instr::Const(_, _) | Instr::Load(_, _) => vec![],
+        }
+    }
+}
+
+impl Terminator {
+    /// 返回终结指令的后继基本块
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(target) => vec![*target],
+            Terminator::Branch(_, then_block, else_block) => vec![*then_block, *else_block],
+            Terminator::Return(_) => vec![],
+        }
+    }
+
+    /// 返回终结指令使用的临时变量
+    pub fn uses(&self) -> Vec<Temp> {
+        match self {
+            Terminator::Branch(cond, _, _) => vec![*cond],
+            Terminator::Return(Some(temp)) => vec![*temp],
+            Terminator::Jump(_) | Terminator::Return(None) => vec![],
+        }
+    }
+}
+
+impl IrProgram {
+    /// 返回编号为 `id` 的基本块
+    pub fn block(&self, id: BlockId) -> &BasicBlock {
+        &self.blocks[id.0]
+    }
+
+    /// 返回从入口可达的基本块的逆后序
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        let mut visited: HashSet<BlockId> = HashSet::new();
+        let mut order: Vec<BlockId> = vec![];
+        // 显式栈：(基本块, 是否已经访问过后继)。后继按顺序入栈，使得 then 分支在逆后序中排在 else 分支之前
+        let mut stack: Vec<(BlockId, bool)> = vec![(self.entry, false)];
+
+        while let Some((block, expanded)) = stack.pop() {
+            if expanded {
+                order.push(block);
+                continue;
+            }
+            if !visited.insert(block) {
+                continue;
+            }
+
+            stack.push((block, true));
+            for successor in self.block(block).terminator.successors() {
+                if !visited.contains(&successor) {
+                    stack.push((successor, false));
+                }
+            }
+        }
+
+        order.reverse();
+        order
+    }
+
+    /// 计算每个基本块的直接后支配块，没有后支配块（如以 `return` 结尾）的基本块对应 `None`
+    pub fn immediate_post_dominators(&self) -> HashMap<BlockId, Option<BlockId>> {
+        let mut post_dominators: HashMap<BlockId, BTreeSet<BlockId>> = HashMap::new();
+
+        // 控制流图无环，按后序处理时每个基本块的后继都已经处理过
+        for block in self.reverse_postorder().into_iter().rev() {
+            let mut set: Option<BTreeSet<BlockId>> = None;
+            for successor in self.block(block).terminator.successors() {
+                let successor = post_dominators.get(&successor).cloned().unwrap_or_default();
+                set = Some(match set {
+                    Some(set) => set.intersection(&successor).copied().collect(),
+                    None => successor,
+                });
+            }
+
+            let mut set = set.unwrap_or_default();
+            set.insert(block);
+            post_dominators.insert(block, set);
+        }
+
+        post_dominators
+            .iter()
+            .map(|(block, set)| {
+                let immediate = set
+                    .iter()
+                    .filter(|candidate| *candidate != block)
+                    .max_by_key(|candidate| post_dominators[*candidate].len())
+                    .copied();
+                (*block, immediate)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Temp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Instr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instr::Const(temp, literal) => write!(f, "{} = const {}", temp, literal),
+            Instr::Load(temp, name) => write!(f, "{} = load {}", temp, name),
+            Instr::Store(name, temp) => write!(f, "store {}, {}", name, temp),
+            Instr::Copy(temp, source) => write!(f, "{} = {}", temp, source),
+            Instr::Binary(temp, op, lhs, rhs) => {
+                write!(f, "{} = {} {}, {}", temp, op.as_raw(), lhs, rhs)
+            }
+            Instr::Yield(temp) => write!(f, "yield {}", temp),
+        }
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(target) => write!(f, "jump {}", target),
+            Terminator::Branch(cond, then_block, else_block) => {
+                write!(f, "branch {}, {}, {}", cond, then_block, else_block)
+            }
+            Terminator::Return(Some(temp)) => write!(f, "return {}", temp),
+            Terminator::Return(None) => write!(f, "return"),
+        }
+    }
+}
+
+impl fmt::Display for IrProgram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for block in self.reverse_postorder() {
+            let block = self.block(block);
+            writeln!(f, "{}:", block.id)?;
+            for instr in &block.instrs {
+                writeln!(f, "    {}", instr)?;
+            }
+            writeln!(f, "    {}", block.terminator)?;
+        }
+
+        Ok(())
+    }
+}
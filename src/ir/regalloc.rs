@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use crate::compiler::register::{Reg, Slot};
+
+use super::{BlockId, IrProgram, Temp};
+
+/// 临时变量的活跃区间，端点是指令在线性化后的位置，定义处为起点，最后一次使用处为终点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval {
+    pub temp: Temp,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// 临时变量被分配到的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Register(Reg),
+    Spilled(Slot),
+}
+
+/// 寄存器分配的结果
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Allocation {
+    pub locations: HashMap<Temp, Location>,
+    /// 使用的溢出槽数量
+    pub slots: usize,
+}
+
+impl IrProgram {
+    /// 计算按 `order` 线性化后每个临时变量的活跃区间，按起点排列
+    ///
+    /// 每条指令与每条终结指令各占一个位置。控制流图无环，而 `order` 是逆后序时，
+    /// 定义支配所有的使用，临时变量只在定义与最后一次使用之间的位置上活跃。
+    /// 作为值的 if 的结果在每个分支中各被定义一次，区间从第一次定义开始。
+    pub fn live_intervals(&self, order: &[BlockId]) -> Vec<Interval> {
+        let mut intervals: HashMap<Temp, Interval> = HashMap::new();
+        let mut position = 0;
+
+        for block in order {
+            let block = self.block(*block);
+            let uses = block
+                .instrs
+                .iter()
+                .map(|instr| (instr.uses(), instr.def()))
+                .chain([(block.terminator.uses(), None)]);
+
+            for (used, def) in uses {
+                for temp in used {
+                    if let Some(interval) = intervals.get_mut(&temp) {
+                        interval.end = interval.end.max(position);
+                    }
+                }
+                if let Some(temp) = def {
+                    intervals
+                        .entry(temp)
+                        .and_modify(|interval| interval.end = interval.end.max(position))
+                        .or_insert(Interval {
+                            temp,
+                            start: position,
+                            end: position,
+                        });
+                }
+                position += 1;
+            }
+        }
+
+        let mut intervals: Vec<Interval> = intervals.into_values().collect();
+        intervals.sort_by_key(|interval| (interval.start, interval.temp));
+        intervals
+    }
+}
+
+/// 线性扫描寄存器分配
+///
+/// 按起点依次处理活跃区间，终点不晚于当前起点的区间释放它们的寄存器（指令先读取操作数再写入结果，
+/// 因此结果可以复用最后一次使用的操作数的寄存器）。没有空闲的寄存器时，溢出终点最晚的区间。
+pub fn linear_scan(intervals: &[Interval], registers: usize) -> Allocation {
+    let mut allocation = Allocation::default();
+    let mut free: Vec<Reg> = (0..registers).rev().map(Reg).collect();
+    // 占用寄存器的区间，按终点排列
+    let mut active: Vec<(Interval, Reg)> = vec![];
+
+    for interval in intervals {
+        active.retain(|(active, reg)| {
+            let expired = active.end <= interval.start;
+            if expired {
+                free.push(*reg);
+            }
+            !expired
+        });
+
+        let reg = match free.pop() {
+            Some(reg) => reg,
+            None => {
+                let spill_slot = Location::Spilled(Slot(allocation.slots));
+                allocation.slots += 1;
+
+                match active.last() {
+                    // 从终点最晚的区间手中接过寄存器
+                    Some((last, reg)) if last.end > interval.end => {
+                        let reg = *reg;
+                        allocation.locations.insert(last.temp, spill_slot);
+                        active.pop();
+                        reg
+                    }
+                    _ => {
+                        allocation.locations.insert(interval.temp, spill_slot);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        allocation
+            .locations
+            .insert(interval.temp, Location::Register(reg));
+        let index = active.partition_point(|(active, _)| active.end <= interval.end);
+        active.insert(index, (*interval, reg));
+    }
+
+    allocation
+}
@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use crate::compiler::register::{Reg, RegisterInstr, RegisterProgram};
+use crate::compiler::value::Value;
+use crate::compiler::CompilerError;
+
+use super::regalloc::{linear_scan, Allocation, Location};
+use super::{BlockId, Instr, IrProgram, Temp, Terminator};
+
+/// 为载入溢出的操作数保留的寄存器数量，二元运算最多需要同时载入两个操作数
+pub const SCRATCH_REGISTERS: usize = 2;
+
+impl IrProgram {
+    /// 将中间表示生成为使用 `registers` 个寄存器的寄存器指令
+    ///
+    /// 基本块按逆后序排列，跳转到紧随其后的基本块时省略跳转指令。最后 `SCRATCH_REGISTERS`
+    /// 个寄存器保留给溢出的临时变量，其余的寄存器由线性扫描分配给临时变量。
+    pub fn emit_registers(&self, registers: usize) -> Result<RegisterProgram, CompilerError> {
+        if registers < SCRATCH_REGISTERS {
+            return Err(CompilerError::TooFewRegisters(SCRATCH_REGISTERS, registers));
+        }
+
+        let order = self.reverse_postorder();
+        let allocatable = registers - SCRATCH_REGISTERS;
+        let allocation = linear_scan(&self.live_intervals(&order), allocatable);
+
+        let mut emitter = RegisterEmitter {
+            allocation: &allocation,
+            scratch: Reg(allocatable),
+            code: vec![],
+            labels: HashMap::new(),
+            patches: vec![],
+        };
+
+        for (index, block_id) in order.iter().enumerate() {
+            let next = order.get(index + 1).copied();
+            emitter.labels.insert(*block_id, emitter.code.len());
+            emitter.emit_block(self, *block_id, next)?;
+        }
+
+        for (index, target) in emitter.patches {
+            let label = emitter.labels[&target];
+            match &mut emitter.code[index] {
+                RegisterInstr::Jump(pc) | RegisterInstr::JumpIfNot(_, pc) => *pc = label,
+                _ => unreachable!(),
+            }
+        }
+
+        Ok(RegisterProgram {
+            code: emitter.code,
+            registers,
+            slots: allocation.slots,
+        })
+    }
+}
+
+struct RegisterEmitter<'a> {
+    allocation: &'a Allocation,
+    /// 第一个保留的寄存器
+    scratch: Reg,
+    code: Vec<RegisterInstr>,
+    /// 基本块的第一条指令的下标
+    labels: HashMap<BlockId, usize>,
+    /// 需要回填目标的跳转指令的下标与目标基本块
+    patches: Vec<(usize, BlockId)>,
+}
+
+impl RegisterEmitter<'_> {
+    fn emit_block(
+        &mut self,
+        program: &IrProgram,
+        block_id: BlockId,
+        next: Option<BlockId>,
+    ) -> Result<(), CompilerError> {
+        let block = program.block(block_id);
+
+        for instr in &block.instrs {
+            match instr {
+                Instr::Const(temp, literal) => {
+                    let value = Value::from_literal(literal)
+                        .ok_or_else(|| CompilerError::InvalidConstant(literal.clone()))?;
+                    let reg = self.target(*temp);
+                    self.code.push(RegisterInstr::Const(reg, value));
+                    self.spill(*temp, reg);
+                }
+                Instr::Load(temp, name) => {
+                    let reg = self.target(*temp);
+                    self.code.push(RegisterInstr::Load(reg, name.clone()));
+                    self.spill(*temp, reg);
+                }
+                Instr::Store(name, temp) => {
+                    let reg = self.operand(*temp, 0)?;
+                    self.code.push(RegisterInstr::Store(name.clone(), reg));
+                }
+                Instr::Copy(temp, source) => {
+                    let source = self.operand(*source, 0)?;
+                    let reg = self.target(*temp);
+                    self.code.push(RegisterInstr::Move(reg, source));
+                    self.spill(*temp, reg);
+                }
+                Instr::Binary(temp, op, lhs, rhs) => {
+                    let lhs = self.operand(*lhs, 0)?;
+                    let rhs = self.operand(*rhs, 1)?;
+                    let reg = self.target(*temp);
+                    self.code
+                        .push(RegisterInstr::Binary(reg, op.clone(), lhs, rhs));
+                    self.spill(*temp, reg);
+                }
+                Instr::Yield(temp) => {
+                    let reg = self.operand(*temp, 0)?;
+                    self.code.push(RegisterInstr::Yield(reg));
+                }
+            }
+        }
+
+        match &block.terminator {
+            Terminator::Jump(target) => self.jump(*target, next),
+            Terminator::Branch(cond, then_block, else_block) => {
+                let cond = self.operand(*cond, 0)?;
+                self.patches.push((self.code.len(), *else_block));
+                self.code.push(RegisterInstr::JumpIfNot(cond, 0));
+                self.jump(*then_block, next);
+            }
+            Terminator::Return(temp) => {
+                if let Some(temp) = temp {
+                    let reg = self.operand(*temp, 0)?;
+                    self.code.push(RegisterInstr::Yield(reg));
+                }
+                self.code.push(RegisterInstr::Halt);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn jump(&mut self, target: BlockId, next: Option<BlockId>) {
+        if Some(target) != next {
+            self.patches.push((self.code.len(), target));
+            self.code.push(RegisterInstr::Jump(0));
+        }
+    }
+
+    /// 返回保存临时变量的寄存器，溢出的临时变量被载入第 `index` 个保留的寄存器
+    fn operand(&mut self, temp: Temp, index: usize) -> Result<Reg, CompilerError> {
+        match self.allocation.locations.get(&temp) {
+            Some(Location::Register(reg)) => Ok(*reg),
+            Some(Location::Spilled(slot)) => {
+                let reg = Reg(self.scratch.0 + index);
+                self.code.push(RegisterInstr::Reload(reg, *slot));
+                Ok(reg)
+            }
+            None => Err(CompilerError::UndefinedTemp(temp.to_string())),
+        }
+    }
+
+    /// 返回写入临时变量的寄存器，溢出的临时变量先写入第一个保留的寄存器
+    fn target(&self, temp: Temp) -> Reg {
+        match self.allocation.locations.get(&temp) {
+            Some(Location::Register(reg)) => *reg,
+            _ => self.scratch,
+        }
+    }
+
+    /// 将写入保留寄存器的溢出临时变量保存到溢出槽中
+    fn spill(&mut self, temp: Temp, reg: Reg) {
+        if let Some(Location::Spilled(slot)) = self.allocation.locations.get(&temp) {
+            self.code.push(RegisterInstr::Spill(*slot, reg));
+        }
+    }
+}
@@ -1 +1,15 @@
+pub mod analysis;
+pub mod cli;
+pub mod compiler;
+pub mod explain;
+pub mod format;
+// 程序生成器只供测试与 `fuzz` 目录中的模糊测试目标使用
+#[cfg(any(test, feature = "fuzz"))]
+pub mod fuzz;
+pub mod interpreter;
+pub mod ir;
+pub mod lsp;
+pub mod optimizer;
 pub mod parser;
+#[cfg(test)]
+mod tests;
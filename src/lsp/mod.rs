@@ -0,0 +1,353 @@
+//! 语言服务器
+//!
+//! 通过标准输入输出与编辑器以 LSP 协议通信，支持以下功能：
+//!
+//! - 打开与修改文档时发布解析与编译的诊断信息（`textDocument/publishDiagnostics`）；
+//! - 悬停显示变量推断出的类型（`textDocument/hover`）；
+//! - 跳转到 `let` 声明与查找变量的所有引用（`textDocument/definition`、`textDocument/references`）；
+//! - 列出文档中声明的变量（`textDocument/documentSymbol`）；
+//! - 格式化文档（`textDocument/formatting`）。
+//!
+//! 文档只支持全量同步。
+
+pub mod position;
+pub mod transport;
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::analysis::{analyze, Analysis, ReferenceKind, Severity};
+use crate::format::format;
+use crate::parser::span::Span;
+use position::{offset, range};
+pub use transport::{read_message, write_message};
+
+#[derive(Error, Debug)]
+pub enum LspError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Parse error: {0}")]
+    Parse(serde_json::Error),
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+}
+
+/// JSON-RPC 的错误码
+mod error_code {
+    pub const PARSE_ERROR: i64 = -32700;
+    pub const INVALID_REQUEST: i64 = -32600;
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    pub const INVALID_PARAMS: i64 = -32602;
+    pub const SERVER_NOT_INITIALIZED: i64 = -32002;
+}
+
+/// 服务器支持的请求
+const REQUESTS: &[&str] = &[
+    "textDocument/hover",
+    "textDocument/definition",
+    "textDocument/references",
+    "textDocument/documentSymbol",
+    "textDocument/formatting",
+];
+
+/// LSP 中变量的符号种类
+const SYMBOL_KIND_VARIABLE: u32 = 13;
+
+/// 运行语言服务器，直到收到 `exit` 通知或输入结束
+///
+/// 内容不是合法 JSON 的消息以解析错误回复，之后继续处理后续的消息。
+pub fn run<R: BufRead, W: Write>(mut reader: R, writer: W) -> Result<(), LspError> {
+    let mut server = Server::new(writer);
+
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(LspError::Parse(err)) => {
+                server.parse_error(&err)?;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        if !server.handle(&message)? {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// 打开的文档及其分析结果
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+pub struct Server<W: Write> {
+    writer: W,
+    documents: HashMap<String, Document>,
+    initialized: bool,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            documents: HashMap::new(),
+            initialized: false,
+            shutdown: false,
+        }
+    }
+
+    /// 回复无法解析的消息，请求的 `id` 未知，因此为 `null`
+    pub fn parse_error(&mut self, err: &serde_json::Error) -> Result<(), LspError> {
+        let response = json!({
+            "jsonrpc": "2.0",
+            "id": Value::Null,
+            "error": { "code": error_code::PARSE_ERROR, "message": err.to_string() },
+        });
+        write_message(&mut self.writer, &response)
+    }
+
+    /// 处理一条消息，收到 `exit` 通知时返回 `false`
+    pub fn handle(&mut self, message: &Value) -> Result<bool, LspError> {
+        let method = message.get("method").and_then(Value::as_str);
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let Some(id) = message.get("id").cloned() else {
+            // 通知
+            match method {
+                Some("exit") => return Ok(false),
+                Some(method) if self.initialized && !self.shutdown => {
+                    self.notification(method, &params)?
+                }
+                _ => {}
+            }
+            return Ok(true);
+        };
+
+        let Some(method) = method else {
+            // 客户端对服务器请求的响应，服务器不发送请求，忽略
+            return Ok(true);
+        };
+
+        let response = match method {
+            "initialize" => {
+                self.initialized = true;
+                Ok(capabilities())
+            }
+            _ if !self.initialized => Err((
+                error_code::SERVER_NOT_INITIALIZED,
+                "server is not initialized".to_string(),
+            )),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            _ if self.shutdown => Err((
+                error_code::INVALID_REQUEST,
+                "server is shutting down".to_string(),
+            )),
+            method => self.request(method, &params),
+        };
+
+        let response = match response {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": code, "message": message },
+            }),
+        };
+        write_message(&mut self.writer, &response)?;
+        Ok(true)
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Result<(), LspError> {
+        let Some(uri) = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+        else {
+            return Ok(());
+        };
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params.pointer("/textDocument/text").and_then(Value::as_str);
+                if let Some(text) = text {
+                    self.update(&uri, text.to_string())?;
+                }
+            }
+            "textDocument/didChange" => {
+                let changes = params.get("contentChanges").and_then(Value::as_array);
+                let text = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Value::as_str);
+                if let Some(text) = text {
+                    self.update(&uri, text.to_string())?;
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish(&uri, vec![])?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        if !REQUESTS.contains(&method) {
+            return Err((
+                error_code::METHOD_NOT_FOUND,
+                format!("method not found: {}", method),
+            ));
+        }
+
+        let uri = params
+            .pointer("/textDocument/uri")
+            .and_then(Value::as_str)
+            .ok_or((
+                error_code::INVALID_PARAMS,
+                "missing textDocument.uri".to_string(),
+            ))?;
+        let document = self.documents.get(uri).ok_or((
+            error_code::INVALID_PARAMS,
+            format!("unknown document: {}", uri),
+        ))?;
+        let text = &document.text;
+        let symbols = &document.analysis.symbols;
+        let cursor = || {
+            params
+                .get("position")
+                .and_then(|position| offset(text, position))
+                .ok_or((error_code::INVALID_PARAMS, "invalid position".to_string()))
+        };
+        let location = |span| json!({ "uri": uri, "range": range(text, span) });
+
+        match method {
+            "textDocument/hover" => {
+                let Some(reference) = symbols.reference_at(cursor()?) else {
+                    return Ok(Value::Null);
+                };
+                let name = &text[reference.span.start..reference.span.end];
+                let code = match reference.kind {
+                    ReferenceKind::Declaration => format!("let {}: {}", name, reference.ty),
+                    _ => format!("{}: {}", name, reference.ty),
+                };
+                Ok(json!({
+                    "contents": { "kind": "markdown", "value": format!("```hare\n{}\n```", code) },
+                    "range": range(text, reference.span),
+                }))
+            }
+            "textDocument/definition" => Ok(symbols
+                .definition_at(cursor()?)
+                .map_or(Value::Null, |symbol| location(symbol.span))),
+            "textDocument/references" => {
+                let declaration = params
+                    .pointer("/context/includeDeclaration")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(true);
+                let Some(symbol) = symbols
+                    .reference_at(cursor()?)
+                    .and_then(|reference| reference.symbol)
+                else {
+                    return Ok(Value::Null);
+                };
+                Ok(symbols
+                    .references_of(symbol)
+                    .into_iter()
+                    .filter(|reference| declaration || reference.kind != ReferenceKind::Declaration)
+                    .map(|reference| location(reference.span))
+                    .collect())
+            }
+            "textDocument/documentSymbol" => Ok(symbols
+                .symbols
+                .iter()
+                .map(|symbol| {
+                    json!({
+                        "name": symbol.name,
+                        "detail": symbol.ty.name(),
+                        "kind": SYMBOL_KIND_VARIABLE,
+                        "range": range(text, symbol.statement),
+                        "selectionRange": range(text, symbol.span),
+                    })
+                })
+                .collect()),
+            "textDocument/formatting" => match format(text) {
+                Ok(formatted) if formatted == *text => Ok(json!([])),
+                Ok(formatted) => Ok(json!([{
+                    "range": range(text, Span::new(0, text.len())),
+                    "newText": formatted,
+                }])),
+                // 有语法错误的文档不进行格式化
+                Err(_) => Ok(Value::Null),
+            },
+            _ => unreachable!("unsupported requests are rejected above"),
+        }
+    }
+
+    /// 更新文档内容，重新分析并发布诊断信息
+    fn update(&mut self, uri: &str, text: String) -> Result<(), LspError> {
+        let analysis = analyze(&text);
+        let diagnostics = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let message = match &diagnostic.hint {
+                    Some(hint) => format!("{}\nhint: {}", diagnostic.message, hint),
+                    None => diagnostic.message.clone(),
+                };
+                json!({
+                    "range": range(&text, diagnostic.span),
+                    "severity": match diagnostic.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2,
+                    },
+                    "source": "hare",
+                    "message": message,
+                })
+            })
+            .collect();
+
+        self.documents
+            .insert(uri.to_string(), Document { text, analysis });
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Value>) -> Result<(), LspError> {
+        write_message(
+            &mut self.writer,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": uri, "diagnostics": diagnostics },
+            }),
+        )
+    }
+}
+
+/// `initialize` 请求的结果，声明服务器支持的功能
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            // 全量同步
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "documentSymbolProvider": true,
+            "documentFormattingProvider": true,
+        },
+        "serverInfo": { "name": "hare", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
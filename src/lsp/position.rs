@@ -0,0 +1,50 @@
+use serde_json::{json, Value};
+
+use crate::parser::span::Span;
+
+/// 将字节偏移量转换为 LSP 的位置，行号从 0 开始，列号以 UTF-16 编码单元计
+pub fn position(text: &str, offset: usize) -> Value {
+    let offset = floor_char_boundary(text, offset);
+    let before = &text[..offset];
+    let line = before.matches('\n').count();
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    let character: usize = before[line_start..].chars().map(char::len_utf16).sum();
+
+    json!({ "line": line, "character": character })
+}
+
+/// 将区间转换为 LSP 的范围
+pub fn range(text: &str, span: Span) -> Value {
+    json!({ "start": position(text, span.start), "end": position(text, span.end) })
+}
+
+/// 将 LSP 的位置转换为字节偏移量，超出行尾或文本末尾的位置被截断
+pub fn offset(text: &str, position: &Value) -> Option<usize> {
+    let line = position.get("line")?.as_u64()? as usize;
+    let character = position.get("character")?.as_u64()? as usize;
+
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(index) => line_start += index + 1,
+            None => return Some(text.len()),
+        }
+    }
+
+    let mut units = 0;
+    for (index, c) in text[line_start..].char_indices() {
+        if c == '\n' || units >= character {
+            return Some(line_start + index);
+        }
+        units += c.len_utf16();
+    }
+    Some(text.len())
+}
+
+fn floor_char_boundary(text: &str, offset: usize) -> usize {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
@@ -0,0 +1,71 @@
+use std::io::{BufRead, Write};
+
+use serde_json::Value;
+
+use super::LspError;
+
+/// 单条消息内容的最大字节数，超过时拒绝读取，避免按头部声明的长度分配任意大的内存
+pub const MAX_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
+
+/// 读取一条 JSON-RPC 消息
+///
+/// 消息由若干行以 `\r\n` 结尾的头部、一个空行以及长度为 `Content-Length` 字节的 JSON 内容组成。
+///
+/// # 返回值
+///
+/// 返回解析出的消息；输入在消息开始之前结束时返回 `Ok(None)`。
+/// 内容不是合法的 JSON 时返回 [`LspError::Parse`]，此时整条消息已被读完，可以继续读取下一条。
+pub fn read_message<R: BufRead>(reader: &mut R) -> Result<Option<Value>, LspError> {
+    let mut length: Option<usize> = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(LspError::Protocol("unexpected end of headers".to_string())),
+            };
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(LspError::Protocol(format!("malformed header: {}", line)));
+        };
+        if name.trim().eq_ignore_ascii_case("Content-Length") {
+            length =
+                Some(value.trim().parse().map_err(|_| {
+                    LspError::Protocol(format!("invalid Content-Length: {}", value))
+                })?);
+        }
+    }
+
+    let length = length.ok_or(LspError::Protocol("missing Content-Length".to_string()))?;
+    if length > MAX_CONTENT_LENGTH {
+        return Err(LspError::Protocol(format!(
+            "Content-Length {} exceeds the limit of {} bytes",
+            length, MAX_CONTENT_LENGTH
+        )));
+    }
+    let mut content = vec![0u8; length];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(LspError::Parse)
+}
+
+/// 写入一条 JSON-RPC 消息
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> Result<(), LspError> {
+    let content = serde_json::to_string(message)?;
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use crate::analysis::types::Type;
+use crate::compiler::value::Value;
+use crate::compiler::{CompilerWarning, Located};
+use crate::parser::ast::AstNode;
+use crate::parser::errors::Label;
+use crate::parser::serialize::SpanTree;
+use crate::parser::span::Span;
+
+use super::fold::constant_value;
+use super::Optimized;
+
+/// 死分支消除
+///
+/// 条件为布尔常量的 if 语句会被化简：条件恒为假的分支被丢弃，条件恒为真的分支之后的所有分支也被丢弃。
+/// 如果化简后只剩下一个无条件的分支，整个 if 语句被替换为该分支的块；如果没有分支剩下，则被替换为空节点。
+/// 每一个被丢弃的分支都会产生一条 `CompilerWarning::UnreachableBranch` 警告。
+///
+/// 表达式中作为值的 if 同样会被化简，只剩下一个分支时被替换为作为值的块。
+///
+/// 该函数应在常量折叠之后调用，以便识别出诸如 `1 == 1` 这样的条件。
+pub fn eliminate_dead_branches(node: &AstNode, warnings: &mut Vec<CompilerWarning>) -> AstNode {
+    let (node, _, located) = eliminate_located(node, &SpanTree::default(), "");
+    warnings.extend(located.into_iter().map(|warning| warning.value));
+    node
+}
+
+/// 与 `eliminate_dead_branches` 相同，`spans` 为与 `node` 对应的区间树，`source` 为源代码
+///
+/// 同时返回与化简后的节点对应的区间树。警告的位置为被丢弃的分支，所在的 if 作为次要位置，
+/// 警告中的条件取自源代码。
+pub fn eliminate_located(node: &AstNode, spans: &SpanTree, source: &str) -> Optimized {
+    let mut eliminator = Eliminator {
+        source,
+        warnings: vec![],
+    };
+    let (node, spans) = eliminator.node(node, spans);
+    (node, spans, eliminator.warnings)
+}
+
+/// if 的一个分支
+struct Branch {
+    /// 条件及其区间树，`None` 表示 else 分支
+    cond: Option<(AstNode, SpanTree)>,
+    block: (AstNode, SpanTree),
+    /// 第一个分支从 `if` 开始到块结束，其余的分支为 elif 或 else 节点的区间
+    span: Span,
+}
+
+struct Eliminator<'a> {
+    source: &'a str,
+    warnings: Vec<Located<CompilerWarning>>,
+}
+
+impl Eliminator<'_> {
+    fn node(&mut self, node: &AstNode, spans: &SpanTree) -> (AstNode, SpanTree) {
+        let tree = |children: Vec<SpanTree>| SpanTree {
+            span: spans.span,
+            children,
+        };
+
+        match node {
+            AstNode::Program(nodes) => {
+                let (nodes, children) = self.nodes(nodes, spans);
+                (AstNode::Program(nodes), tree(children))
+            }
+            AstNode::Block(nodes) => {
+                let (nodes, children) = self.nodes(nodes, spans);
+                (AstNode::Block(nodes), tree(children))
+            }
+            // `format_ast` 加上的包装节点与被包装的节点共用区间树
+            AstNode::Expr(left, None, None) => {
+                let (left, left_spans) = self.value(left, spans);
+                (AstNode::Expr(Box::new(left), None, None), left_spans)
+            }
+            AstNode::Expr(left, op, right) => {
+                let (left, left_spans) = self.value(left, spans.child(0));
+                let mut children = vec![left_spans];
+                let right = right.as_ref().map(|right| {
+                    let (right, right_spans) = self.value(right, spans.child(1));
+                    children.push(right_spans);
+                    Box::new(right)
+                });
+                (
+                    AstNode::Expr(Box::new(left), op.clone(), right),
+                    tree(children),
+                )
+            }
+            AstNode::Assign(identifier, type_annotation, value) => {
+                let (value, children) = self.last_value(node, value, spans);
+                (
+                    AstNode::Assign(identifier.clone(), type_annotation.clone(), Box::new(value)),
+                    tree(children),
+                )
+            }
+            AstNode::SetValue(identifier, value) => {
+                let (value, children) = self.last_value(node, value, spans);
+                (
+                    AstNode::SetValue(identifier.clone(), Box::new(value)),
+                    tree(children),
+                )
+            }
+            AstNode::ReturnBlock(value) => {
+                let (value, children) = self.last_value(node, value, spans);
+                (AstNode::ReturnBlock(Box::new(value)), tree(children))
+            }
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                let block_spans = spans.child(1);
+                let mut branches: Vec<Branch> = vec![Branch {
+                    cond: Some(self.value(cond, spans.child(0))),
+                    block: self.node(block, block_spans),
+                    span: Span::new(spans.span.start, block_spans.span.end),
+                }];
+
+                for (index, elif_node) in elif_nodes.iter().enumerate() {
+                    if let AstNode::Elif(cond, block) = elif_node {
+                        let elif_spans = spans.child(2 + index);
+                        branches.push(Branch {
+                            cond: Some(self.value(cond, elif_spans.child(0))),
+                            block: self.node(block, elif_spans.child(1)),
+                            span: elif_spans.span,
+                        });
+                    }
+                }
+
+                if let Some(AstNode::Else(block)) = else_node.as_deref() {
+                    let else_spans = spans.child(2 + elif_nodes.len());
+                    branches.push(Branch {
+                        cond: None,
+                        block: self.node(block, else_spans.child(0)),
+                        span: else_spans.span,
+                    });
+                }
+
+                self.rebuild_if(branches, spans.span)
+            }
+            _ => (node.clone(), spans.clone()),
+        }
+    }
+
+    /// 化简作为值的节点
+    ///
+    /// 不合法的作为值的 if（如没有 else 分支）保持原样，留给编译时报告错误。
+    fn value(&mut self, node: &AstNode, spans: &SpanTree) -> (AstNode, SpanTree) {
+        match node {
+            AstNode::If(_, _, _, _) if node.check_if_value(&|_| Type::Unknown).is_err() => {
+                (node.clone(), spans.clone())
+            }
+            _ => self.node(node, spans),
+        }
+    }
+
+    /// 化简赋值语句或 `rtb` 语句 `node` 的值 `value`，返回化简后的值与 `node` 的各个子节点的区间树
+    ///
+    /// 值总是 `node` 的最后一个子节点。
+    fn last_value(
+        &mut self,
+        node: &AstNode,
+        value: &AstNode,
+        spans: &SpanTree,
+    ) -> (AstNode, Vec<SpanTree>) {
+        let count = node.children().len();
+        let (value, value_spans) = self.value(value, spans.child(count - 1));
+        let children = (0..count - 1)
+            .map(|index| spans.child(index).clone())
+            .chain([value_spans])
+            .collect();
+        (value, children)
+    }
+
+    fn nodes(&mut self, nodes: &[AstNode], spans: &SpanTree) -> (Vec<AstNode>, Vec<SpanTree>) {
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| self.node(node, spans.child(index)))
+            .filter(|(node, _)| !matches!(node, AstNode::Empty))
+            .unzip()
+    }
+
+    /// 根据分支列表重新构造 if 语句，`span` 为原来的 if 的区间
+    fn rebuild_if(&mut self, branches: Vec<Branch>, span: Span) -> (AstNode, SpanTree) {
+        let mut kept: Vec<Branch> = vec![];
+        let mut taken = false;
+
+        for (index, branch) in branches.into_iter().enumerate() {
+            if taken {
+                self.unreachable(index, &branch, span);
+                continue;
+            }
+
+            match branch
+                .cond
+                .as_ref()
+                .and_then(|(cond, _)| constant_value(cond))
+            {
+                Some(Value::Bool(false)) => self.unreachable(index, &branch, span),
+                Some(Value::Bool(true)) => {
+                    kept.push(Branch {
+                        cond: None,
+                        ..branch
+                    });
+                    taken = true;
+                }
+                _ => {
+                    taken = branch.cond.is_none();
+                    kept.push(branch);
+                }
+            }
+        }
+
+        let mut kept = kept.into_iter();
+        let (cond, block) = match kept.next() {
+            Some(Branch {
+                cond: Some(cond),
+                block,
+                ..
+            }) => (cond, block),
+            Some(Branch {
+                cond: None, block, ..
+            }) => return block,
+            None => return (AstNode::Empty, SpanTree::default()),
+        };
+
+        let mut children = vec![cond.1, block.1];
+        let mut elif_nodes: Vec<AstNode> = vec![];
+        let mut else_node: Option<Box<AstNode>> = None;
+        for branch in kept {
+            match branch.cond {
+                Some((cond, cond_spans)) => {
+                    elif_nodes.push(AstNode::Elif(Box::new(cond), Box::new(branch.block.0)));
+                    children.push(SpanTree {
+                        span: branch.span,
+                        children: vec![cond_spans, branch.block.1],
+                    });
+                }
+                None => {
+                    else_node = Some(Box::new(AstNode::Else(Box::new(branch.block.0))));
+                    children.push(SpanTree {
+                        span: branch.span,
+                        children: vec![branch.block.1],
+                    });
+                }
+            }
+        }
+
+        (
+            AstNode::If(Box::new(cond.0), Box::new(block.0), elif_nodes, else_node),
+            SpanTree { span, children },
+        )
+    }
+
+    /// 记录第 `index` 个分支不可达的警告，`span` 为所在的 if 的区间
+    fn unreachable(&mut self, index: usize, branch: &Branch, span: Span) {
+        let warning = CompilerWarning::UnreachableBranch(self.describe(index, branch));
+        self.warnings
+            .push(Located::new(warning, branch.span).with_label(Label::new(span, "in this `if`")));
+    }
+
+    /// 分支的开头，条件取自源代码，没有源代码时由节点生成
+    fn describe(&self, index: usize, branch: &Branch) -> String {
+        let cond = branch.cond.as_ref().map(|(cond, spans)| {
+            match self.source.get(spans.span.start..spans.span.end) {
+                Some(code) if !code.is_empty() => code.to_string(),
+                _ => cond.as_code(),
+            }
+        });
+
+        match (index, cond) {
+            (0, Some(cond)) => format!("if {}", cond),
+            (_, Some(cond)) => format!("elif {}", cond),
+            (_, None) => "else".to_string(),
+        }
+    }
+}
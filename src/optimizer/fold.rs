@@ -0,0 +1,126 @@
+use crate::compiler::value::{Value, ValueError};
+use crate::compiler::{CompilerError, Located};
+use crate::parser::ast::AstNode;
+use crate::parser::serialize::SpanTree;
+
+/// 返回节点在编译期可知的常量值
+///
+/// 只有常量节点，以及只包裹了一个常量的表达式节点才被视为常量。
+pub fn constant_value(node: &AstNode) -> Option<Value> {
+    match node {
+        AstNode::Constant(literal) => Value::from_literal(literal),
+        AstNode::Expr(inner, None, None) => constant_value(inner),
+        _ => None,
+    }
+}
+
+/// 常量折叠
+///
+/// 递归地将操作数均为常量的二元表达式替换为其运算结果，运算语义与虚拟机一致（见 `Value::binary`）。
+/// 除数为零时不会折叠，而是返回 `CompilerError::DivisionByZero`；类型错误或整数溢出的表达式保持原样，
+/// 留给运行期报告。
+pub fn fold_constants(node: &AstNode) -> Result<AstNode, CompilerError> {
+    fold_located(node, &SpanTree::default()).map_err(|err| err.value)
+}
+
+/// 与 `fold_constants` 相同，`spans` 为与 `node` 对应的区间树，除数为零的错误附带该表达式的区间
+///
+/// 被折叠的表达式成为没有子节点的常量，因此 `spans` 同样与折叠后的节点对应。
+pub fn fold_located(node: &AstNode, spans: &SpanTree) -> Result<AstNode, Located<CompilerError>> {
+    let folded = match node {
+        AstNode::Program(nodes) => AstNode::Program(fold_nodes(nodes, spans)?),
+        AstNode::Block(nodes) => AstNode::Block(fold_nodes(nodes, spans)?),
+        AstNode::Expr(left, op, right) => {
+            // `format_ast` 加上的包装节点与被包装的节点共用区间树
+            let left = match (op, right) {
+                (None, None) => fold_located(left, spans)?,
+                _ => fold_located(left, spans.child(0))?,
+            };
+
+            match (op, right) {
+                (Some(op), Some(right)) => {
+                    let right = fold_located(right, spans.child(1))?;
+
+                    if let (Some(lhs), Some(rhs)) = (constant_value(&left), constant_value(&right))
+                    {
+                        match lhs.binary(op, &rhs) {
+                            Ok(value) => {
+                                return Ok(AstNode::Expr(
+                                    Box::new(AstNode::Constant(value.to_literal())),
+                                    None,
+                                    None,
+                                ))
+                            }
+                            Err(ValueError::DivisionByZero(_)) => {
+                                return Err(Located::new(
+                                    CompilerError::DivisionByZero(node.as_code()),
+                                    spans.span,
+                                ))
+                            }
+                            Err(_) => {}
+                        }
+                    }
+
+                    AstNode::Expr(Box::new(left), Some(op.clone()), Some(Box::new(right)))
+                }
+                _ => AstNode::Expr(Box::new(left), op.clone(), right.clone()),
+            }
+        }
+        // 值总是最后一个子节点
+        AstNode::Assign(identifier, type_annotation, value) => AstNode::Assign(
+            identifier.clone(),
+            type_annotation.clone(),
+            Box::new(fold_located(value, spans.child(node.children().len() - 1))?),
+        ),
+        AstNode::SetValue(identifier, value) => AstNode::SetValue(
+            identifier.clone(),
+            Box::new(fold_located(value, spans.child(1))?),
+        ),
+        AstNode::ReturnBlock(value) => {
+            AstNode::ReturnBlock(Box::new(fold_located(value, spans.child(0))?))
+        }
+        AstNode::If(_, _, _, _) | AstNode::Elif(_, _) | AstNode::Else(_) => {
+            fold_branches(node, spans)?
+        }
+        _ => node.clone(),
+    };
+
+    Ok(folded)
+}
+
+/// 折叠 if 节点与它的分支
+///
+/// 与 `fold_located` 分开，使得递归折叠很长的运算符链时每层占用的栈较小。
+fn fold_branches(node: &AstNode, spans: &SpanTree) -> Result<AstNode, Located<CompilerError>> {
+    let folded = match node {
+        AstNode::If(cond, block, elif_nodes, else_node) => AstNode::If(
+            Box::new(fold_located(cond, spans.child(0))?),
+            Box::new(fold_located(block, spans.child(1))?),
+            elif_nodes
+                .iter()
+                .enumerate()
+                .map(|(index, node)| fold_located(node, spans.child(2 + index)))
+                .collect::<Result<_, _>>()?,
+            else_node
+                .as_ref()
+                .map(|node| fold_located(node, spans.child(2 + elif_nodes.len())).map(Box::new))
+                .transpose()?,
+        ),
+        AstNode::Elif(cond, block) => AstNode::Elif(
+            Box::new(fold_located(cond, spans.child(0))?),
+            Box::new(fold_located(block, spans.child(1))?),
+        ),
+        AstNode::Else(block) => AstNode::Else(Box::new(fold_located(block, spans.child(0))?)),
+        _ => node.clone(),
+    };
+
+    Ok(folded)
+}
+
+fn fold_nodes(nodes: &[AstNode], spans: &SpanTree) -> Result<Vec<AstNode>, Located<CompilerError>> {
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| fold_located(node, spans.child(index)))
+        .collect()
+}
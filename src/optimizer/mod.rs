@@ -0,0 +1,79 @@
+pub mod branch;
+pub mod fold;
+pub mod peephole;
+
+use crate::compiler::{ByteCode, CompilerError, CompilerWarning, Located};
+use crate::parser::ast::AstNode;
+use crate::parser::serialize::SpanTree;
+
+pub use branch::{eliminate_dead_branches, eliminate_located};
+pub use fold::{fold_constants, fold_located};
+pub use peephole::{peephole, PeepholeStats};
+
+/// 带有位置的优化结果：优化后的抽象语法树、与之对应的区间树以及附带位置的警告
+pub type Optimized = (AstNode, SpanTree, Vec<Located<CompilerWarning>>);
+
+/// 按优化等级对抽象语法树进行优化
+///
+/// # 参数
+///
+/// - `ast`: 要优化的抽象语法树。
+/// - `level`: 优化等级，`0` 表示不做任何优化，`1` 及以上会进行常量折叠与死分支消除。
+///
+/// # 返回值
+///
+/// 返回优化后的抽象语法树以及优化过程中产生的警告；如果在优化过程中发现编译期错误（如除数为零），则返回`Err`。
+pub fn optimize(
+    ast: &AstNode,
+    level: u8,
+) -> Result<(AstNode, Vec<CompilerWarning>), CompilerError> {
+    let (ast, _, warnings) =
+        optimize_located(ast, &SpanTree::default(), "", level).map_err(|err| err.value)?;
+    Ok((
+        ast,
+        warnings.into_iter().map(|warning| warning.value).collect(),
+    ))
+}
+
+/// 与 `optimize` 相同，`spans` 为与 `ast` 对应的区间树，`source` 为源代码
+///
+/// 同时返回与优化后的抽象语法树对应的区间树，警告与错误附带所在的节点的区间，
+/// 见 `fold_located` 与 `eliminate_located`。
+pub fn optimize_located(
+    ast: &AstNode,
+    spans: &SpanTree,
+    source: &str,
+    level: u8,
+) -> Result<Optimized, Located<CompilerError>> {
+    if level == 0 {
+        return Ok((ast.clone(), spans.clone(), vec![]));
+    }
+
+    let ast = fold_located(ast, spans)?;
+    Ok(eliminate_located(&ast, spans, source))
+}
+
+/// 按优化等级对字节码进行优化
+///
+/// # 参数
+///
+/// - `codes`: 要优化的字节码。
+/// - `level`: 优化等级，`2` 及以上会进行窥孔优化。
+///
+/// # 返回值
+///
+/// 返回优化后的字节码以及窥孔优化的统计信息。
+pub fn optimize_bytecodes(codes: &[ByteCode], level: u8) -> (Vec<ByteCode>, PeepholeStats) {
+    if level >= 2 {
+        peephole(codes)
+    } else {
+        (
+            codes.to_vec(),
+            PeepholeStats {
+                before: codes.len(),
+                after: codes.len(),
+                ..Default::default()
+            },
+        )
+    }
+}
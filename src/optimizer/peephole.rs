@@ -0,0 +1,426 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::compiler::operand::Operand;
+use crate::compiler::section::find_sections;
+use crate::compiler::value::Value;
+use crate::compiler::{ByteCode, OpCode};
+
+/// 不动点迭代的最大轮数，防止规则之间相互改写导致死循环
+const MAX_ITERATIONS: usize = 64;
+
+/// 规则的改写结果：改写后的字节码与改写次数，没有可改写之处时为 `None`
+pub type Rewrite = Option<(Vec<ByteCode>, usize)>;
+
+/// 窥孔优化规则
+pub struct PeepholeRule {
+    /// 规则名，用于统计
+    pub name: &'static str,
+    /// 规则的说明
+    pub description: &'static str,
+    /// 在整段字节码上应用规则
+    pub apply: fn(&[ByteCode]) -> Rewrite,
+}
+
+/// 窥孔优化规则表，按顺序应用
+///
+/// 涉及 Section 的规则假设 Section 的名字在整段字节码中唯一，并且只会通过 `Jump`、`JumpIf` 与 `JumpIfElse` 被引用。
+pub const RULES: &[PeepholeRule] = &[
+    PeepholeRule {
+        name: "push-push",
+        description: "`Push a; Push b` => `Push a b`",
+        apply: push_push,
+    },
+    PeepholeRule {
+        name: "push-pop",
+        description: "`Push .. x; Pop` => `Push ..`",
+        apply: push_pop,
+    },
+    PeepholeRule {
+        name: "dup-pop",
+        description: "`Dup; Pop` => (nothing)",
+        apply: dup_pop,
+    },
+    PeepholeRule {
+        name: "push-dup",
+        description: "`Push .. x; Dup` => `Push .. x x`",
+        apply: push_dup,
+    },
+    PeepholeRule {
+        name: "push-neg",
+        description: "`Push .. x; Neg` => `Push .. -x`",
+        apply: push_neg,
+    },
+    PeepholeRule {
+        name: "store-load",
+        description: "`StoreName a; LoadName a` => `Dup; StoreName a`",
+        apply: store_load,
+    },
+    PeepholeRule {
+        name: "const-jump-if",
+        description: "`Push .. true; JumpIf s` => `Push ..; Jump s`, `Push .. false; JumpIf s` => `Push ..`, \
+             `Push .. c; JumpIfElse s t` => `Push ..; Jump s` or `Push ..; Jump t`",
+        apply: const_jump_if,
+    },
+    PeepholeRule {
+        name: "empty-section",
+        description: "`Jump s` => (nothing), `JumpIf s` => `Pop` when section `s` is empty",
+        apply: empty_section,
+    },
+    PeepholeRule {
+        name: "thread-jumps",
+        description: "`Jump s` => `Jump t` when section `s` only jumps to section `t`",
+        apply: thread_jumps,
+    },
+    PeepholeRule {
+        name: "dead-section",
+        description: "remove sections that are never jumped to",
+        apply: dead_section,
+    },
+];
+
+/// 窥孔优化的统计信息
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PeepholeStats {
+    /// 优化前的指令数
+    pub before: usize,
+    /// 优化后的指令数
+    pub after: usize,
+    /// 不动点迭代的轮数
+    pub iterations: usize,
+    /// 每条规则的改写次数
+    pub rewrites: BTreeMap<&'static str, usize>,
+}
+
+impl PeepholeStats {
+    /// 返回被删除的指令数
+    pub fn removed(&self) -> usize {
+        self.before.saturating_sub(self.after)
+    }
+}
+
+impl fmt::Display for PeepholeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "peephole: removed {} of {} instructions in {} iterations",
+            self.removed(),
+            self.before,
+            self.iterations
+        )?;
+
+        for (name, count) in &self.rewrites {
+            write!(f, "\n  {}: {}", name, count)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// 返回规则表中名为 `name` 的规则
+pub fn rule(name: &str) -> Option<&'static PeepholeRule> {
+    RULES.iter().find(|rule| rule.name == name)
+}
+
+/// 使用全部规则对字节码进行窥孔优化，直到不再发生改写
+pub fn peephole(codes: &[ByteCode]) -> (Vec<ByteCode>, PeepholeStats) {
+    peephole_with(codes, RULES)
+}
+
+/// 使用给定的规则对字节码进行窥孔优化，直到不再发生改写
+pub fn peephole_with(codes: &[ByteCode], rules: &[PeepholeRule]) -> (Vec<ByteCode>, PeepholeStats) {
+    let mut codes = codes.to_vec();
+    let mut stats = PeepholeStats {
+        before: codes.len(),
+        ..Default::default()
+    };
+
+    while stats.iterations < MAX_ITERATIONS {
+        stats.iterations += 1;
+        let mut changed = false;
+
+        for rule in rules {
+            if let Some((rewritten, count)) = (rule.apply)(&codes) {
+                codes = rewritten;
+                *stats.rewrites.entry(rule.name).or_default() += count;
+                changed = true;
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    stats.after = codes.len();
+    (codes, stats)
+}
+
+/// 用 `rewrite` 依次尝试改写长度为 `size` 的窗口，改写过的指令不会再参与同一轮的匹配
+fn rewrite_windows(
+    codes: &[ByteCode],
+    size: usize,
+    rewrite: impl Fn(&[ByteCode]) -> Option<Vec<ByteCode>>,
+) -> Rewrite {
+    let mut output: Vec<ByteCode> = vec![];
+    let mut count = 0;
+    let mut index = 0;
+
+    while index < codes.len() {
+        if index + size <= codes.len() {
+            if let Some(replacement) = rewrite(&codes[index..index + size]) {
+                output.extend(replacement);
+                index += size;
+                count += 1;
+                continue;
+            }
+        }
+
+        output.push(codes[index].clone());
+        index += 1;
+    }
+
+    (count > 0).then_some((output, count))
+}
+
+/// 构造一条 `Push` 指令，没有参数时返回空
+fn push(operands: Vec<Operand>) -> Vec<ByteCode> {
+    ByteCode::new(OpCode::Push, operands).into_iter().collect()
+}
+
+/// 构造一条没有参数的指令
+fn bare(op: OpCode) -> ByteCode {
+    ByteCode::new(op, vec![]).expect("op takes no operands")
+}
+
+fn push_push(codes: &[ByteCode]) -> Rewrite {
+    rewrite_windows(codes, 2, |window| match (&window[0].op, &window[1].op) {
+        (OpCode::Push, OpCode::Push) => {
+            let mut operands = window[0].operands().to_vec();
+            operands.extend_from_slice(window[1].operands());
+            Some(push(operands))
+        }
+        _ => None,
+    })
+}
+
+fn push_pop(codes: &[ByteCode]) -> Rewrite {
+    rewrite_windows(codes, 2, |window| match (&window[0].op, &window[1].op) {
+        (OpCode::Push, OpCode::Pop) => {
+            let mut operands = window[0].operands().to_vec();
+            operands.pop();
+            Some(push(operands))
+        }
+        _ => None,
+    })
+}
+
+fn dup_pop(codes: &[ByteCode]) -> Rewrite {
+    rewrite_windows(codes, 2, |window| match (&window[0].op, &window[1].op) {
+        (OpCode::Dup, OpCode::Pop) => Some(vec![]),
+        _ => None,
+    })
+}
+
+fn push_dup(codes: &[ByteCode]) -> Rewrite {
+    rewrite_windows(codes, 2, |window| match (&window[0].op, &window[1].op) {
+        (OpCode::Push, OpCode::Dup) => {
+            let mut operands = window[0].operands().to_vec();
+            operands.push(operands.last()?.clone());
+            Some(push(operands))
+        }
+        _ => None,
+    })
+}
+
+fn push_neg(codes: &[ByteCode]) -> Rewrite {
+    rewrite_windows(codes, 2, |window| match (&window[0].op, &window[1].op) {
+        (OpCode::Push, OpCode::Neg) => {
+            let mut operands = window[0].operands().to_vec();
+            let value = operands.pop()?.value()?.neg().ok()?;
+            operands.push(Operand::constant(value));
+            Some(push(operands))
+        }
+        _ => None,
+    })
+}
+
+fn store_load(codes: &[ByteCode]) -> Rewrite {
+    rewrite_windows(codes, 2, |window| match (&window[0].op, &window[1].op) {
+        (OpCode::StoreName, OpCode::LoadName) if window[0].name() == window[1].name() => {
+            Some(vec![bare(OpCode::Dup), window[0].clone()])
+        }
+        _ => None,
+    })
+}
+
+fn const_jump_if(codes: &[ByteCode]) -> Rewrite {
+    rewrite_windows(codes, 2, |window| match (&window[0].op, &window[1].op) {
+        (OpCode::Push, OpCode::JumpIf) => {
+            let mut operands = window[0].operands().to_vec();
+            let cond = operands.pop()?.value()?;
+            let mut replacement = push(operands);
+
+            match cond {
+                Value::Bool(true) => replacement
+                    .push(ByteCode::new(OpCode::Jump, window[1].operands().to_vec()).ok()?),
+                Value::Bool(false) => {}
+                _ => return None,
+            }
+
+            Some(replacement)
+        }
+        (OpCode::Push, OpCode::JumpIfElse) => {
+            let mut operands = window[0].operands().to_vec();
+            let cond = operands.pop()?.value()?;
+            let mut replacement = push(operands);
+            let target = match cond {
+                Value::Bool(true) => window[1].operands()[0].clone(),
+                Value::Bool(false) => window[1].operands()[1].clone(),
+                _ => return None,
+            };
+
+            replacement.push(ByteCode::new(OpCode::Jump, vec![target]).ok()?);
+            Some(replacement)
+        }
+        _ => None,
+    })
+}
+
+fn empty_section(codes: &[ByteCode]) -> Rewrite {
+    let empty: HashSet<String> = find_sections(codes)
+        .into_iter()
+        .filter(|section| section.end == section.start + 1)
+        .map(|section| section.name)
+        .collect();
+
+    let mut output: Vec<ByteCode> = vec![];
+    let mut count = 0;
+
+    for code in codes {
+        let target_is_empty = code.name().is_some_and(|name| empty.contains(name));
+
+        match code.op {
+            OpCode::Jump if target_is_empty => count += 1,
+            OpCode::JumpIf if target_is_empty => {
+                output.push(bare(OpCode::Pop));
+                count += 1;
+            }
+            _ => output.push(code.clone()),
+        }
+    }
+
+    (count > 0).then_some((output, count))
+}
+
+fn thread_jumps(codes: &[ByteCode]) -> Rewrite {
+    let sections = find_sections(codes);
+
+    for section in &sections {
+        let body = section.body(codes);
+        let target = match body.as_slice() {
+            [jump] if codes[*jump].op == OpCode::Jump => {
+                // 目标 Section 的返回值会被当前 Section 丢弃，因此目标必须不返回值
+                let target = codes[*jump].name()?;
+                let returns = sections
+                    .iter()
+                    .filter(|candidate| candidate.name == target)
+                    .any(|candidate| {
+                        candidate
+                            .body(codes)
+                            .iter()
+                            .any(|index| codes[*index].op == OpCode::Return)
+                    });
+                if returns {
+                    continue;
+                }
+                target
+            }
+            [jump, ret] if codes[*jump].op == OpCode::Jump && codes[*ret].op == OpCode::Return => {
+                codes[*jump].name()?
+            }
+            _ => continue,
+        };
+
+        if target == section.name {
+            continue;
+        }
+
+        let mut output: Vec<ByteCode> = vec![];
+        let mut count = 0;
+
+        for (index, code) in codes.iter().enumerate() {
+            if index == section.start {
+                // 将嵌套的 Section 定义提升到当前 Section 之前，使得跳转目标在调用处已经被定义
+                let body: HashSet<usize> = body.iter().copied().collect();
+                output.extend(
+                    (section.start + 1..section.end)
+                        .filter(|index| !body.contains(index))
+                        .map(|index| codes[index].clone()),
+                );
+                output.push(code.clone());
+                output.extend(body_codes(codes, section.start, section.end, &body));
+            } else if index > section.start && index <= section.end {
+                if index == section.end {
+                    output.push(code.clone());
+                }
+            } else if code.targets().contains(&section.name.as_str()) {
+                let operands = code
+                    .operands()
+                    .iter()
+                    .map(|operand| match operand.name() {
+                        Some(name) if name == section.name => Operand::Section(target.to_string()),
+                        _ => operand.clone(),
+                    })
+                    .collect();
+                output.push(ByteCode::new(code.op.clone(), operands).ok()?);
+                count += 1;
+            } else {
+                output.push(code.clone());
+            }
+        }
+
+        if count > 0 {
+            return Some((output, count));
+        }
+    }
+
+    None
+}
+
+fn body_codes(
+    codes: &[ByteCode],
+    start: usize,
+    end: usize,
+    body: &HashSet<usize>,
+) -> Vec<ByteCode> {
+    (start + 1..end)
+        .filter(|index| body.contains(index))
+        .map(|index| codes[index].clone())
+        .collect()
+}
+
+fn dead_section(codes: &[ByteCode]) -> Rewrite {
+    let referenced: HashSet<&str> = codes.iter().flat_map(ByteCode::targets).collect();
+
+    let mut removed = vec![false; codes.len()];
+    let mut count = 0;
+
+    for section in find_sections(codes) {
+        if removed[section.start] || referenced.contains(section.name.as_str()) {
+            continue;
+        }
+
+        removed[section.start..=section.end].fill(true);
+        count += 1;
+    }
+
+    let output = codes
+        .iter()
+        .zip(removed)
+        .filter(|(_, removed)| !removed)
+        .map(|(code, _)| code.clone())
+        .collect();
+
+    (count > 0).then_some((output, count))
+}
@@ -1,4 +1,3 @@
-
 /// 表示二元操作符的枚举类型
 ///
 /// 这个枚举类型定义了所有支持的二元操作符，如加法、减法、乘法、除法等。
@@ -51,6 +50,8 @@ pub enum AstNode {
     ReturnBlock(Box<AstNode>),
     /// 表示空节点，仅用于解析器内部使用，不会出现在对外的接口中
     Empty,
+    /// 表示一段无法解析的代码，仅在错误恢复时作为占位节点出现，包含被跳过的源代码
+    Error(String),
 
     // 流程控制节点
     /// If 节点，包含条件和分支，Fallback 分支
@@ -66,6 +67,15 @@ pub enum AstNode {
     Else(Box<AstNode>),
 }
 
+/// if 节点的分支，由 `AstNode::if_branches` 返回
+#[derive(Debug, Clone)]
+pub struct IfBranches<'a> {
+    /// if 与每个 elif 的条件与块
+    pub branches: Vec<(&'a AstNode, &'a AstNode)>,
+    /// else 分支的块
+    pub otherwise: Option<&'a AstNode>,
+}
+
 impl BinaryOp {
     /// 返回二元操作符的原始字符串表示
     ///
@@ -155,7 +165,7 @@ impl AstNode {
             }
             AstNode::ReturnBlock(value) => {
                 let value_string = value.as_code().to_string();
-                format!("return {};", value_string)
+                format!("rtb {};", value_string)
             }
             AstNode::If(cond, block, elif_nodes, else_nodes) => {
                 let mut code = format!("if {} \n{}\n", cond.as_code(), block.as_code());
@@ -175,11 +185,134 @@ impl AstNode {
             AstNode::Else(block) => {
                 format!("else \n{}\n", block.as_code())
             }
+            AstNode::Error(code) => code.to_string(),
 
             _ => "".to_string(),
         }
     }
 
+    /// 返回抽象语法树的深度，叶子节点的深度为 1
+    ///
+    /// elif 分支在编译时相当于嵌套在前一个分支的 else 中，因此第 n 个 elif 分支与 else 分支按嵌套 n 层计算。
+    /// 使用显式栈遍历，因此可以安全地用于检查任意深度的语法树。
+    pub fn depth(&self) -> usize {
+        let mut max_depth = 0;
+        let mut stack: Vec<(&AstNode, usize)> = vec![(self, 1)];
+
+        while let Some((node, depth)) = stack.pop() {
+            max_depth = max_depth.max(depth);
+            match node {
+                AstNode::If(cond, block, elif_nodes, else_node) => {
+                    stack.extend([(cond.as_ref(), depth + 1), (block.as_ref(), depth + 1)]);
+                    stack.extend(
+                        elif_nodes
+                            .iter()
+                            .chain(else_node.as_deref())
+                            .enumerate()
+                            .map(|(index, child)| (child, depth + 1 + index)),
+                    );
+                }
+                _ => stack.extend(node.children().into_iter().map(|child| (child, depth + 1))),
+            }
+        }
+
+        max_depth
+    }
+
+    /// 返回块的值，即块中第一个 `rtb` 语句的值
+    ///
+    /// 嵌套的块与 if 语句的分支中的 `rtb` 只会提前结束块，不计入。节点不是块或块中没有 `rtb` 时返回 `None`。
+    pub fn block_value(&self) -> Option<&AstNode> {
+        match self {
+            AstNode::Block(nodes) => nodes.iter().find_map(|node| match node {
+                AstNode::ReturnBlock(value) => Some(value.as_ref()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// 判断块中是否含有提前结束块的 `rtb`
+    ///
+    /// 包括嵌套的块与 if 语句的分支中的 `rtb`，不包括作为值的块与作为值的 if 中的 `rtb`。节点不是块时返回 `false`。
+    pub fn contains_return(&self) -> bool {
+        let AstNode::Block(nodes) = self else {
+            return false;
+        };
+
+        nodes.iter().any(|node| match node {
+            AstNode::ReturnBlock(_) => true,
+            AstNode::Block(_) => node.contains_return(),
+            AstNode::If(_, _, _, _) => node.if_branches().is_some_and(|branches| {
+                branches
+                    .branches
+                    .iter()
+                    .map(|(_, block)| *block)
+                    .chain(branches.otherwise)
+                    .any(|block| block.contains_return())
+            }),
+            _ => false,
+        })
+    }
+
+    /// 返回 if 节点的所有分支
+    ///
+    /// 节点不是 if，或者 elif 与 else 分支的结构不合法时返回 `None`。
+    pub fn if_branches(&self) -> Option<IfBranches<'_>> {
+        let AstNode::If(cond, block, elif_nodes, else_node) = self else {
+            return None;
+        };
+
+        let mut branches: Vec<(&AstNode, &AstNode)> = vec![(cond, block)];
+        for node in elif_nodes {
+            match node {
+                AstNode::Elif(cond, block) => branches.push((cond, block)),
+                _ => return None,
+            }
+        }
+        let otherwise = match else_node.as_deref() {
+            Some(AstNode::Else(block)) => Some(block.as_ref()),
+            Some(_) => return None,
+            None => None,
+        };
+
+        Some(IfBranches {
+            branches,
+            otherwise,
+        })
+    }
+
+    /// 返回节点的直接子节点
+    pub fn children(&self) -> Vec<&AstNode> {
+        match self {
+            AstNode::Program(nodes) | AstNode::Block(nodes) => nodes.iter().collect(),
+            AstNode::Expr(left, _, right) => {
+                let mut children: Vec<&AstNode> = vec![left];
+                children.extend(right.as_deref());
+                children
+            }
+            AstNode::Assign(identifier, type_annotation, value) => {
+                let mut children: Vec<&AstNode> = vec![identifier];
+                children.extend(type_annotation.as_deref());
+                children.push(value);
+                children
+            }
+            AstNode::SetValue(identifier, value) => vec![identifier, value],
+            AstNode::ReturnBlock(value) => vec![value],
+            AstNode::If(cond, block, elif_nodes, else_node) => {
+                let mut children: Vec<&AstNode> = vec![cond, block];
+                children.extend(elif_nodes);
+                children.extend(else_node.as_deref());
+                children
+            }
+            AstNode::Elif(cond, block) => vec![cond, block],
+            AstNode::Else(block) => vec![block],
+            AstNode::Constant(_) | AstNode::Identifier(_) | AstNode::Empty | AstNode::Error(_) => {
+                vec![]
+            }
+        }
+    }
+
     /// 将抽象语法树节点格式化，确保符合规范
     pub fn format_ast(&self) -> AstNode {
         match self {
@@ -246,15 +379,20 @@ impl PartialEq for BinaryOp {
     ///
     /// 这个方法比较两个二元操作符是否相等，返回 `true` 或 `false`。
     fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (BinaryOp::Add, BinaryOp::Add) => true,
-            (BinaryOp::Sub, BinaryOp::Sub) => true,
-            (BinaryOp::Mul, BinaryOp::Mul) => true,
-            (BinaryOp::Div, BinaryOp::Div) => true,
-            (BinaryOp::Mod, BinaryOp::Mod) => true,
-            (BinaryOp::Eq, BinaryOp::Eq) => true,
-            _ => false,
-        }
+        matches!(
+            (self, other),
+            (BinaryOp::Add, BinaryOp::Add)
+                | (BinaryOp::Sub, BinaryOp::Sub)
+                | (BinaryOp::Mul, BinaryOp::Mul)
+                | (BinaryOp::Div, BinaryOp::Div)
+                | (BinaryOp::Mod, BinaryOp::Mod)
+                | (BinaryOp::Eq, BinaryOp::Eq)
+                | (BinaryOp::Neq, BinaryOp::Neq)
+                | (BinaryOp::Gt, BinaryOp::Gt)
+                | (BinaryOp::Gte, BinaryOp::Gte)
+                | (BinaryOp::Lt, BinaryOp::Lt)
+                | (BinaryOp::Lte, BinaryOp::Lte)
+        )
     }
 }
 
@@ -265,6 +403,7 @@ impl PartialEq for AstNode {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (AstNode::Constant(a), AstNode::Constant(b)) => a == b,
+            (AstNode::Error(a), AstNode::Error(b)) => a == b,
             (AstNode::Expr(a, op1, b), AstNode::Expr(c, op2, d)) => {
                 a == c
                     && op1.as_ref().unwrap_or(&BinaryOp::Add)
@@ -304,28 +443,21 @@ impl Clone for AstNode {
     fn clone(&self) -> Self {
         use AstNode::*;
         match self {
-            Program(nodes) => Program(nodes.iter().map(|node| node.clone()).collect()),
-            Block(nodes) => Block(nodes.iter().map(|node| node.clone()).collect()),
+            Program(nodes) => Program(nodes.to_vec()),
+            Block(nodes) => Block(nodes.to_vec()),
             Constant(s) => Constant(s.clone()),
-            Expr(left, op, right) => Expr(
-                left.clone(),
-                op.clone(),
-                right.as_ref().map(|node| node.clone()),
-            ),
+            Expr(left, op, right) => Expr(left.clone(), op.clone(), right.clone()),
             Identifier(s) => Identifier(s.clone()),
-            Assign(id, value, expr) => Assign(
-                id.clone(),
-                value.as_ref().map(|node| node.clone()),
-                expr.clone(),
-            ),
+            Assign(id, value, expr) => Assign(id.clone(), value.clone(), expr.clone()),
             SetValue(func, params) => SetValue(func.clone(), params.clone()),
             ReturnBlock(expr) => ReturnBlock(expr.clone()),
             Empty => Empty,
+            Error(code) => Error(code.clone()),
             If(cond, block, elifs, fallback) => If(
                 cond.clone(),
                 block.clone(),
-                elifs.iter().map(|node| node.clone()).collect(),
-                fallback.as_ref().map(|node| node.clone()),
+                elifs.to_vec(),
+                fallback.clone(),
             ),
             Elif(cond, block) => Elif(cond.clone(), block.clone()),
             Else(block) => Else(block.clone()),
@@ -1,13 +1,268 @@
 use thiserror::Error;
 
-use super::Rule;
+use super::span::Span;
+use super::{Rule, MAX_EXPRESSION_DEPTH, MAX_NESTING_DEPTH};
 
-#[derive(Error, Debug)]
+/// 解析错误
+///
+/// 每种错误有一个稳定的代码（见 `code`），`hare explain <代码>` 输出对它的详细解释。
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum ParserError {
-    #[error("Syntax error: {0}")]
-    SyntaxError(String),
-    #[error("Unknown error: {0}")]
-    UnknownError(String),
+    /// 意外出现的内容，`expected` 为该位置期望的内容，可能为空
+    #[error("{}", unexpected(.expected, .found))]
+    Unexpected {
+        expected: Vec<&'static str>,
+        found: String,
+    },
+    /// 缺少表达式，`after` 为紧邻其前的运算符或关键字
+    #[error("{}", expected_expression(.after, .found))]
+    ExpectedExpression {
+        after: Option<String>,
+        found: String,
+    },
+    #[error("expected identifier, found {0}")]
+    ExpectedIdentifier(String),
+    #[error("expected variable name after 'let'")]
+    MissingVariableName,
+    #[error("expected type name after ':'")]
+    MissingTypeName,
+    #[error("missing '=' in variable declaration")]
+    MissingInitializer,
+    /// `if`、`elif` 或 `else` 之后缺少块，包含该关键字
+    #[error("missing '{{' after {}", block_owner(.0))]
+    MissingBlock(&'static str),
+    /// `elif` 或 `else` 之前没有 `if`，包含该关键字
+    #[error("'{0}' without a preceding 'if'")]
+    DanglingBranch(&'static str),
+    #[error("missing ')'")]
+    UnclosedParen,
+    #[error("unclosed block")]
+    UnclosedBlock,
+    /// 字面量无法被表示，包含字面量的种类与代码
+    #[error("{0} literal out of range: {1}")]
+    LiteralOutOfRange(&'static str, String),
+    #[error("Expression is nested too deeply (more than {max} levels)", max = MAX_EXPRESSION_DEPTH)]
+    ExpressionTooDeep,
+    #[error("Brackets are nested too deeply (more than {max} levels)", max = MAX_NESTING_DEPTH)]
+    BracketsTooDeep,
+    /// pest 的自定义错误，文法中不会产生
+    #[error("{0}")]
+    Custom(String),
+    /// 内部错误：文法中的运算符没有对应的 `BinaryOp`
+    #[error("Unknown binary operator: {0:?}")]
+    UnknownOperator(Rule),
+    /// 内部错误：解析结果中出现了不应出现在该位置的规则
+    #[error("Unknown pair: {0:?}")]
+    UnknownPair(Rule),
     #[error("Pest Parser error: {0}")]
     PestError(#[from] pest::error::Error<Rule>),
 }
+
+impl ParserError {
+    /// 返回错误的代码，在机器可读的输出与 `hare explain` 中使用
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParserError::Unexpected { .. } => "E0001",
+            ParserError::ExpectedExpression { .. } => "E0002",
+            ParserError::ExpectedIdentifier(_) => "E0003",
+            ParserError::MissingVariableName => "E0004",
+            ParserError::MissingTypeName => "E0005",
+            ParserError::MissingInitializer => "E0006",
+            ParserError::MissingBlock(_) => "E0007",
+            ParserError::DanglingBranch(_) => "E0008",
+            ParserError::UnclosedParen => "E0009",
+            ParserError::UnclosedBlock => "E0010",
+            ParserError::LiteralOutOfRange(_, _) => "E0011",
+            ParserError::ExpressionTooDeep => "E0012",
+            ParserError::BracketsTooDeep => "E0013",
+            ParserError::Custom(_) => "E0014",
+            ParserError::UnknownOperator(_) => "E0015",
+            ParserError::UnknownPair(_) => "E0016",
+            ParserError::PestError(_) => "E0017",
+        }
+    }
+}
+
+fn unexpected(expected: &[&str], found: &str) -> String {
+    match expected {
+        [] => format!("unexpected {}", found),
+        names => format!("expected {}, found {}", names.join(" or "), found),
+    }
+}
+
+fn expected_expression(after: &Option<String>, found: &str) -> String {
+    match after {
+        Some(token) => format!("expected expression after '{}'", token),
+        None => format!("expected expression, found {}", found),
+    }
+}
+
+fn block_owner(keyword: &str) -> String {
+    match keyword {
+        "else" => "'else'".to_string(),
+        keyword => format!("{} condition", keyword),
+    }
+}
+
+/// 诊断信息中的次要位置，附带说明
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+impl Label {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+/// 可以被工具自动应用的修改：将 `span` 中的代码替换为 `replacement`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub span: Span,
+    pub replacement: String,
+}
+
+impl Fix {
+    /// 在 `offset` 处插入 `text`
+    pub fn insert(offset: usize, text: impl Into<String>) -> Self {
+        Self {
+            span: Span::new(offset, offset),
+            replacement: text.into(),
+        }
+    }
+}
+
+/// 带有位置信息的解析错误
+#[derive(Error, Debug)]
+#[error("{error}")]
+pub struct Diagnostic {
+    pub span: Span,
+    pub error: ParserError,
+    /// 修改建议
+    pub hint: Option<String>,
+    /// 与错误相关的其他位置
+    pub labels: Vec<Label>,
+    /// 实现修改建议的修改
+    pub fix: Option<Fix>,
+}
+
+impl Diagnostic {
+    pub fn new(span: Span, error: ParserError) -> Self {
+        Self {
+            span,
+            error,
+            hint: None,
+            labels: vec![],
+            fix: None,
+        }
+    }
+
+    pub fn with_hint(mut self, hint: Option<String>) -> Self {
+        self.hint = hint;
+        self
+    }
+
+    pub fn with_label(mut self, label: Label) -> Self {
+        self.labels.push(label);
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Option<Fix>) -> Self {
+        self.fix = fix;
+        self
+    }
+
+    /// 返回错误信息
+    pub fn message(&self) -> String {
+        self.error.to_string()
+    }
+
+    /// 将错误渲染为带有源代码片段的文本
+    ///
+    /// ```text
+    /// error: expected expression after '='
+    ///  --> 1:9
+    ///   |
+    /// 1 | let a = ;
+    ///   |         ^
+    ///   = hint: ...
+    /// ```
+    pub fn render(&self, input: &str) -> String {
+        render_snippet(
+            "error",
+            &self.message(),
+            self.hint.as_deref(),
+            self.span,
+            None,
+            input,
+        )
+    }
+
+    /// 与 `render` 相同，位置前带有文件名，形如 `--> main.ba:1:9`
+    pub fn render_file(&self, path: &str, input: &str) -> String {
+        render_snippet(
+            "error",
+            &self.message(),
+            self.hint.as_deref(),
+            self.span,
+            Some(path),
+            input,
+        )
+    }
+}
+
+/// 将一条诊断信息渲染为带有源代码片段的文本，格式见 `Diagnostic::render`
+///
+/// # 参数
+///
+/// - `severity`: 严重程度，如 `error` 或 `warning`。
+/// - `path`: 文件名，为 `None` 时只显示行号与列号。
+pub fn render_snippet(
+    severity: &str,
+    message: &str,
+    hint: Option<&str>,
+    span: Span,
+    path: Option<&str>,
+    input: &str,
+) -> String {
+    let (line, column) = span.location(input);
+    let source = input.lines().nth(line - 1).unwrap_or("");
+    let width = line.to_string().len();
+    let padding = " ".repeat(width);
+
+    // 标记的长度不超过所在行的剩余部分，且至少为 1
+    let rest = source.chars().count().saturating_sub(column - 1);
+    let length = input
+        .get(span.start..span.end.min(input.len()))
+        .map_or(0, |span| span.trim_end().chars().count())
+        .min(rest)
+        .max(1);
+    let location = match path {
+        Some(path) => format!("{}:{}:{}", path, line, column),
+        None => format!("{}:{}", line, column),
+    };
+
+    let mut output = format!(
+        "{}: {}\n{}--> {}\n{} |\n{} | {}\n{} | {}{}\n",
+        severity,
+        message,
+        padding,
+        location,
+        padding,
+        line,
+        source,
+        padding,
+        " ".repeat(column - 1),
+        "^".repeat(length)
+    );
+    if let Some(hint) = hint {
+        output.push_str(&format!("{} = hint: {}\n", padding, hint));
+    }
+
+    output
+}
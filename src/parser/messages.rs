@@ -0,0 +1,249 @@
+use pest::error::ErrorVariant;
+
+use super::errors::ParserError;
+use super::Rule;
+
+/// 翻译后的语法错误：面向语言使用者的错误，以及可选的修改建议
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub error: ParserError,
+    pub hint: Option<String>,
+    /// 在出错位置插入即可修正错误的代码
+    pub insert: Option<&'static str>,
+}
+
+impl Message {
+    fn new(error: ParserError) -> Self {
+        Self {
+            error,
+            hint: None,
+            insert: None,
+        }
+    }
+
+    fn hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    fn insert(mut self, text: &'static str) -> Self {
+        self.insert = Some(text);
+        self
+    }
+}
+
+/// 语法规则对使用者的称呼，不需要被提及的规则返回 `None`
+fn rule_name(rule: Rule) -> Option<&'static str> {
+    match rule {
+        Rule::expr | Rule::constant | Rule::int | Rule::float | Rule::string | Rule::boolean => {
+            Some("expression")
+        }
+        Rule::ident => Some("identifier"),
+        Rule::statement
+        | Rule::assign_statement
+        | Rule::set_value_statement
+        | Rule::return_block_statement
+        | Rule::if_statement => Some("statement"),
+        Rule::block => Some("'{'"),
+        Rule::elif_statement | Rule::else_statement => Some("'elif' or 'else'"),
+        Rule::add
+        | Rule::subtract
+        | Rule::multiply
+        | Rule::divide
+        | Rule::modulo
+        | Rule::equals
+        | Rule::not_equals
+        | Rule::greater_than
+        | Rule::less_than
+        | Rule::greater_than_or_equal_to
+        | Rule::less_than_or_equal_to => Some("operator"),
+        Rule::EOI => Some("end of input"),
+        _ => None,
+    }
+}
+
+/// 判断代码是否以关键字 `keyword` 开头
+pub fn starts_with_keyword(code: &str, keyword: &str) -> bool {
+    code.strip_prefix(keyword).is_some_and(|rest| {
+        !rest
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// 返回代码中最后一个标记，跳过末尾的空白字符
+fn last_token(code: &str) -> Option<&str> {
+    let code = code.trim_end();
+    let last = code.chars().last()?;
+
+    if last.is_alphanumeric() || last == '_' {
+        let start = code
+            .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(0, |index| index + 1);
+        return Some(&code[start..]);
+    }
+
+    ["==", "!=", ">=", "<="]
+        .into_iter()
+        .find(|op| code.ends_with(op))
+        .or_else(|| Some(&code[code.len() - last.len_utf8()..]))
+}
+
+/// 返回代码开头的标记，用于描述意外出现的内容
+fn next_token(code: &str) -> Option<&str> {
+    let first = code.chars().next()?;
+
+    if first.is_alphanumeric() || first == '_' {
+        let end = code
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(code.len());
+        return Some(&code[..end]);
+    }
+
+    Some(&code[..first.len_utf8()])
+}
+
+/// 返回代码中未闭合的圆括号数量
+fn unclosed_parens(code: &str) -> usize {
+    code.chars().fold(0usize, |depth, c| match c {
+        '(' => depth + 1,
+        ')' => depth.saturating_sub(1),
+        _ => depth,
+    })
+}
+
+/// 将 pest 的解析错误翻译为面向语言使用者的错误信息
+///
+/// # 参数
+///
+/// - `variant`: pest 的错误，包含在出错位置期望的规则集合。
+/// - `statement`: 出错的语句从开头到语句结束的代码。
+/// - `offset`: 出错位置在 `statement` 中的偏移量。
+pub fn translate(variant: &ErrorVariant<Rule>, statement: &str, offset: usize) -> Message {
+    let positives: Vec<Rule> = match variant {
+        ErrorVariant::ParsingError { positives, .. } => positives.clone(),
+        ErrorVariant::CustomError { message } => {
+            return Message::new(ParserError::Custom(message.clone()))
+        }
+    };
+    let offset = offset.min(statement.len());
+    let (before, after) = statement.split_at(offset);
+    let statement = statement.trim();
+
+    // 常见错误的专门提示
+    for keyword in ["elif", "else"] {
+        if starts_with_keyword(statement, keyword) {
+            return Message::new(ParserError::DanglingBranch(keyword)).hint(format!(
+                "'{}' must directly follow the closing '}}' of an 'if' or 'elif' block",
+                keyword
+            ));
+        }
+    }
+
+    if starts_with_keyword(statement, "let") && !statement.contains('=') {
+        return Message::new(ParserError::MissingInitializer)
+            .hint("variables must be initialized when declared, e.g. `let x = 0;`");
+    }
+
+    let expects = |rule: Rule| positives.contains(&rule);
+    let previous = last_token(before);
+    let unexpected = match next_token(after.trim_start()) {
+        Some(token) => format!("'{}'", token),
+        None => "end of input".to_string(),
+    };
+
+    // 块也可以作为操作数，期望常量时缺少的是表达式而不是块
+    if expects(Rule::block) && !expects(Rule::statement) && !expects(Rule::constant) {
+        // 缺少的块属于出错位置之前最近的 if、elif 或 else
+        let keyword = match before
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .rfind(|token| matches!(*token, "if" | "elif" | "else"))
+        {
+            Some("elif") => "elif",
+            Some("else") => "else",
+            _ => "if",
+        };
+        let example = match keyword {
+            "else" => "else".to_string(),
+            keyword => format!("{} x", keyword),
+        };
+        return Message::new(ParserError::MissingBlock(keyword)).hint(format!(
+            "the body of '{}' must be a block, e.g. `{} {{ ... }}`",
+            keyword, example
+        ));
+    }
+
+    // 标识符与常量同时出现时，期望的是一个表达式
+    let names: Vec<&str> = positives
+        .iter()
+        .filter_map(|rule| match rule {
+            Rule::ident | Rule::block | Rule::if_statement if expects(Rule::constant) => {
+                Some("expression")
+            }
+            rule => rule_name(*rule),
+        })
+        .collect();
+    let only = |name: &str| !names.is_empty() && names.iter().all(|candidate| *candidate == name);
+
+    if only("expression") {
+        let after = match previous {
+            Some(token) if !token.chars().all(|c| c.is_alphanumeric() || c == '_') => Some(token),
+            Some(keyword @ ("if" | "elif" | "rtb")) => Some(keyword),
+            _ => None,
+        };
+        return Message::new(ParserError::ExpectedExpression {
+            after: after.map(str::to_string),
+            found: unexpected,
+        });
+    }
+
+    if only("identifier") {
+        return Message::new(match previous {
+            Some(":") => ParserError::MissingTypeName,
+            Some("let") => ParserError::MissingVariableName,
+            _ => ParserError::ExpectedIdentifier(unexpected),
+        });
+    }
+
+    if names.contains(&"operator") && unclosed_parens(before) > 0 {
+        return Message::new(ParserError::UnclosedParen)
+            .hint("every '(' must be closed by a matching ')'")
+            .insert(")");
+    }
+
+    if names.contains(&"statement") || only("operator") {
+        let text = ParserError::Unexpected {
+            expected: vec![],
+            found: unexpected,
+        };
+        return match after.trim_start().chars().next() {
+            Some('}') => Message::new(text).hint("this '}' has no matching '{'"),
+            Some('{') => Message::new(text)
+                .hint("a block cannot follow an expression, end the statement with ';' first"),
+            Some('"') => Message::new(text)
+                .hint("string literals may only contain letters and must end with '\"'"),
+            Some(';') if names.contains(&"operator") => Message::new(text),
+            _ if names.contains(&"operator") => {
+                Message::new(text).hint("expected an operator or the end of the statement")
+            }
+            _ => Message::new(text),
+        };
+    }
+
+    let mut expected: Vec<&str> = vec![];
+    for name in names {
+        if !expected.contains(&name) {
+            expected.push(name);
+        }
+    }
+    Message::new(ParserError::Unexpected {
+        expected,
+        found: unexpected,
+    })
+}
+
+/// 块没有闭合时的错误信息
+pub fn unclosed_block() -> Message {
+    Message::new(ParserError::UnclosedBlock).hint("add a '}' to close the block opened here")
+}
@@ -1,16 +1,27 @@
 pub mod ast;
 pub mod errors;
 pub mod grammar;
+pub mod messages;
+pub mod recover;
+pub mod serialize;
+pub mod span;
 pub mod utils;
 
 use errors::ParserError;
 use pest::iterators::{Pair, Pairs};
 use pest::pratt_parser::PrattParser;
-use pest::Parser;
-use utils::*;
 
 pub use ast::*;
 pub use grammar::{BlueArchParser, Rule};
+pub use recover::{parse_items, parse_with_recovery, Item};
+
+/// 允许的最大括号与块的嵌套深度，防止深度嵌套的输入在解析与编译时导致栈溢出
+pub const MAX_NESTING_DEPTH: usize = 64;
+
+/// 允许的语法树的最大深度，运算符链中的每个运算符与 elif 链中的每个分支都算作一层
+///
+/// 编译的各个阶段递归地遍历语法树，没有括号的长运算符链与 elif 链同样会使递归过深。
+pub const MAX_EXPRESSION_DEPTH: usize = 1024;
 
 lazy_static::lazy_static! {
     static ref PRATT_PARSER: PrattParser<Rule> = {
@@ -44,10 +55,35 @@ pub fn parse_binary_op(pair: &Pair<Rule>) -> Result<BinaryOp, ParserError> {
         Rule::less_than_or_equal_to => Some(BinaryOp::Lte),
         _ => None,
     }
-    .ok_or(ParserError::SyntaxError(format!(
-        "Unknown binary operator: {:?}",
-        pair.as_rule()
-    )))
+    .ok_or(ParserError::UnknownOperator(pair.as_rule()))
+}
+
+/// 构造字面量无法被表示时的错误
+fn literal_error(kind: &'static str, pair: &Pair<Rule>) -> ParserError {
+    ParserError::LiteralOutOfRange(kind, pair.as_str().to_string())
+}
+
+/// 返回输入中括号与花括号的最大嵌套深度，注释中的括号不计入
+fn bracket_depth(input: &str) -> usize {
+    let mut depth = 0usize;
+    let mut max_depth = 0usize;
+    let mut comment = false;
+
+    for c in input.chars() {
+        match c {
+            '\n' => comment = false,
+            _ if comment => {}
+            '#' => comment = true,
+            '(' | '{' => {
+                depth += 1;
+                max_depth = max_depth.max(depth);
+            }
+            ')' | '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    max_depth
 }
 
 /// 解析表达式节点
@@ -60,58 +96,52 @@ pub fn parse_binary_op(pair: &Pair<Rule>) -> Result<BinaryOp, ParserError> {
 ///
 /// # 返回值
 ///
-/// 返回一个`Option<AstNode>`类型的值，表示解析后的表达式节点。如果解析失败，则返回`None`。
-pub fn parse_expr(pair: &Pair<Rule>) -> Option<AstNode> {
-    PRATT_PARSER
-        .map_primary(|primary: Pair<'_, Rule>| {
-            let pair = parse_pair(&primary).ok();
+/// 返回一个`Result<AstNode, ParserError>`类型的值，表示解析后的表达式节点。如果解析失败，则返回`Err`。
+pub fn parse_expr(pair: &Pair<Rule>) -> Result<AstNode, ParserError> {
+    // 运算符与操作数交替出现，过长的运算符链在构造语法树之前就被拒绝
+    if pair.clone().into_inner().count() / 2 > MAX_EXPRESSION_DEPTH {
+        return Err(ParserError::ExpressionTooDeep);
+    }
 
-            pair
-        })
+    PRATT_PARSER
+        .map_primary(|primary: Pair<'_, Rule>| parse_pair(&primary))
         .map_infix(|lhs, op, rhs| {
-            let left = lhs.unwrap();
-            let right = rhs;
-
-            Some(AstNode::Expr(
-                Box::new(left),
-                match op.as_rule() {
-                    Rule::add => Some(BinaryOp::Add),
-                    Rule::subtract => Some(BinaryOp::Sub),
-                    Rule::multiply => Some(BinaryOp::Mul),
-                    Rule::divide => Some(BinaryOp::Div),
-                    Rule::modulo => Some(BinaryOp::Mod),
-                    Rule::equals => Some(BinaryOp::Eq),
-                    Rule::not_equals => Some(BinaryOp::Neq),
-                    Rule::greater_than => Some(BinaryOp::Gt),
-                    Rule::greater_than_or_equal_to => Some(BinaryOp::Gte),
-                    Rule::less_than => Some(BinaryOp::Lt),
-                    Rule::less_than_or_equal_to => Some(BinaryOp::Lte),
-                    _ => None,
-                },
-                Some(Box::new(right.unwrap())),
+            Ok(AstNode::Expr(
+                Box::new(lhs?),
+                Some(parse_binary_op(&op)?),
+                Some(Box::new(rhs?)),
             ))
         })
         .parse(pair.clone().into_inner())
-        .map(|x| x.format_ast())
+        .and_then(|x| {
+            if x.depth() > MAX_EXPRESSION_DEPTH {
+                return Err(ParserError::ExpressionTooDeep);
+            }
+            Ok(x.format_ast())
+        })
 }
 
 pub fn parse_pair(pair: &Pair<Rule>) -> Result<AstNode, ParserError> {
     match pair.as_rule() {
-        Rule::expr => parse_expr(&pair).ok_or(ParserError::SyntaxError(format!(
-            "Invalid expression: {:?}",
-            pair.as_str()
-        ))),
+        Rule::expr => parse_expr(pair),
         // 常量节点
-        Rule::int => Ok(AstNode::Constant(
-            pair.as_str().parse::<i128>().unwrap().to_string(),
-        )),
-        Rule::float => Ok(AstNode::Constant(
-            pair.as_str().parse::<f64>().unwrap().to_string(),
-        )),
+        Rule::int => pair
+            .as_str()
+            .parse::<i128>()
+            .map(|value| AstNode::Constant(value.to_string()))
+            .map_err(|_| literal_error("Integer", pair)),
+        // 使用 `{:?}` 格式化以保留小数点，避免 `2.0` 被写成整数 `2`
+        Rule::float => pair
+            .as_str()
+            .parse::<f64>()
+            .map(|value| AstNode::Constant(format!("{:?}", value)))
+            .map_err(|_| literal_error("Float", pair)),
         Rule::string => Ok(AstNode::Constant(pair.as_str().to_string())),
-        Rule::boolean => Ok(AstNode::Constant(
-            pair.as_str().parse::<bool>().unwrap().to_string(),
-        )),
+        Rule::boolean => pair
+            .as_str()
+            .parse::<bool>()
+            .map(|value| AstNode::Constant(value.to_string()))
+            .map_err(|_| literal_error("Boolean", pair)),
         // 标识符
         Rule::ident => Ok(AstNode::Identifier(pair.as_str().to_string())),
         // 语句
@@ -159,10 +189,7 @@ pub fn parse_pair(pair: &Pair<Rule>) -> Result<AstNode, ParserError> {
                     AstNode::Elif(_, _) => elif_branches.push(node),
                     AstNode::Else(_) => else_branch = Some(Box::new(node)),
                     _ => {
-                        return Err(ParserError::UnknownError(format!(
-                            "Unknown pair in if statement: {:?}",
-                            pair.as_rule()
-                        )));
+                        return Err(ParserError::UnknownPair(pair.as_rule()));
                     }
                 }
             }
@@ -188,15 +215,16 @@ pub fn parse_pair(pair: &Pair<Rule>) -> Result<AstNode, ParserError> {
         // 块
         Rule::block => Ok(AstNode::Block(parse_pairs(pair.clone().into_inner())?)),
         // 递归解析
-        Rule::statement | Rule::constant => parse_pair(&pair.clone().into_inner().next().ok_or(
-            ParserError::UnknownError(format!("Unknown pair: {:?}", pair.as_rule())),
-        )?),
+        Rule::statement | Rule::constant => parse_pair(
+            &pair
+                .clone()
+                .into_inner()
+                .next()
+                .ok_or(ParserError::UnknownPair(pair.as_rule()))?,
+        ),
         // 其他
         Rule::EOI | Rule::COMMENT => Ok(AstNode::Empty),
-        _ => Err(ParserError::UnknownError(format!(
-            "Unknown pair: {:?}",
-            pair.as_rule()
-        ))),
+        _ => Err(ParserError::UnknownPair(pair.as_rule())),
     }
     .map(|x| x.format_ast())
 }
@@ -237,10 +265,13 @@ pub fn parse_pairs(pairs: Pairs<Rule>) -> Result<Vec<AstNode>, ParserError> {
 ///
 /// # 返回值
 ///
-/// 返回一个`Result<Vec<AstNode>, pest::error::Error<Rule>>`类型的值，表示解析后的节点对。如果解析失败，则返回`Err`。
+/// 返回一个`Result<AstNode, ParserError>`类型的值，表示解析后的程序。如果解析失败，则返回位置最靠前的错误；
+/// 需要得到所有错误时使用 `parse_with_recovery`。
 pub fn parse(input: &str) -> Result<AstNode, ParserError> {
-    let pairs = BlueArchParser::parse(Rule::program, input)?;
-    print_pairs(&pairs, None);
+    let (ast, diagnostics) = parse_with_recovery(input);
 
-    Ok(AstNode::Program(parse_pairs(pairs)?))
+    match diagnostics.into_iter().next() {
+        Some(diagnostic) => Err(diagnostic.error),
+        None => Ok(ast),
+    }
 }
@@ -0,0 +1,373 @@
+use pest::error::InputLocation;
+use pest::iterators::Pair;
+use pest::Parser;
+
+use super::ast::AstNode;
+use super::errors::{Diagnostic, Fix, Label, ParserError};
+use super::messages::{starts_with_keyword, translate, unclosed_block};
+use super::span::Span;
+use super::utils::print_pair;
+use super::{
+    bracket_depth, parse_pair, BlueArchParser, Rule, MAX_EXPRESSION_DEPTH, MAX_NESTING_DEPTH,
+};
+
+/// 解析输入字符串，在语法错误处恢复并继续解析
+///
+/// 遇到语法错误时，从出错的语句的开头跳到出错位置之后的下一个语句边界（`;`、`}` 或换行），记录错误后继续解析，
+/// 被跳过的代码在语法树中以 `AstNode::Error` 占位。跳过的代码中的块会被递归地解析，使得块中的错误也能被报告。
+///
+/// # 返回值
+///
+/// 返回部分的 `AstNode::Program` 与按位置排列的所有错误；没有错误时语法树与 `parse` 的结果相同。
+pub fn parse_with_recovery(input: &str) -> (AstNode, Vec<Diagnostic>) {
+    let (nodes, _, diagnostics) = recover(input);
+    (AstNode::Program(nodes), diagnostics)
+}
+
+/// 被成功解析的一项（顶层的块或语句），保留 pest 的解析结果以便得到其中各个部分的位置
+#[derive(Debug, Clone)]
+pub struct Item<'i> {
+    /// 解析时输入的起始位置，`pair` 中的位置需要加上该偏移量才是在整个输入中的位置
+    pub offset: usize,
+    pub pair: Pair<'i, Rule>,
+}
+
+impl Item<'_> {
+    /// 返回该项在整个输入中的区间
+    pub fn span(&self) -> Span {
+        self.span_of(&self.pair)
+    }
+
+    /// 返回该项中的某个部分在整个输入中的区间
+    pub fn span_of(&self, pair: &Pair<Rule>) -> Span {
+        let span = pair.as_span();
+        Span::new(self.offset + span.start(), self.offset + span.end())
+    }
+}
+
+/// 与 `parse_with_recovery` 相同地解析输入字符串，但返回被成功解析的各项的 pest 解析结果
+///
+/// 跳过的代码中被递归解析的块里的语句也会被返回，各项按位置排列。用于需要源代码位置的分析。
+pub fn parse_items(input: &str) -> (Vec<Item<'_>>, Vec<Diagnostic>) {
+    let (_, mut items, diagnostics) = recover(input);
+    items.sort_by_key(|item| item.offset);
+    (items, diagnostics)
+}
+
+fn recover(input: &str) -> (Vec<AstNode>, Vec<Item<'_>>, Vec<Diagnostic>) {
+    if bracket_depth(input) > MAX_NESTING_DEPTH {
+        return (
+            vec![],
+            vec![],
+            vec![Diagnostic::new(
+                Span::new(0, input.len()),
+                ParserError::BracketsTooDeep,
+            )],
+        );
+    }
+
+    let mut recovery = Recovery {
+        input,
+        items: vec![],
+        diagnostics: vec![],
+    };
+    let (nodes, _) = recovery.segment(0, None);
+
+    let mut diagnostics = recovery.diagnostics;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    (nodes, recovery.items, diagnostics)
+}
+
+struct Recovery<'a> {
+    input: &'a str,
+    items: Vec<Item<'a>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Recovery<'a> {
+    /// 解析从 `pos` 开始的一段代码
+    ///
+    /// `block` 为块的 `{` 的位置时解析块中的语句，直到匹配的 `}` 为止，否则解析到输入结束。
+    /// 返回解析出的节点与结束位置（块的 `}` 之后）。
+    fn segment(&mut self, mut pos: usize, block: Option<usize>) -> (Vec<AstNode>, usize) {
+        let mut nodes: Vec<AstNode> = vec![];
+        let rule = match block {
+            Some(_) => Rule::block_rest,
+            None => Rule::program,
+        };
+
+        loop {
+            pos = self.skip_trivia(pos);
+
+            if pos >= self.input.len() {
+                if let Some(open) = block {
+                    let message = unclosed_block();
+                    let end = self.input.len();
+                    self.diagnostics.push(
+                        Diagnostic::new(Span::new(open, open + 1), message.error)
+                            .with_hint(message.hint)
+                            .with_label(Label::new(
+                                Span::new(end, end),
+                                "the block is still open at the end of input",
+                            ))
+                            .with_fix(Some(Fix::insert(end, "}"))),
+                    );
+                }
+                return (nodes, pos);
+            }
+
+            if block.is_some() && self.input[pos..].starts_with('}') {
+                return (nodes, pos + 1);
+            }
+
+            let error = match BlueArchParser::parse(rule, &self.input[pos..]) {
+                Ok(_) => {
+                    let end = self.items(pos, self.input.len(), block.is_some(), &mut nodes);
+                    if end == pos {
+                        return (nodes, pos);
+                    }
+                    pos = end;
+                    continue;
+                }
+                Err(error) => error,
+            };
+
+            // pest 报告的是最远的失败位置，出错的语句从它之前最近的、使得之前的代码能被完整解析的边界开始
+            let error_pos = pos + error_offset(&error);
+            let start = self
+                .boundaries(pos, error_pos)
+                .into_iter()
+                .rev()
+                .find(|boundary| self.parses(pos, *boundary, block.is_some()))
+                .unwrap_or(pos);
+
+            let start = self.items(pos, start, block.is_some(), &mut nodes);
+            let start = self.skip_trivia(start);
+            if start >= self.input.len()
+                || (block.is_some() && self.input[start..].starts_with('}'))
+            {
+                pos = start;
+                continue;
+            }
+
+            let (end, blocks) = self.skip(start, error_pos, block.is_some());
+
+            // 位于块中的错误已经在递归解析块时被报告了
+            let reported = blocks
+                .iter()
+                .any(|(open, close)| *open < error_pos && error_pos <= *close);
+            if !reported {
+                let message = translate(
+                    &error.variant,
+                    &self.input[start..end],
+                    error_pos.saturating_sub(start),
+                );
+                self.diagnostics.push(
+                    Diagnostic::new(Span::new(error_pos, end.max(error_pos)), message.error)
+                        .with_hint(message.hint)
+                        .with_fix(message.insert.map(|text| Fix::insert(error_pos, text))),
+                );
+            }
+
+            nodes.push(AstNode::Error(self.input[start..end].trim().to_string()));
+            pos = end;
+        }
+    }
+
+    /// 逐项解析 `input[pos..end]` 中的语句，返回解析停止的位置
+    fn items(
+        &mut self,
+        mut pos: usize,
+        end: usize,
+        in_block: bool,
+        nodes: &mut Vec<AstNode>,
+    ) -> usize {
+        let rule = match in_block {
+            true => Rule::block_item,
+            false => Rule::program_item,
+        };
+
+        loop {
+            pos = self.skip_trivia(pos);
+            if pos >= end {
+                return pos;
+            }
+
+            let Ok(mut pairs) = BlueArchParser::parse(rule, &self.input[pos..end]) else {
+                return pos;
+            };
+            let Some(pair) = pairs.next() else {
+                return pos;
+            };
+            print_pair(&pair, None);
+
+            let item_end = pos + pair.as_span().end();
+            // 每个表达式的深度在解析时已经被检查，这里检查包含语句、块与 elif 链的整项的深度
+            let node = parse_pair(&pair).and_then(|node| match node.depth() {
+                depth if depth > MAX_EXPRESSION_DEPTH => Err(ParserError::ExpressionTooDeep),
+                _ => Ok(node),
+            });
+            match node {
+                Ok(AstNode::Empty) => {}
+                Ok(node) => {
+                    nodes.push(node);
+                    self.items.push(Item { offset: pos, pair });
+                }
+                Err(error) => {
+                    self.diagnostics
+                        .push(Diagnostic::new(Span::new(pos, item_end), error));
+                    nodes.push(AstNode::Error(self.input[pos..item_end].trim().to_string()));
+                }
+            }
+
+            if item_end <= pos {
+                return pos;
+            }
+            pos = item_end;
+        }
+    }
+
+    /// 判断 `input[start..end]` 能否被完整地解析为语句序列
+    fn parses(&self, start: usize, end: usize, in_block: bool) -> bool {
+        let rule = match in_block {
+            true => Rule::statements,
+            false => Rule::program,
+        };
+        BlueArchParser::parse(rule, &self.input[start..end]).is_ok()
+    }
+
+    /// 返回 `input[start..end]` 中不在块内的语句边界，即 `;`、换行与闭合的 `}` 之后的位置
+    fn boundaries(&self, start: usize, end: usize) -> Vec<usize> {
+        let mut boundaries: Vec<usize> = vec![];
+        let mut depth = 0usize;
+        let mut pos = start;
+
+        while pos < end {
+            let Some(c) = self.input[pos..].chars().next() else {
+                break;
+            };
+
+            match c {
+                '#' => {
+                    pos = self.skip_comment(pos);
+                    continue;
+                }
+                '"' => {
+                    pos = self.skip_string(pos);
+                    continue;
+                }
+                '{' => depth += 1,
+                '}' => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        boundaries.push(pos + 1);
+                    }
+                }
+                ';' | '\n' if depth == 0 => boundaries.push(pos + 1),
+                _ => {}
+            }
+
+            pos += c.len_utf8();
+        }
+
+        // 紧跟在块之后的 `elif` 与 `else` 属于前面的 if 语句，它们之前的位置不是边界
+        boundaries.retain(|boundary| {
+            let rest = &self.input[self.skip_trivia(*boundary)..];
+            let after_block = self.input[start..*boundary].trim_end().ends_with('}');
+            let continues = starts_with_keyword(rest, "elif") || starts_with_keyword(rest, "else");
+            *boundary <= end && !(after_block && continues)
+        });
+        boundaries
+    }
+
+    /// 从 `start` 开始跳过一个无法解析的语句，返回语句的结束位置以及其中被递归解析的块的 `{` 与 `}` 的位置
+    ///
+    /// 在到达错误的位置 `error_pos` 之前不会在边界处停下。遇到 `{` 时递归地解析块，块之后紧跟的
+    /// `elif` 与 `else` 分支也属于同一个语句。
+    fn skip(
+        &mut self,
+        start: usize,
+        error_pos: usize,
+        in_block: bool,
+    ) -> (usize, Vec<(usize, usize)>) {
+        let mut blocks: Vec<(usize, usize)> = vec![];
+        let mut pos = start;
+
+        while let Some(c) = self.input[pos..].chars().next() {
+            let reached = pos >= error_pos && pos > start;
+
+            match c {
+                '\n' if reached => break,
+                ';' if pos >= error_pos => {
+                    pos += 1;
+                    break;
+                }
+                '}' if reached && in_block => break,
+                '}' if pos >= error_pos => {
+                    pos += 1;
+                    break;
+                }
+                '#' => pos = self.skip_comment(pos),
+                '"' => pos = self.skip_string(pos),
+                '{' => {
+                    let reported = self.diagnostics.len();
+                    let (_, end) = self.segment(pos + 1, Some(pos));
+                    // 闭合的块到 `}` 为止，之后的错误不属于块；未闭合的块延伸到输入的末尾
+                    let unclosed = self.diagnostics[reported..].iter().any(|diagnostic| {
+                        diagnostic.span.start == pos
+                            && diagnostic.error == ParserError::UnclosedBlock
+                    });
+                    blocks.push((pos, if unclosed { end } else { end - 1 }));
+                    pos = end;
+
+                    let next = self.skip_trivia(pos);
+                    let rest = &self.input[next..];
+                    if !(starts_with_keyword(rest, "elif") || starts_with_keyword(rest, "else")) {
+                        break;
+                    }
+                    pos = next;
+                }
+                c => pos += c.len_utf8(),
+            }
+        }
+
+        (pos, blocks)
+    }
+
+    /// 跳过空白字符与注释
+    fn skip_trivia(&self, mut pos: usize) -> usize {
+        while let Some(c) = self.input[pos..].chars().next() {
+            match c {
+                ' ' | '\n' | '\t' => pos += 1,
+                '#' => pos = self.skip_comment(pos),
+                _ => break,
+            }
+        }
+        pos
+    }
+
+    /// 跳过从 `pos` 开始的注释，返回换行符的位置
+    fn skip_comment(&self, pos: usize) -> usize {
+        self.input[pos..]
+            .find('\n')
+            .map_or(self.input.len(), |index| pos + index)
+    }
+
+    /// 跳过从 `pos` 开始的字符串，未闭合的字符串在行尾结束
+    fn skip_string(&self, pos: usize) -> usize {
+        let rest = &self.input[pos + 1..];
+        match rest.find(['"', '\n']) {
+            Some(index) if rest[index..].starts_with('"') => pos + 1 + index + 1,
+            Some(index) => pos + 1 + index,
+            None => self.input.len(),
+        }
+    }
+}
+
+/// 返回 pest 错误在被解析的字符串中的位置
+fn error_offset(error: &pest::error::Error<Rule>) -> usize {
+    match error.location {
+        InputLocation::Pos(pos) => pos,
+        InputLocation::Span((start, _)) => start,
+    }
+}
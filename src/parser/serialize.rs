@@ -0,0 +1,521 @@
+//! 抽象语法树的 JSON 与 S 表达式表示
+//!
+//! 每个节点序列化为一个带有 `kind` 与 `span` 的 JSON 对象，其余的字段取决于节点的种类：
+//!
+//! | `kind`        | 字段                                                    |
+//! | ------------- | ------------------------------------------------------- |
+//! | `Program`     | `body`: 节点数组                                        |
+//! | `Block`       | `body`: 节点数组                                        |
+//! | `Constant`    | `type`: `int`、`float`、`bool` 或 `string`，`value`: 值 |
+//! | `Identifier`  | `name`: 名字                                            |
+//! | `Expr`        | `left`: 节点，`op`: 运算符或 `null`，`right`: 节点或 `null` |
+//! | `Assign`      | `target`: 节点，`annotation`: 节点或 `null`，`value`: 节点 |
+//! | `SetValue`    | `target`: 节点，`value`: 节点                           |
+//! | `ReturnBlock` | `value`: 节点                                           |
+//! | `If`          | `condition`、`then`: 节点，`elif`: 节点数组，`else`: 节点或 `null` |
+//! | `Elif`        | `condition`、`then`: 节点                               |
+//! | `Else`        | `body`: 节点                                            |
+//! | `Error`       | `code`: 被跳过的源代码                                  |
+//! | `Empty`       | 无                                                      |
+//!
+//! `span` 为 `{ "start": 起点, "end": 终点 }`（字节偏移量，终点不包含在内），没有位置信息时为 `null`。
+//! 整数常量的值以字符串表示，以免超出 JSON 数字的精度；无法识别的常量的 `type` 为 `null`，`value` 为原始的代码。
+//!
+//! S 表达式与 JSON 的结构相同，形如 `(assign @0..10 (identifier @4..5 "a") nil (constant @8..9 int 1))`，
+//! 只用于阅读，没有对应的解析器。
+
+use pest::iterators::Pair;
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use super::span::Span;
+use super::{AstNode, BinaryOp, Item, Rule, PRATT_PARSER};
+
+/// 与抽象语法树结构相同的区间树，`children` 与 `AstNode::children` 的结果一一对应
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SpanTree {
+    pub span: Span,
+    pub children: Vec<SpanTree>,
+}
+
+impl SpanTree {
+    /// 由被成功解析的各项构造区间树，与由各项的节点组成的 `AstNode::Program` 对应
+    pub fn from_items(input: &str, items: &[Item]) -> Self {
+        Self {
+            span: Span::new(0, input.len()),
+            children: items.iter().map(Self::from_item).collect(),
+        }
+    }
+
+    /// 构造与 `parse_pair(&item.pair)` 的结果对应的区间树
+    pub fn from_item(item: &Item) -> Self {
+        Self::from_pair(item.offset, &item.pair)
+    }
+
+    /// 返回第 `index` 个子节点的区间树，没有时返回空的区间树
+    pub fn child(&self, index: usize) -> &SpanTree {
+        static EMPTY: SpanTree = SpanTree {
+            span: Span { start: 0, end: 0 },
+            children: vec![],
+        };
+        self.children.get(index).unwrap_or(&EMPTY)
+    }
+
+    /// 构造与 `parse_pair` 的结果对应的区间树
+    ///
+    /// `format_ast` 为赋值语句的值与 if 的条件加上的 `AstNode::Expr` 没有对应的语法规则，
+    /// 序列化时由被包装的节点的区间代替。
+    fn from_pair(offset: usize, pair: &Pair<Rule>) -> Self {
+        let span = pair.as_span();
+        let span = Span::new(offset + span.start(), offset + span.end());
+        let children = |pair: &Pair<Rule>| -> Vec<SpanTree> {
+            pair.clone()
+                .into_inner()
+                .map(|inner| Self::from_pair(offset, &inner))
+                .collect()
+        };
+
+        match pair.as_rule() {
+            // 二元表达式的区间包括操作数外的括号，括号中的表达式本身的区间不包括括号
+            Rule::expr => {
+                let (tree, _) = PRATT_PARSER
+                    .map_primary(|primary| {
+                        let tree = Self::from_pair(offset, &primary);
+                        let span = match primary.as_rule() {
+                            Rule::expr => parenthesized(offset, &primary),
+                            _ => tree.span,
+                        };
+                        (tree, span)
+                    })
+                    .map_infix(|(lhs, lhs_span), _, (rhs, rhs_span)| {
+                        let span = Span::new(lhs_span.start, rhs_span.end);
+                        let tree = Self {
+                            span,
+                            children: vec![lhs, rhs],
+                        };
+                        (tree, span)
+                    })
+                    .parse(pair.clone().into_inner());
+                tree
+            }
+            Rule::statement | Rule::constant => match pair.clone().into_inner().next() {
+                Some(inner) => Self::from_pair(offset, &inner),
+                None => Self::leaf(span),
+            },
+            Rule::assign_statement
+            | Rule::set_value_statement
+            | Rule::return_block_statement
+            | Rule::if_statement
+            | Rule::elif_statement
+            | Rule::else_statement
+            | Rule::block => Self {
+                span,
+                children: children(pair),
+            },
+            _ => Self::leaf(span),
+        }
+    }
+
+    /// 没有子节点的区间树
+    pub fn leaf(span: Span) -> Self {
+        Self {
+            span,
+            children: vec![],
+        }
+    }
+}
+
+/// 返回括号中的表达式连同括号的区间
+fn parenthesized(offset: usize, pair: &Pair<Rule>) -> Span {
+    let input = pair.get_input();
+    let span = pair.as_span();
+    let before = input[..span.start()].trim_end();
+    let after = input[span.end()..].trim_start();
+
+    let start = match before.ends_with('(') {
+        true => before.len() - 1,
+        false => span.start(),
+    };
+    let end = match after.starts_with(')') {
+        true => input.len() - after.len() + 1,
+        false => span.end(),
+    };
+    Span::new(offset + start, offset + end)
+}
+
+/// 反序列化 JSON 时的错误
+#[derive(Error, Debug)]
+pub enum AstJsonError {
+    #[error("Expected a node object, found: {0}")]
+    ExpectedNode(Value),
+    #[error("Unknown node kind: {0}")]
+    UnknownKind(String),
+    #[error("Missing or invalid field `{1}` in {0} node")]
+    InvalidField(String, &'static str),
+    #[error("Unknown operator: {0}")]
+    UnknownOperator(String),
+    #[error("Invalid {0} constant: {1}")]
+    InvalidConstant(String, Value),
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// 将抽象语法树序列化为 JSON，`spans` 为 `None` 时所有节点的 `span` 为 `null`
+pub fn to_json(node: &AstNode, spans: Option<&SpanTree>) -> Value {
+    let children = node.children();
+    let child = |index: usize| spans.and_then(|tree| tree.children.get(index));
+    let nodes = |nodes: &[AstNode], from: usize| -> Vec<Value> {
+        nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| to_json(node, child(from + index)))
+            .collect()
+    };
+
+    let mut object = Map::new();
+    object.insert("kind".to_string(), json!(kind(node)));
+    object.insert(
+        "span".to_string(),
+        match spans {
+            Some(tree) => json!({ "start": tree.span.start, "end": tree.span.end }),
+            None => Value::Null,
+        },
+    );
+    let mut field = |name: &str, value: Value| {
+        object.insert(name.to_string(), value);
+    };
+
+    match node {
+        AstNode::Program(body) | AstNode::Block(body) => field("body", json!(nodes(body, 0))),
+        AstNode::Constant(literal) => {
+            let (ty, value) = constant(literal);
+            field("type", json!(ty));
+            field("value", value);
+        }
+        AstNode::Identifier(name) => field("name", json!(name)),
+        // `format_ast` 加上的包装节点与被包装的节点共用区间
+        AstNode::Expr(left, None, None) => {
+            field("left", to_json(left, spans));
+            field("op", Value::Null);
+            field("right", Value::Null);
+        }
+        AstNode::Expr(left, op, right) => {
+            field("left", to_json(left, child(0)));
+            field("op", json!(op.as_ref().map(BinaryOp::as_raw)));
+            field(
+                "right",
+                right
+                    .as_ref()
+                    .map_or(Value::Null, |right| to_json(right, child(1))),
+            );
+        }
+        AstNode::Assign(target, annotation, value) => {
+            field("target", to_json(target, child(0)));
+            field(
+                "annotation",
+                annotation
+                    .as_ref()
+                    .map_or(Value::Null, |annotation| to_json(annotation, child(1))),
+            );
+            field("value", to_json(value, child(children.len() - 1)));
+        }
+        AstNode::SetValue(target, value) => {
+            field("target", to_json(target, child(0)));
+            field("value", to_json(value, child(1)));
+        }
+        AstNode::ReturnBlock(value) => field("value", to_json(value, child(0))),
+        AstNode::If(condition, then, elif_nodes, else_node) => {
+            field("condition", to_json(condition, child(0)));
+            field("then", to_json(then, child(1)));
+            field("elif", json!(nodes(elif_nodes, 2)));
+            field(
+                "else",
+                else_node.as_ref().map_or(Value::Null, |node| {
+                    to_json(node, child(2 + elif_nodes.len()))
+                }),
+            );
+        }
+        AstNode::Elif(condition, then) => {
+            field("condition", to_json(condition, child(0)));
+            field("then", to_json(then, child(1)));
+        }
+        AstNode::Else(body) => field("body", to_json(body, child(0))),
+        AstNode::Error(code) => field("code", json!(code)),
+        AstNode::Empty => {}
+    }
+
+    Value::Object(object)
+}
+
+/// 由 `to_json` 的结果构造抽象语法树，`span` 被忽略
+pub fn from_json(value: &Value) -> Result<AstNode, AstJsonError> {
+    let object = value
+        .as_object()
+        .ok_or_else(|| AstJsonError::ExpectedNode(value.clone()))?;
+    let kind = object
+        .get("kind")
+        .and_then(Value::as_str)
+        .ok_or_else(|| AstJsonError::ExpectedNode(value.clone()))?;
+
+    let invalid = |field: &'static str| AstJsonError::InvalidField(kind.to_string(), field);
+    let get = |field: &'static str| object.get(field).ok_or_else(|| invalid(field));
+    let node = |field: &'static str| get(field).and_then(from_json).map(Box::new);
+    let optional = |field: &'static str| match object.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => from_json(value).map(|node| Some(Box::new(node))),
+    };
+    let nodes = |field: &'static str| {
+        get(field)?
+            .as_array()
+            .ok_or_else(|| invalid(field))?
+            .iter()
+            .map(from_json)
+            .collect::<Result<Vec<AstNode>, AstJsonError>>()
+    };
+    let string = |field: &'static str| {
+        get(field)?
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| invalid(field))
+    };
+
+    Ok(match kind {
+        "Program" => AstNode::Program(nodes("body")?),
+        "Block" => AstNode::Block(nodes("body")?),
+        "Constant" => AstNode::Constant(literal(
+            object.get("type").unwrap_or(&Value::Null),
+            get("value")?,
+        )?),
+        "Identifier" => AstNode::Identifier(string("name")?),
+        "Expr" => {
+            let op = match object.get("op") {
+                None | Some(Value::Null) => None,
+                Some(Value::String(op)) => {
+                    Some(operator(op).ok_or_else(|| AstJsonError::UnknownOperator(op.clone()))?)
+                }
+                Some(_) => return Err(invalid("op")),
+            };
+            AstNode::Expr(node("left")?, op, optional("right")?)
+        }
+        "Assign" => AstNode::Assign(node("target")?, optional("annotation")?, node("value")?),
+        "SetValue" => AstNode::SetValue(node("target")?, node("value")?),
+        "ReturnBlock" => AstNode::ReturnBlock(node("value")?),
+        "If" => AstNode::If(
+            node("condition")?,
+            node("then")?,
+            nodes("elif")?,
+            optional("else")?,
+        ),
+        "Elif" => AstNode::Elif(node("condition")?, node("then")?),
+        "Else" => AstNode::Else(node("body")?),
+        "Error" => AstNode::Error(string("code")?),
+        "Empty" => AstNode::Empty,
+        kind => return Err(AstJsonError::UnknownKind(kind.to_string())),
+    })
+}
+
+/// 由 JSON 文本构造抽象语法树
+pub fn from_json_str(text: &str) -> Result<AstNode, AstJsonError> {
+    from_json(&serde_json::from_str(text)?)
+}
+
+/// 将抽象语法树序列化为 S 表达式，较长的列表被折行并缩进
+pub fn to_sexpr(node: &AstNode, spans: Option<&SpanTree>) -> String {
+    let mut output = String::new();
+    sexpr(&to_json(node, spans)).render(0, &mut output);
+    output.push('\n');
+    output
+}
+
+/// 每行的最大宽度，超过时列表被折行
+const LINE_WIDTH: usize = 80;
+
+enum Sexpr {
+    Atom(String),
+    List(Vec<Sexpr>),
+}
+
+impl Sexpr {
+    fn inline(&self) -> String {
+        match self {
+            Sexpr::Atom(atom) => atom.clone(),
+            Sexpr::List(items) => format!(
+                "({})",
+                items
+                    .iter()
+                    .map(Sexpr::inline)
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            ),
+        }
+    }
+
+    /// 放不下一行时，开头的原子留在第一行，其余的元素各占一行
+    fn render(&self, indent: usize, output: &mut String) {
+        let inline = self.inline();
+        let Sexpr::List(items) = self else {
+            output.push_str(&inline);
+            return;
+        };
+        if indent + inline.len() <= LINE_WIDTH {
+            output.push_str(&inline);
+            return;
+        }
+
+        let head = items
+            .iter()
+            .take_while(|item| matches!(item, Sexpr::Atom(_)))
+            .count();
+        output.push('(');
+        output.push_str(
+            &items[..head]
+                .iter()
+                .map(Sexpr::inline)
+                .collect::<Vec<String>>()
+                .join(" "),
+        );
+        for item in &items[head..] {
+            output.push('\n');
+            output.push_str(&" ".repeat(indent + 2));
+            item.render(indent + 2, output);
+        }
+        output.push(')');
+    }
+}
+
+/// 将 `to_json` 的结果转换为 S 表达式
+fn sexpr(value: &Value) -> Sexpr {
+    let atom = |text: String| Sexpr::Atom(text);
+    let field = |name: &str| value.get(name).unwrap_or(&Value::Null);
+    let node = |name: &str| match field(name) {
+        Value::Null => atom("nil".to_string()),
+        value => sexpr(value),
+    };
+
+    let kind = field("kind").as_str().unwrap_or_default();
+    let mut items = vec![atom(snake_case(kind))];
+    if let (Some(start), Some(end)) = (field("span").get("start"), field("span").get("end")) {
+        items.push(atom(format!("@{}..{}", start, end)));
+    }
+
+    match kind {
+        "Program" | "Block" => {
+            items.extend(field("body").as_array().into_iter().flatten().map(sexpr));
+        }
+        "Constant" => {
+            let value = match (field("type").as_str(), field("value")) {
+                (Some("int"), Value::String(int)) => int.clone(),
+                (_, value) => value.to_string(),
+            };
+            items.push(atom(field("type").as_str().unwrap_or("nil").to_string()));
+            items.push(atom(value));
+        }
+        "Identifier" => items.push(atom(field("name").to_string())),
+        "Expr" => {
+            if let Some(op) = field("op").as_str() {
+                items.push(atom(op.to_string()));
+            }
+            items.push(node("left"));
+            if !field("right").is_null() {
+                items.push(node("right"));
+            }
+        }
+        "Assign" => items.extend([node("target"), node("annotation"), node("value")]),
+        "SetValue" => items.extend([node("target"), node("value")]),
+        "ReturnBlock" => items.push(node("value")),
+        "If" => {
+            items.extend([node("condition"), node("then")]);
+            items.extend(field("elif").as_array().into_iter().flatten().map(sexpr));
+            if !field("else").is_null() {
+                items.push(node("else"));
+            }
+        }
+        "Elif" => items.extend([node("condition"), node("then")]),
+        "Else" => items.push(node("body")),
+        "Error" => items.push(atom(field("code").to_string())),
+        _ => {}
+    }
+
+    Sexpr::List(items)
+}
+
+fn snake_case(kind: &str) -> String {
+    let mut name = String::new();
+    for (index, c) in kind.chars().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            name.push('_');
+        }
+        name.push(c.to_ascii_lowercase());
+    }
+    name
+}
+
+fn kind(node: &AstNode) -> &'static str {
+    match node {
+        AstNode::Program(_) => "Program",
+        AstNode::Block(_) => "Block",
+        AstNode::Constant(_) => "Constant",
+        AstNode::Expr(_, _, _) => "Expr",
+        AstNode::Identifier(_) => "Identifier",
+        AstNode::Assign(_, _, _) => "Assign",
+        AstNode::SetValue(_, _) => "SetValue",
+        AstNode::ReturnBlock(_) => "ReturnBlock",
+        AstNode::Empty => "Empty",
+        AstNode::Error(_) => "Error",
+        AstNode::If(_, _, _, _) => "If",
+        AstNode::Elif(_, _) => "Elif",
+        AstNode::Else(_) => "Else",
+    }
+}
+
+/// 返回常量的类型与值，规则与编译器解析字面量时相同
+fn constant(literal: &str) -> (Option<&'static str>, Value) {
+    if literal.len() >= 2 && literal.starts_with('"') && literal.ends_with('"') {
+        return (Some("string"), json!(literal[1..literal.len() - 1]));
+    }
+    if let Ok(boolean) = literal.parse::<bool>() {
+        return (Some("bool"), json!(boolean));
+    }
+    if let Ok(int) = literal.parse::<i128>() {
+        return (Some("int"), json!(int.to_string()));
+    }
+    match literal.parse::<f64>() {
+        Ok(float) if float.is_finite() => (Some("float"), json!(float)),
+        _ => (None, json!(literal)),
+    }
+}
+
+/// 由常量的类型与值构造字面量，与 `parse_pair` 生成的字面量相同
+fn literal(ty: &Value, value: &Value) -> Result<String, AstJsonError> {
+    let ty = ty.as_str();
+    let literal = match (ty, value) {
+        (Some("int"), Value::String(int)) => int.parse::<i128>().ok().map(|int| int.to_string()),
+        (Some("int"), Value::Number(int)) => int.as_i64().map(|int| int.to_string()),
+        (Some("float"), Value::Number(float)) => float.as_f64().map(|float| format!("{:?}", float)),
+        (Some("bool"), Value::Bool(boolean)) => Some(boolean.to_string()),
+        (Some("string"), Value::String(string)) => Some(format!("\"{}\"", string)),
+        (None, Value::String(literal)) => Some(literal.clone()),
+        _ => None,
+    };
+
+    literal.ok_or_else(|| {
+        AstJsonError::InvalidConstant(ty.unwrap_or("untyped").to_string(), value.clone())
+    })
+}
+
+fn operator(op: &str) -> Option<BinaryOp> {
+    Some(match op {
+        "+" => BinaryOp::Add,
+        "-" => BinaryOp::Sub,
+        "*" => BinaryOp::Mul,
+        "/" => BinaryOp::Div,
+        "%" => BinaryOp::Mod,
+        "==" => BinaryOp::Eq,
+        "!=" => BinaryOp::Neq,
+        ">" => BinaryOp::Gt,
+        ">=" => BinaryOp::Gte,
+        "<" => BinaryOp::Lt,
+        "<=" => BinaryOp::Lte,
+        _ => return None,
+    })
+}
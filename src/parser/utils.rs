@@ -13,7 +13,7 @@ pub fn print_pair(pair: &Pair<Rule>, level: Option<u8>) {
     ));
 
     let inner = pair.into_inner();
-    if inner.len() > 0 {
+    if !inner.is_empty() {
         print_pairs(&inner, Some(level.unwrap_or(0) + 1));
     }
 }
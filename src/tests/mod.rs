@@ -1,3 +1,39 @@
+mod test_analysis;
+mod test_asm;
 mod test_assign;
+mod test_block;
+mod test_branch;
+mod test_c;
+mod test_cli;
+mod test_disasm;
+mod test_dot;
+mod test_explain;
 mod test_expr;
+mod test_fold;
+mod test_format;
+mod test_fuzz;
+mod test_if_value;
+mod test_interpreter;
+mod test_ir;
+mod test_lsp;
+mod test_messages;
+mod test_operand;
+mod test_peephole;
+mod test_recover;
+mod test_register;
+mod test_section;
+mod test_serialize;
 mod test_set_value;
+mod test_snapshot;
+mod test_stack;
+mod test_verify;
+mod test_wat;
+
+/// 用解释器执行字节码，返回程序的输出
+fn output(codes: &[crate::compiler::ByteCode]) -> String {
+    use crate::interpreter::Interpreter;
+
+    let mut interpreter = Interpreter::new();
+    interpreter.run(codes).unwrap();
+    interpreter.output()
+}
@@ -0,0 +1,155 @@
+#[test]
+fn test_analysis_symbols() {
+    use crate::analysis::{analyze, ReferenceKind, Type};
+
+    let code = "let a = 1;\nlet b: float = a;\nif a > 0 {\n    a = \"s\";\n}\nlet c = a + \"t\";";
+    let analysis = analyze(code);
+
+    assert!(analysis.diagnostics.is_empty());
+    let symbols: Vec<(&str, Type)> = analysis
+        .symbols
+        .symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.ty))
+        .collect();
+    assert_eq!(
+        symbols,
+        vec![("a", Type::Int), ("b", Type::Float), ("c", Type::Str)]
+    );
+
+    // 第 4 行的赋值改变了 `a` 的类型
+    let write = analysis
+        .symbols
+        .reference_at(code.find("a = \"s\"").unwrap())
+        .unwrap();
+    assert_eq!(write.kind, ReferenceKind::Write);
+    assert_eq!(write.ty, Type::Str);
+
+    let a: Vec<usize> = analysis
+        .symbols
+        .references_of(0)
+        .iter()
+        .map(|reference| reference.span.location(code).0)
+        .collect();
+    assert_eq!(a, vec![1, 2, 3, 4, 6]);
+}
+
+#[test]
+fn test_analysis_redeclaration() {
+    use crate::analysis::analyze;
+
+    let code = "let x = 1;\nx;\nlet x = true;\nx;";
+    let analysis = analyze(code);
+
+    let first = analysis
+        .symbols
+        .definition_at(code.find("x;").unwrap())
+        .unwrap();
+    assert_eq!(first.span.location(code), (1, 5));
+    let second = analysis
+        .symbols
+        .definition_at(code.rfind("x;").unwrap())
+        .unwrap();
+    assert_eq!(second.span.location(code), (3, 5));
+    assert!(analysis.symbols.definition_at(0).is_none());
+}
+
+#[test]
+fn test_analysis_diagnostics() {
+    use crate::analysis::{analyze, Severity};
+
+    let code = "let a = 1;\nlet b = a / (2 - 2);\nlet c = 1 / 0;\nif false {\n}";
+    let analysis = analyze(code);
+    let diagnostics: Vec<(Severity, &str)> = analysis
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let span = diagnostic.span;
+            (diagnostic.severity, &code[span.start..span.end])
+        })
+        .collect();
+    assert_eq!(
+        diagnostics,
+        vec![
+            (Severity::Error, "1 / 0"),
+            (Severity::Warning, "if false {\n}")
+        ]
+    );
+
+    // 有语法错误时只报告语法错误，但仍然建立符号表
+    let analysis = analyze("let a = 1;\nlet b = ;\nlet c = a;");
+    assert_eq!(analysis.diagnostics.len(), 1);
+    assert_eq!(analysis.diagnostics[0].severity, Severity::Error);
+    assert_eq!(analysis.symbols.symbols.len(), 2);
+}
+
+#[test]
+fn test_analysis_diagnostic_spans() {
+    use crate::analysis::analyze;
+
+    // 死分支的位置是分支本身，所在的 if 作为次要位置，警告中的条件取自源代码
+    let code = "let a = 2;\nif true {\n    a = 1;\n} elif a==2 { a = 2; } else {\n    a = 3;\n}\n";
+    let analysis = analyze(code);
+    let slices: Vec<(&str, &str)> = analysis
+        .diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let span = diagnostic.span;
+            (diagnostic.message.as_str(), &code[span.start..span.end])
+        })
+        .collect();
+    assert_eq!(
+        slices,
+        [
+            (
+                "Unreachable branch: `elif a==2` can never run",
+                "elif a==2 { a = 2; }"
+            ),
+            (
+                "Unreachable branch: `else` can never run",
+                "else {\n    a = 3;\n}"
+            ),
+        ]
+    );
+    for diagnostic in &analysis.diagnostics {
+        assert_eq!(diagnostic.labels.len(), 1);
+        let span = diagnostic.labels[0].span;
+        assert_eq!(span.start, code.find("if true").unwrap());
+        assert_eq!(span.end, code.len() - 1);
+    }
+
+    // 作为值的 if 与 `rtb` 中的错误同样标记在出错的节点上
+    let code =
+        "let x = 1;\nlet y = { rtb (x * 2) + 4 % (3 - 3); };\nlet z = if x > 0 { rtb 1; };\n";
+    let slices: Vec<&str> = analyze(code)
+        .diagnostics
+        .iter()
+        .map(|diagnostic| &code[diagnostic.span.start..diagnostic.span.end])
+        .collect();
+    assert_eq!(slices, ["4 % (3 - 3)", "if x > 0 { rtb 1; }"]);
+}
+
+#[test]
+fn test_analysis_infer() {
+    use crate::analysis::types::{infer, Type};
+    use crate::parser::ast::AstNode;
+    use crate::parser::parse;
+
+    let cases = [
+        ("1 + 2 * 3", Type::Int),
+        ("1 + 2.0", Type::Float),
+        ("1 + 2 == 3", Type::Bool),
+        ("\"a\" + \"b\"", Type::Str),
+        ("\"a\" - \"b\"", Type::Unknown),
+        ("x < 1", Type::Bool),
+        ("x + 1", Type::Unknown),
+        ("true != 1", Type::Bool),
+    ];
+
+    for (code, expected) in cases {
+        let AstNode::Program(nodes) = parse(code).unwrap() else {
+            panic!("expected a program");
+        };
+        assert_eq!(infer(&nodes[0], &|_| Type::Unknown), expected, "{}", code);
+    }
+}
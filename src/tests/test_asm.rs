@@ -0,0 +1,144 @@
+#[test]
+fn test_asm() {
+    use crate::compiler::asm::assemble;
+    use crate::compiler::value::Value;
+    use crate::interpreter::Interpreter;
+
+    let text = r#"
+; 返回较大值的 Section
+Push 3 "a b" 2.5
+StoreName x
+StoreName "the name"
+.section @max           ; 以栈顶的两个值调用
+    Dup
+    LoadName x
+    Gt
+    .section @"then branch"
+        Push true
+        Return
+    .end
+    JumpIf @"then branch"
+    Return
+.end
+0004:  Push 1
+       Jump @max
+"#;
+    let codes = assemble(text).unwrap();
+
+    assert_eq!(codes.len(), 16);
+    assert_eq!(codes[0].args(), vec!["3", "\"a b\"", "2.5"]);
+    assert_eq!(codes[2].args(), vec!["the name"]);
+    assert_eq!(codes[7].args(), vec!["then branch"]);
+
+    let mut interpreter = Interpreter::new();
+    interpreter.run(&codes).unwrap();
+    assert_eq!(interpreter.stack(), &[Value::Int(3), Value::Int(1)]);
+    assert_eq!(
+        interpreter.name("the name"),
+        Some(&Value::Str("a b".to_string()))
+    );
+}
+
+#[test]
+fn test_asm_round_trip() {
+    use crate::compiler::asm::assemble;
+    use crate::compiler::disasm::disassemble;
+    use crate::compiler::section::SectionNamer;
+    use crate::fuzz::Generator;
+    use crate::optimizer::{optimize, optimize_bytecodes};
+    use crate::parser::parse;
+
+    for seed in 0..200 {
+        let program = parse(&Generator::new(seed).program()).unwrap();
+
+        for level in 0..=2 {
+            let Ok((ast, _)) = optimize(&program, level) else {
+                continue;
+            };
+            let Ok(codes) = ast.compile_with(&mut SectionNamer::seeded(seed)) else {
+                continue;
+            };
+            let (codes, _) = optimize_bytecodes(&codes, level);
+
+            let text = disassemble(&codes);
+            assert_eq!(
+                assemble(&text).unwrap(),
+                codes,
+                "seed {} level {}:\n{}",
+                seed,
+                level,
+                text
+            );
+        }
+    }
+}
+
+#[test]
+fn test_asm_errors() {
+    use crate::compiler::asm::{assemble, AsmError};
+
+    let cases = [
+        (
+            "Push 1\nPsh 2",
+            AsmError::UnknownInstruction(2, "Psh".to_string()),
+        ),
+        (
+            "Push",
+            AsmError::WrongArity(
+                1,
+                "Push".to_string(),
+                "at least one constant".to_string(),
+                0,
+            ),
+        ),
+        (
+            "Pop 1",
+            AsmError::WrongArity(1, "Pop".to_string(), "no operands".to_string(), 1),
+        ),
+        (
+            "Push x",
+            AsmError::WrongOperand(
+                1,
+                "x".to_string(),
+                "Push".to_string(),
+                "constant".to_string(),
+            ),
+        ),
+        (
+            "LoadName @a",
+            AsmError::WrongOperand(
+                1,
+                "@a".to_string(),
+                "LoadName".to_string(),
+                "name".to_string(),
+            ),
+        ),
+        (
+            "Jump a",
+            AsmError::WrongOperand(
+                1,
+                "a".to_string(),
+                "Jump".to_string(),
+                "section label".to_string(),
+            ),
+        ),
+        ("Push \"a", AsmError::UnterminatedString(1)),
+        (".end", AsmError::UnmatchedEnd(1)),
+        (
+            "\n.section @a\n.section @b\n.end",
+            AsmError::UnclosedSection(2, "a".to_string()),
+        ),
+        (
+            ".section @a\n.end\n.section @a\n.end",
+            AsmError::DuplicateSection(3, "a".to_string()),
+        ),
+        (
+            "JumpIf @nowhere",
+            AsmError::UndefinedSection(1, "nowhere".to_string()),
+        ),
+    ];
+
+    for (text, expected) in cases {
+        assert_eq!(assemble(text), Err(expected), "{:?}", text);
+    }
+}
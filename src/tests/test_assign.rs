@@ -2,7 +2,7 @@
 fn test_assign1() {
     use crate::parser::parse_pairs;
     use crate::parser::BlueArchParser;
-    use crate::Rule;
+    use crate::parser::Rule;
     use pest::Parser;
 
     let expr = "let a = 1";
@@ -22,7 +22,7 @@ fn test_assign1() {
 fn test_assign2() {
     use crate::parser::parse_pairs;
     use crate::parser::BlueArchParser;
-    use crate::Rule;
+    use crate::parser::Rule;
     use pest::Parser;
 
     let expr = "let a = 1 + 2";
@@ -39,7 +39,7 @@ fn test_assign2() {
 fn test_assign3() {
     use crate::parser::parse_pairs;
     use crate::parser::BlueArchParser;
-    use crate::Rule;
+    use crate::parser::Rule;
     use pest::Parser;
 
     let expr = "let a: int = 1 + 2 + 3";
@@ -55,10 +55,10 @@ fn test_assign3() {
 #[test]
 fn test_assign4() {
     use crate::parser::BlueArchParser;
-    use crate::Rule;
+    use crate::parser::Rule;
     use pest::Parser;
 
     let expr = "a: int = 1";
     let pairs = BlueArchParser::parse(Rule::program, expr);
-    assert!(!pairs.is_ok());
+    assert!(pairs.is_err());
 }
@@ -0,0 +1,66 @@
+#[test]
+fn test_block_value() {
+    use super::output;
+    use crate::interpreter::Interpreter;
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let programs = [
+        ("let x = { let a = 2; rtb a * 3; }; x;", "6\na = 2\nx = 6\n"),
+        ("{ rtb 1; } + 2;", "3\n"),
+        ("let x = { rtb { rtb 4; } * 2; }; x;", "8\nx = 8\n"),
+        // 作为语句的块与 if 语句的分支中的 `rtb` 提前结束外层的作为值的块
+        ("let x = { if 1 == 1 { rtb 5; } rtb 6; }; x;", "5\nx = 5\n"),
+        ("let x = { if 1 == 2 { rtb 5; } rtb 6; }; x;", "6\nx = 6\n"),
+        ("let x = { { rtb 3; } rtb 4; }; x;", "3\nx = 3\n"),
+        (
+            "let a = 1; let x = { if a > 0 { a = 2; if a > 1 { rtb a; } } rtb 0; }; x;",
+            "2\na = 2\nx = 2\n",
+        ),
+        // 不在作为值的块中时，`rtb` 结束最外层的作为语句的块，值被丢弃
+        ("let a = 1; { rtb 2; a = 3; } a;", "1\na = 1\n"),
+        ("let a = 1; { if true { rtb 2; } a = 3; } a;", "1\na = 1\n"),
+    ];
+
+    for (program, expected) in programs {
+        let ast = parse(program).unwrap();
+        assert_eq!(output(&ast.compile().unwrap()), expected, "{}", program);
+
+        let registers = lower(&ast).unwrap().emit_registers(2).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.run_registers(&registers).unwrap();
+        assert_eq!(interpreter.output(), expected, "{}", program);
+    }
+}
+
+#[test]
+fn test_block_errors() {
+    use crate::compiler::CompilerError;
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let ast = parse("let a = 1; rtb a;").unwrap();
+    assert!(matches!(
+        ast.compile(),
+        Err(CompilerError::ReturnOutsideBlock(_))
+    ));
+    assert!(matches!(
+        lower(&ast),
+        Err(CompilerError::ReturnOutsideBlock(_))
+    ));
+
+    // 作为值的块必须以 `rtb` 结束，嵌套的块中的 `rtb` 只会提前结束它
+    for program in ["let a = { 1; };", "let a = { { rtb 1; } };"] {
+        let ast = parse(program).unwrap();
+        assert!(
+            matches!(ast.compile(), Err(CompilerError::BlockWithoutValue(_))),
+            "{}",
+            program
+        );
+        assert!(
+            matches!(lower(&ast), Err(CompilerError::BlockWithoutValue(_))),
+            "{}",
+            program
+        );
+    }
+}
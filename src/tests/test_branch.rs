@@ -0,0 +1,123 @@
+#[allow(dead_code)]
+fn eliminate(
+    code: &str,
+) -> (
+    crate::parser::AstNode,
+    Vec<crate::compiler::CompilerWarning>,
+) {
+    use crate::optimizer::optimize;
+    use crate::parser::parse;
+
+    optimize(&parse(code).unwrap(), 1).unwrap()
+}
+
+#[test]
+fn test_branch_true() {
+    use crate::parser::AstNode;
+
+    let code = std::fs::read_to_string("examples/if.ba").unwrap();
+    let (ast, warnings) = eliminate(&code);
+
+    assert_eq!(warnings.len(), 3);
+    assert_eq!(ast.as_code(), "{let b = 1;\n}\n");
+    assert!(matches!(ast, AstNode::Program(ref nodes) if matches!(nodes[0], AstNode::Block(_))));
+}
+
+#[test]
+fn test_branch_false() {
+    let (ast, warnings) =
+        eliminate("if 1 > 2 { let b = 1; } elif a { let b = 2; } else { let b = 3; }");
+
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(
+        warnings[0].to_string(),
+        "Unreachable branch: `if false` can never run"
+    );
+    assert_eq!(
+        ast.as_code(),
+        "if a \n{let b = 2;\n}\nelse \n{let b = 3;\n}\n\n\n"
+    );
+}
+
+#[test]
+fn test_branch_removed() {
+    let (ast, warnings) = eliminate("let a = 1; if false { let a = 2; }");
+
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(ast.as_code(), "let a = 1;\n");
+}
+
+#[test]
+fn test_branch_elif_true() {
+    let (ast, warnings) = eliminate(
+        "if a { let b = 1; } elif true { let b = 2; } elif b { let b = 3; } else { let b = 4; }",
+    );
+
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(
+        warnings[0].to_string(),
+        "Unreachable branch: `elif b` can never run"
+    );
+    assert_eq!(
+        warnings[1].to_string(),
+        "Unreachable branch: `else` can never run"
+    );
+    assert_eq!(
+        ast.as_code(),
+        "if a \n{let b = 1;\n}\nelse \n{let b = 2;\n}\n\n\n"
+    );
+}
+
+#[test]
+fn test_branch_nested() {
+    let (ast, warnings) = eliminate("if a { if 1 == 1 { let b = 1; } else { let b = 2; } }");
+
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(ast.as_code(), "if a \n{{let b = 1;\n}\n}\n\n");
+}
+
+#[test]
+fn test_branch_compile() {
+    use crate::compiler::OpCode;
+
+    let code = std::fs::read_to_string("examples/if.ba").unwrap();
+    let (ast, _) = eliminate(&code);
+    let codes = ast.compile().unwrap();
+
+    // 只剩下被选中的分支，它作为块被直接生成在当前位置
+    assert!(codes
+        .iter()
+        .all(|code| code.op != OpCode::JumpIf && code.op != OpCode::MakeSection));
+}
+
+#[test]
+fn test_branch_value() {
+    use crate::compiler::OpCode;
+
+    // 表达式中作为值的 if 同样被化简，只剩下一个分支时成为作为值的块
+    let (ast, warnings) = eliminate(
+        "let w = if true { rtb 1; } else { rtb 2; }; w = 1 + if 1 > 2 { rtb 1; } elif w == 1 { rtb 5; } else { rtb 2; };",
+    );
+    assert_eq!(warnings.len(), 2);
+    let codes = ast.compile().unwrap();
+    assert_eq!(
+        codes
+            .iter()
+            .filter(|code| code.op == OpCode::JumpIfElse)
+            .count(),
+        1
+    );
+
+    let (ast, warnings) = eliminate("let w = { rtb if false { rtb 1; } else { rtb 2; }; };");
+    assert_eq!(warnings.len(), 1);
+    assert!(ast
+        .compile()
+        .unwrap()
+        .iter()
+        .all(|code| code.op != OpCode::JumpIfElse));
+
+    // 不合法的作为值的 if 保持原样，由编译报告错误
+    let (ast, warnings) = eliminate("let w = if false { rtb 1; };");
+    assert!(warnings.is_empty());
+    assert_eq!(ast.compile().unwrap_err().code(), "E0117");
+}
@@ -0,0 +1,124 @@
+/// 用系统的 C 编译器编译并链接数学库，运行生成的代码，返回标准输出；运行时出错时返回 `Err`。
+/// 没有可用的 C 编译器时返回 `None`
+#[allow(dead_code)]
+fn run_c(program: &str, name: &str) -> Option<Result<String, String>> {
+    use crate::compiler::c::compile_c;
+    use crate::parser::parse;
+    use std::process::Command;
+
+    let source = compile_c(&parse(program).unwrap()).unwrap();
+    let dir = std::env::temp_dir().join(format!("hare-c-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (file, binary) = (dir.join("main.c"), dir.join("main"));
+    std::fs::write(&file, &source).unwrap();
+
+    let status = Command::new("cc")
+        .arg("-o")
+        .arg(&binary)
+        .arg(&file)
+        .arg("-lm")
+        .status()
+        .ok()?;
+    assert!(
+        status.success(),
+        "failed to compile {}:\n{}",
+        program,
+        source
+    );
+
+    let output = Command::new(&binary).output().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    Some(match output.status.success() {
+        true => Ok(String::from_utf8(output.stdout).unwrap()),
+        false => Err(String::from_utf8(output.stderr).unwrap()),
+    })
+}
+
+/// 用参考解释器执行程序，返回可观察的结果；运行时出错时返回 `Err`
+#[allow(dead_code)]
+fn run_interpreter(program: &str) -> Result<String, String> {
+    use crate::interpreter::Interpreter;
+    use crate::parser::parse;
+
+    let codes = parse(program).unwrap().compile().unwrap();
+    let mut interpreter = Interpreter::new();
+    match interpreter.run(&codes) {
+        Ok(()) => Ok(interpreter.output()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[test]
+fn test_c_matches_interpreter() {
+    use crate::fuzz::Generator;
+    use crate::parser::parse;
+
+    let mut programs: Vec<String> = std::fs::read_dir("examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ba"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+    programs.extend(
+        [
+            "1 + 2 * 3 / 4; 7 % 3; 10 / 4.0; 1.5 % 1; 0.1 + 0.2; 100000000.0 * 100000000.0; 1.0 / 3;",
+            "2 == 2.0; 1 != true; \"a\" < \"b\"; \"ab\" + \"cd\"; 3 >= 3; 2.5 <= 1;",
+            "let a = 2; if a == 1 { let b = 1; } elif a == 2 { let b = 2; 1 + 1; } else { let b = 3; } b;",
+            "let x = 1; { let y = x + 1; { x = y * 10; } } if x > 5 { x = x - 1; } else { x = 0; }",
+            "170141183460469231731687303715884105727; 0.00001; 12345678901234567890.0;",
+            "let a = 1; a / 0;",
+            "if 1 { 2; }",
+            "if \"s\" { } else { 1; }",
+            "if 5 { 1; } elif true { 2; }",
+            "missing + 1;",
+            "let x = { if 1 == 1 { rtb 5; } rtb 6; }; let y = { { rtb 3; } rtb 4; };",
+            "let a = 2; let b = if a > 1 { let c = a * 2; rtb c + 1; } else { rtb 0; }; { if b > 3 { rtb b; } b = 0; }",
+        ]
+        .map(String::from),
+    );
+    programs.extend((0..200).map(|seed| Generator::new(seed).program()));
+
+    // 只比较能被解析并编译的程序，最多比较 50 个
+    let programs = programs
+        .iter()
+        .filter(|program| parse(program).is_ok_and(|ast| ast.compile().is_ok()))
+        .take(50);
+
+    for (index, program) in programs.enumerate() {
+        let Some(actual) = run_c(program, &index.to_string()) else {
+            // 没有 C 编译器时测试失败，除非显式要求跳过
+            assert!(
+                std::env::var_os("HARE_SKIP_C_TESTS").is_some(),
+                "no C compiler (`cc`) available; set HARE_SKIP_C_TESTS=1 to skip this test"
+            );
+            eprintln!("test_c_matches_interpreter skipped: no C compiler (`cc`) available");
+            return;
+        };
+
+        match run_interpreter(program) {
+            Ok(expected) => assert_eq!(actual, Ok(expected), "{}", program),
+            Err(expected) => {
+                let actual = actual.unwrap_err();
+                assert!(
+                    actual.contains(&expected),
+                    "{}: {} / {}",
+                    program,
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_c_block_errors() {
+    use crate::compiler::c::compile_c;
+    use crate::parser::parse;
+
+    for program in ["rtb 1;", "let x = { 1; };", "let x = if true { rtb 1; };"] {
+        let expected = parse(program).unwrap().compile().unwrap_err();
+        let err = compile_c(&parse(program).unwrap()).unwrap_err();
+        assert_eq!(err.to_string(), expected.to_string(), "{}", program);
+    }
+}
@@ -0,0 +1,391 @@
+/// 以 `stdin` 作为标准输入执行命令行，返回退出码、标准输出与标准错误
+#[allow(dead_code)]
+fn hare(args: &[&str], stdin: &str) -> (i32, String, String) {
+    use crate::cli::{run, Io};
+
+    let mut input = stdin.as_bytes();
+    let (mut stdout, mut stderr): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
+    let mut io = Io::new(&mut input, &mut stdout, &mut stderr);
+    let code = run(std::iter::once("hare").chain(args.iter().copied()), &mut io);
+
+    (
+        code,
+        String::from_utf8_lossy(&stdout).into_owned(),
+        String::from_utf8(stderr).unwrap(),
+    )
+}
+
+/// 创建一个空的临时目录
+#[allow(dead_code)]
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("hare-cli-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    use crate::cli::{EXIT_FAILURE, EXIT_IO, EXIT_SUCCESS, EXIT_USAGE};
+
+    assert_eq!(
+        hare(&["check", "-"], "let a = 1;"),
+        (EXIT_SUCCESS, String::new(), String::new())
+    );
+
+    let (code, _, stderr) = hare(&["check", "-"], "let a = ;");
+    assert_eq!(code, EXIT_FAILURE);
+    assert!(stderr.contains("--> <stdin>:1:9"), "{}", stderr);
+
+    let (code, _, stderr) = hare(&["run", "-"], "1 / 0;");
+    assert_eq!(
+        (code, stderr.as_str()),
+        (EXIT_FAILURE, "error: <stdin>: Division by zero: 1 / 0\n")
+    );
+
+    let (code, _, stderr) = hare(&["ast", "missing.ba"], "");
+    assert_eq!(code, EXIT_IO);
+    assert!(stderr.starts_with("error: missing.ba: "), "{}", stderr);
+
+    assert_eq!(hare(&["run"], "").0, EXIT_USAGE);
+    assert_eq!(hare(&["build", "-"], "1;").0, EXIT_USAGE);
+
+    let (code, stdout, _) = hare(&["--help"], "");
+    assert_eq!(code, EXIT_SUCCESS);
+    assert!(stdout.contains("Usage:"));
+
+    // 不带子命令时编译并输出字节码
+    assert_eq!(
+        hare(&["-c", "1 + 2;"], "").1,
+        "Push [\"1\"]\nPush [\"2\"]\nAdd []\n"
+    );
+    assert_eq!(hare(&["-c", "1 +"], "").0, EXIT_FAILURE);
+}
+
+#[test]
+fn test_cli_multiple_files() {
+    use crate::cli::EXIT_IO;
+
+    let dir = temp_dir("multiple");
+    let first = dir.join("first.ba");
+    std::fs::write(&first, "let a = 1; a + 1;").unwrap();
+    let first = first.to_str().unwrap();
+
+    // 缺失的文件不会阻止之后的文件被执行，退出码取最大的一个
+    let (code, stdout, stderr) = hare(&["run", first, "missing.ba", "-"], "2 * 3;");
+    assert_eq!(code, EXIT_IO);
+    assert_eq!(
+        stdout,
+        format!(
+            "==> {} <==\n2\na = 1\n\n==> missing.ba <==\n\n==> - <==\n6\n",
+            first
+        )
+    );
+    assert!(stderr.starts_with("error: missing.ba: "));
+
+    // `check` 只为有错误或警告的文件输出文件名
+    let (code, stdout, stderr) = hare(&["check", first, "missing.ba", "-"], "if false { }");
+    assert_eq!(code, EXIT_IO);
+    assert_eq!(stdout, "==> missing.ba <==\n\n==> - <==\n");
+    assert!(stderr.starts_with("error: missing.ba: "));
+    assert!(stderr.contains("Unreachable branch"), "{}", stderr);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_build_and_run() {
+    use crate::cli::EXIT_SUCCESS;
+
+    let dir = temp_dir("build");
+    let source = dir.join("main.ba");
+    std::fs::write(&source, "let a = 2;\nif a > 1 { a = a * 10; }\na;\n").unwrap();
+
+    let (code, _, stderr) = hare(&["build", source.to_str().unwrap()], "");
+    assert_eq!((code, stderr.as_str()), (EXIT_SUCCESS, ""));
+
+    let object = dir.join("main.hbc");
+    let object = object.to_str().unwrap();
+    assert_eq!(
+        hare(&["run", object], ""),
+        (EXIT_SUCCESS, "20\na = 20\n".to_string(), String::new())
+    );
+
+    let (code, stdout, _) = hare(&["disasm", "--source-lines", object], "");
+    assert_eq!(code, EXIT_SUCCESS);
+    assert!(
+        stdout.contains("; 2 | if a > 1 { a = a * 10; }"),
+        "{}",
+        stdout
+    );
+
+    // 从标准输入编译，写入指定的文件
+    let output = dir.join("stdin.hbc");
+    let output = output.to_str().unwrap();
+    assert_eq!(
+        hare(&["build", "-", "-o", output], "1 + 1;").0,
+        EXIT_SUCCESS
+    );
+    assert_eq!(hare(&["run", output], "").1, "2\n");
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_run_register() {
+    use crate::cli::{EXIT_FAILURE, EXIT_SUCCESS, EXIT_USAGE};
+
+    // 两种目标的执行结果相同
+    let code = "let a = 2; let b = if a > 1 { rtb a * 10; } else { rtb 0; }; a + b;";
+    let expected = (
+        EXIT_SUCCESS,
+        "22\na = 2\nb = 20\n".to_string(),
+        String::new(),
+    );
+    assert_eq!(hare(&["run", "-"], code), expected);
+    assert_eq!(hare(&["run", "--target", "register", "-"], code), expected);
+    assert_eq!(
+        hare(
+            &["run", "--target", "register", "--registers", "2", "-"],
+            code
+        ),
+        expected
+    );
+
+    let (status, _, stderr) = hare(&["run", "--target", "register", "-"], "1 / 0;");
+    assert_eq!(status, EXIT_FAILURE);
+    assert!(stderr.contains("Division by zero"), "{}", stderr);
+
+    let dir = temp_dir("register");
+    let object = dir.join("main.hbc");
+    let object = object.to_str().unwrap();
+    assert_eq!(hare(&["build", "-", "-o", object], code).0, EXIT_SUCCESS);
+    assert_eq!(
+        hare(&["run", "--target", "register", object], "").0,
+        EXIT_USAGE
+    );
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_fmt() {
+    use crate::cli::{EXIT_FAILURE, EXIT_SUCCESS};
+
+    assert_eq!(
+        hare(&["fmt", "-"], "let   a=1;{a;}"),
+        (
+            EXIT_SUCCESS,
+            "let a = 1;\n{\n    a;\n}\n".to_string(),
+            String::new()
+        )
+    );
+
+    let dir = temp_dir("fmt");
+    let file = dir.join("main.ba");
+    std::fs::write(&file, "let   a=1;").unwrap();
+    let path = file.to_str().unwrap();
+
+    let (code, _, stderr) = hare(&["fmt", "--check", path], "");
+    assert_eq!(code, EXIT_FAILURE);
+    assert_eq!(stderr, format!("error: {}: not formatted\n", path));
+
+    assert_eq!(hare(&["fmt", path], "").0, EXIT_SUCCESS);
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "let a = 1;\n");
+    assert_eq!(hare(&["fmt", "--check", path], "").0, EXIT_SUCCESS);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_cli_json_diagnostics() {
+    use crate::cli::{EXIT_FAILURE, EXIT_IO};
+    use serde_json::{json, Value};
+
+    let parse = |stderr: &str| -> Vec<Value> {
+        stderr
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+
+    let (code, _, stderr) = hare(
+        &["check", "--error-format=json", "-"],
+        "let a = (1 + 2;\n{ a;",
+    );
+    assert_eq!(code, EXIT_FAILURE);
+    let diagnostics = parse(&stderr);
+    assert_eq!(diagnostics.len(), 2);
+
+    assert_eq!(diagnostics[0]["code"], "E0009");
+    assert_eq!(diagnostics[0]["message"], "missing ')'");
+    assert_eq!(
+        diagnostics[0]["suggestions"][0]["replacements"][0],
+        json!({
+            "span": {
+                "file": "<stdin>",
+                "byte_start": 14,
+                "byte_end": 14,
+                "line_start": 1,
+                "column_start": 15,
+                "line_end": 1,
+                "column_end": 15,
+            },
+            "text": ")",
+        })
+    );
+
+    // 未闭合的块：主要位置是 `{`，次要位置是输入的末尾
+    let spans = diagnostics[1]["spans"].as_array().unwrap();
+    assert_eq!(diagnostics[1]["code"], "E0010");
+    assert_eq!(
+        (
+            spans[0]["line_start"].clone(),
+            spans[0]["is_primary"].clone()
+        ),
+        (json!(2), json!(true))
+    );
+    assert_eq!(
+        (
+            spans[1]["byte_start"].clone(),
+            spans[1]["is_primary"].clone()
+        ),
+        (json!(20), json!(false))
+    );
+
+    // 编译期的警告标记在不可达的分支上，并以所在的 `if` 作为次要位置
+    let (code, _, stderr) = hare(
+        &["check", "--error-format=json", "-"],
+        "if true { 1; } else { 2; }\nlet a = 1 / 0;",
+    );
+    assert_eq!(code, EXIT_FAILURE);
+    let diagnostics = parse(&stderr);
+    assert_eq!(diagnostics[0]["code"], "W0001");
+    assert_eq!(diagnostics[0]["severity"], "warning");
+    assert_eq!(diagnostics[0]["notes"].as_array().unwrap().len(), 1);
+    let spans = diagnostics[0]["spans"].as_array().unwrap();
+    assert_eq!(spans.len(), 2);
+    assert_eq!(
+        (
+            spans[0]["byte_start"].clone(),
+            spans[0]["is_primary"].clone()
+        ),
+        (json!(15), json!(true))
+    );
+    assert_eq!(
+        (
+            spans[1]["byte_start"].clone(),
+            spans[1]["byte_end"].clone(),
+            spans[1]["is_primary"].clone(),
+            spans[1]["label"].clone()
+        ),
+        (json!(0), json!(26), json!(false), json!("in this `if`"))
+    );
+    // 编译期的错误标记在出错的子表达式上
+    assert_eq!(diagnostics[1]["code"], "E0101");
+    assert_eq!(
+        (
+            diagnostics[1]["spans"][0]["byte_start"].clone(),
+            diagnostics[1]["spans"][0]["byte_end"].clone()
+        ),
+        (json!(35), json!(40))
+    );
+
+    // 没有源代码位置的错误只有文件名
+    let (code, _, stderr) = hare(&["run", "missing.ba", "--error-format", "json"], "");
+    assert_eq!(code, EXIT_IO);
+    let diagnostics = parse(&stderr);
+    assert_eq!(diagnostics[0]["code"], Value::Null);
+    assert_eq!(diagnostics[0]["spans"][0]["file"], "missing.ba");
+    assert_eq!(diagnostics[0]["spans"][0]["line_start"], Value::Null);
+}
+
+#[test]
+fn test_cli_ast() {
+    use crate::cli::EXIT_SUCCESS;
+    use crate::parser::serialize::from_json_str;
+
+    let (code, stdout, _) = hare(&["ast", "--format=sexpr", "-"], "a = 1;");
+    assert_eq!(
+        (code, stdout.as_str()),
+        (
+            EXIT_SUCCESS,
+            "(program @0..6 (set_value @0..5 (identifier @0..1 \"a\") (constant @4..5 int 1)))\n"
+        )
+    );
+
+    let (code, stdout, _) = hare(&["ast", "--format", "json", "-"], "let a = 1;");
+    assert_eq!(code, EXIT_SUCCESS);
+    assert_eq!(from_json_str(&stdout).unwrap().as_code(), "let a = 1;\n");
+
+    // `--emit dot` 输出优化之前的语法树或编译后的控制流图
+    let (_, stdout, _) = hare(&["--emit=dot", "--graph=ast", "-O1", "-c", "1 + 2;"], "");
+    assert!(
+        stdout.contains("n1 [label=\"+\", shape=circle];"),
+        "{}",
+        stdout
+    );
+    let (_, stdout, _) = hare(&["--emit=dot", "-c", "1 + 2;"], "");
+    assert!(stdout.starts_with("digraph cfg {"), "{}", stdout);
+}
+
+#[test]
+fn test_cli_explain() {
+    use crate::cli::{EXIT_SUCCESS, EXIT_USAGE};
+
+    let (code, stdout, _) = hare(&["explain", "E0010"], "");
+    assert_eq!(code, EXIT_SUCCESS);
+    assert!(stdout.starts_with("# E0010: unclosed block\n"));
+
+    let (code, _, stderr) = hare(&["explain", "E9999"], "");
+    assert_eq!(
+        (code, stderr.as_str()),
+        (EXIT_USAGE, "error: unknown error code `E9999`\n")
+    );
+}
+
+#[test]
+fn test_cli_long_chain() {
+    use crate::cli::EXIT_SUCCESS;
+    use crate::parser::{MAX_EXPRESSION_DEPTH, MAX_NESTING_DEPTH};
+
+    // 左结合的运算符链没有括号，它的长度不受最大嵌套深度的限制
+    let terms = MAX_EXPRESSION_DEPTH - 8;
+    assert!(terms > MAX_NESTING_DEPTH);
+    let chain = format!("let a = 1{};", " + 1".repeat(terms - 1));
+    let elifs = format!(
+        "let a = 1; if a == 0 {{ a = 0; }}{} else {{ a = 2; }}",
+        " elif a == 1 { a = 1; }".repeat(terms)
+    );
+
+    // 接近最大深度的语法树在与主线程相同大小（8 MiB）的栈上编译时不会栈溢出
+    let thread = std::thread::Builder::new().stack_size(8 << 20);
+    thread
+        .spawn(move || {
+            let code = format!("{} a;", chain);
+            assert_eq!(
+                hare(&["check", "-"], &code),
+                (EXIT_SUCCESS, String::new(), String::new())
+            );
+            assert_eq!(
+                hare(&["run", "-"], &code),
+                (
+                    EXIT_SUCCESS,
+                    format!("{}\na = {}\n", terms, terms),
+                    String::new()
+                )
+            );
+            assert_eq!(hare(&["-c", &code], "").0, EXIT_SUCCESS);
+            assert_eq!(hare(&["run", "-"], &elifs).0, EXIT_SUCCESS);
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+
+    let (code, _, stderr) = hare(
+        &["check", "-"],
+        &format!("1{};", " + 1".repeat(MAX_EXPRESSION_DEPTH)),
+    );
+    assert_ne!(code, EXIT_SUCCESS);
+    assert!(stderr.contains("nested too deeply"), "{}", stderr);
+}
@@ -0,0 +1,122 @@
+#[test]
+fn test_disasm() {
+    use crate::compiler::disasm::disassemble;
+    use crate::parser::parse;
+
+    let codes = parse("let a = 1.5;\nif a > 1 {\n    a = \"s\";\n}")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let expected = "\
+; 10 instructions, 1 section
+0000:  Push 1.5                          ; float
+0001:  StoreName a
+0002:  .section @00000000                ; 0003..0005
+0003:      Push \"s\"                      ; str
+0004:      StoreName a
+0005:  .end                              ; @00000000
+0006:  LoadName a
+0007:  Push 1                            ; int
+0008:  Gt
+0009:  JumpIf @00000000                  ; -> 0002
+";
+
+    assert_eq!(disassemble(&codes), expected);
+}
+
+#[test]
+fn test_disasm_malformed() {
+    use crate::compiler::disasm::disassemble;
+    use crate::compiler::{ByteCode, OpCode};
+
+    let codes = vec![
+        ByteCode::from_args(OpCode::Push, &["1", "\"x\""]).unwrap(),
+        ByteCode::from_args(OpCode::Jump, &["missing"]).unwrap(),
+        ByteCode::from_args(OpCode::LoadName, &["a b"]).unwrap(),
+        ByteCode::from_args(OpCode::EndMakeSection, &[]).unwrap(),
+        ByteCode::from_args(OpCode::MakeSection, &["s"]).unwrap(),
+    ];
+    let expected = "\
+; 5 instructions, 0 sections
+0000:  Push 1 \"x\"                        ; int, str
+0001:  Jump @missing                     ; -> undefined section
+0002:  LoadName \"a b\"
+0003:  .end                              ; unmatched end of section
+0004:  .section @s                       ; unterminated section
+";
+
+    assert_eq!(disassemble(&codes), expected);
+}
+
+#[test]
+fn test_disasm_source_lines() {
+    use crate::compiler::debug::compile_items;
+    use crate::compiler::disasm::disassemble_with_source;
+    use crate::compiler::section::SectionNamer;
+    use crate::parser::parse_items;
+
+    let code = "let a = 1; # one\n\n{\n    a = 2\n}\n";
+    let (items, _) = parse_items(code);
+    let (codes, _, debug) = compile_items(code, &items, 0, &mut SectionNamer::new()).unwrap();
+    let expected = "\
+; 4 instructions, 0 sections
+       ; 1 | let a = 1; # one
+0000:  Push 1                            ; int
+0001:  StoreName a
+       ; 3 | {
+       ; 4 |     a = 2
+       ; 5 | }
+0002:  Push 2                            ; int
+0003:  StoreName a
+";
+
+    assert_eq!(disassemble_with_source(&codes, &debug), expected);
+}
+
+#[test]
+fn test_disasm_object() {
+    use crate::compiler::debug::compile_items;
+    use crate::compiler::disasm::{disassemble, disassemble_object, disassemble_with_source};
+    use crate::compiler::object::{Object, ObjectError};
+    use crate::compiler::section::SectionNamer;
+    use crate::compiler::{ByteCode, OpCode};
+    use crate::parser::parse_items;
+
+    let code = "let a = 1;\nif a == 1 {\n    a = \"中\"\n} else {\n    a = 2.5\n}\n";
+    let (items, _) = parse_items(code);
+    let (codes, _, debug) = compile_items(code, &items, 2, &mut SectionNamer::new()).unwrap();
+
+    let object = Object::new(codes.clone()).with_debug(debug.clone());
+    let bytes = object.to_bytes();
+    let loaded = Object::from_bytes(&bytes).unwrap();
+    assert_eq!(loaded, object);
+    assert_eq!(loaded.sections.len(), 2);
+    assert_eq!(
+        disassemble_object(&loaded, true),
+        disassemble_with_source(&codes, &debug)
+    );
+    assert_eq!(disassemble_object(&loaded, false), disassemble(&codes));
+
+    let stripped = Object::new(codes.clone());
+    assert_eq!(Object::from_bytes(&stripped.to_bytes()).unwrap(), stripped);
+
+    assert_eq!(Object::from_bytes(b"HAR"), Err(ObjectError::BadMagic));
+    assert_eq!(
+        Object::from_bytes(&bytes[..bytes.len() - 1]),
+        Err(ObjectError::UnexpectedEnd(bytes.len() - 4))
+    );
+    // 没有常量与名字时，第一条指令的操作码位于文件头与三个计数之后
+    let mut corrupted =
+        Object::new(vec![ByteCode::from_args(OpCode::Pop, &[]).unwrap()]).to_bytes();
+    corrupted[23] = 200;
+    assert_eq!(
+        Object::from_bytes(&corrupted),
+        Err(ObjectError::InvalidOpCode(200, 23))
+    );
+    let mut versioned = bytes.clone();
+    versioned[4] = 9;
+    assert_eq!(
+        Object::from_bytes(&versioned),
+        Err(ObjectError::UnsupportedVersion(9))
+    );
+}
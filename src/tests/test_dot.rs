@@ -0,0 +1,111 @@
+#[test]
+fn test_dot_ast() {
+    use crate::compiler::dot::ast_to_dot;
+    use crate::parser::parse;
+
+    // 乘法比加法结合得更紧，`*` 是 `+` 的右子节点
+    let ast = parse("a = 1 + 2 * \"x\";").unwrap();
+    let expected = "\
+digraph ast {
+    ordering=out;
+    node [fontname=\"monospace\"];
+    n0 [label=\"Program\"];
+    n1 [label=\"=\"];
+    n2 [label=\"a\", shape=box];
+    n1 -> n2;
+    n3 [label=\"+\", shape=circle];
+    n4 [label=\"1\", shape=box];
+    n3 -> n4;
+    n5 [label=\"*\", shape=circle];
+    n6 [label=\"2\", shape=box];
+    n5 -> n6;
+    n7 [label=\"\\\"x\\\"\", shape=box];
+    n5 -> n7;
+    n3 -> n5;
+    n1 -> n3;
+    n0 -> n1;
+}
+";
+
+    assert_eq!(ast_to_dot(&ast), expected);
+}
+
+#[test]
+fn test_dot_cfg() {
+    use crate::compiler::dot::cfg_to_dot;
+    use crate::parser::parse;
+
+    let codes = parse("let a = 1;\nif a > 1 { a = 2; } else { a = 3; }\na;")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let expected = "\
+digraph cfg {
+    node [shape=box, fontname=\"monospace\"];
+    start [shape=oval];
+    end [shape=oval];
+    start -> b0;
+    b0 [label=\"0000: Push 1\\l0001: StoreName a\\l0010: LoadName a\\l0011: Push 1\\l0012: Gt\\l0013: Dup\\l0014: Neg\\l0015: JumpIf @00000001\\l\"];
+    b16 [label=\"0016: JumpIf @00000000\\l\"];
+    b17 [label=\"0017: LoadName a\\l\"];
+    subgraph \"cluster_00000000\" {
+        label=\"@00000000\";
+        exit2 [shape=point];
+        b3 [label=\"0003: Push 2\\l0004: StoreName a\\l\"];
+    }
+    subgraph \"cluster_00000001\" {
+        label=\"@00000001\";
+        exit6 [shape=point];
+        b7 [label=\"0007: Push 3\\l0008: StoreName a\\l\"];
+    }
+    b0 -> b7 [label=\"true\"];
+    b0 -> b16 [label=\"false\"];
+    exit6 -> b16 [label=\"return\", style=dashed];
+    b16 -> b3 [label=\"true\"];
+    b16 -> b17 [label=\"false\"];
+    exit2 -> b17 [label=\"return\", style=dashed];
+    b17 -> end;
+    b3 -> exit2;
+    b7 -> exit6;
+}
+";
+
+    assert_eq!(cfg_to_dot(&codes), expected);
+}
+
+#[test]
+fn test_dot_cfg_return() {
+    use crate::compiler::dot::cfg_to_dot;
+    use crate::compiler::{ByteCode, OpCode};
+
+    // `Return` 直接到达所在 Section 的出口，空的 Section 的入口即为出口
+    let codes = vec![
+        ByteCode::from_args(OpCode::MakeSection, &["s"]).unwrap(),
+        ByteCode::from_args(OpCode::Return, &[]).unwrap(),
+        ByteCode::from_args(OpCode::Push, &["1"]).unwrap(),
+        ByteCode::from_args(OpCode::EndMakeSection, &[]).unwrap(),
+        ByteCode::from_args(OpCode::MakeSection, &["e"]).unwrap(),
+        ByteCode::from_args(OpCode::EndMakeSection, &[]).unwrap(),
+        ByteCode::from_args(OpCode::Jump, &["s"]).unwrap(),
+        ByteCode::from_args(OpCode::Jump, &["e"]).unwrap(),
+    ];
+    let dot = cfg_to_dot(&codes);
+
+    assert!(
+        dot.contains("    b1 -> exit0 [label=\"return\"];\n"),
+        "{}",
+        dot
+    );
+    assert!(dot.contains("    b2 -> exit0;\n"), "{}", dot);
+    assert!(dot.contains("    b6 -> b1 [label=\"jump\"];\n"), "{}", dot);
+    assert!(
+        dot.contains("    b7 -> exit4 [label=\"jump\"];\n"),
+        "{}",
+        dot
+    );
+    assert!(
+        dot.contains("    exit4 -> end [label=\"return\", style=dashed];\n"),
+        "{}",
+        dot
+    );
+}
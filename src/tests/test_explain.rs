@@ -0,0 +1,130 @@
+/// 返回解释中以 `kind` 标记的代码块
+#[allow(dead_code)]
+fn examples<'a>(text: &'a str, kind: &str) -> Vec<&'a str> {
+    let fence = format!("```{}\n", kind);
+    text.split(fence.as_str())
+        .skip(1)
+        .map(|rest| &rest[..rest.find("```").unwrap()])
+        .collect()
+}
+
+/// 返回代码产生的所有错误与警告的代码，包括各个后端报告的错误
+#[allow(dead_code)]
+fn codes(program: &str) -> Vec<&'static str> {
+    use crate::analysis::analyze;
+    use crate::compiler::c::compile_c;
+    use crate::compiler::wat::compile_wat;
+    use crate::parser::parse;
+
+    let mut codes: Vec<&'static str> = analyze(program)
+        .diagnostics
+        .iter()
+        .filter_map(|diagnostic| diagnostic.code)
+        .collect();
+    if let Ok(ast) = parse(program) {
+        codes.extend(compile_wat(&ast).err().map(|err| err.code()));
+        codes.extend(compile_c(&ast).err().map(|err| err.code()));
+    }
+    codes
+}
+
+#[test]
+fn test_explain_every_code() {
+    use crate::analysis::Type;
+    use crate::compiler::operand::OperandError;
+    use crate::compiler::{CompilerError, CompilerWarning, OpCode};
+    use crate::explain::{explain, EXPLANATIONS};
+    use crate::parser::errors::ParserError;
+    use crate::parser::{BlueArchParser, Rule};
+    use pest::Parser;
+
+    let pest_error = BlueArchParser::parse(Rule::program, "@").unwrap_err();
+    let parser_errors = vec![
+        ParserError::Unexpected {
+            expected: vec![],
+            found: "'@'".to_string(),
+        },
+        ParserError::ExpectedExpression {
+            after: None,
+            found: "';'".to_string(),
+        },
+        ParserError::ExpectedIdentifier("'1'".to_string()),
+        ParserError::MissingVariableName,
+        ParserError::MissingTypeName,
+        ParserError::MissingInitializer,
+        ParserError::MissingBlock("if"),
+        ParserError::DanglingBranch("else"),
+        ParserError::UnclosedParen,
+        ParserError::UnclosedBlock,
+        ParserError::LiteralOutOfRange("Integer", "1".to_string()),
+        ParserError::ExpressionTooDeep,
+        ParserError::BracketsTooDeep,
+        ParserError::Custom(String::new()),
+        ParserError::UnknownOperator(Rule::add),
+        ParserError::UnknownPair(Rule::EOI),
+        ParserError::PestError(pest_error),
+    ];
+    let compiler_errors = vec![
+        CompilerError::InvalidConstant(String::new()),
+        CompilerError::DivisionByZero(String::new()),
+        CompilerError::InvalidOperand(OperandError::WrongArity(OpCode::Pop, String::new(), 1)),
+        CompilerError::Unsupported("C", String::new()),
+        CompilerError::UndefinedVariable(String::new()),
+        CompilerError::SyntaxErrors(String::new()),
+        CompilerError::ExpectedIdentifier(String::new()),
+        CompilerError::ExpectedBlock(String::new()),
+        CompilerError::ExpectedExpression(String::new()),
+        CompilerError::UnexpectedElif(String::new()),
+        CompilerError::UnexpectedElse(String::new()),
+        CompilerError::MissingOperand,
+        CompilerError::TempsNotOnStack(String::new()),
+        CompilerError::UndefinedTemp(String::new()),
+        CompilerError::TooFewRegisters(2, 1),
+        CompilerError::ReturnOutsideBlock(String::new()),
+        CompilerError::BlockWithoutValue(String::new()),
+        CompilerError::IfWithoutElse(String::new()),
+        CompilerError::IncompatibleBranches(Type::Int, Type::Str, String::new()),
+    ];
+
+    let mut codes: Vec<&str> = parser_errors.iter().map(ParserError::code).collect();
+    codes.extend(compiler_errors.iter().map(CompilerError::code));
+    codes.push(CompilerWarning::UnreachableBranch(String::new()).code());
+
+    // 每个代码都有解释，且解释中没有多余的代码
+    let registered: Vec<&str> = EXPLANATIONS.iter().map(|(code, _)| *code).collect();
+    assert_eq!(codes, registered);
+
+    for (code, text) in EXPLANATIONS {
+        assert!(text.starts_with(&format!("# {}: ", code)), "{}", code);
+    }
+    assert_eq!(explain("e0002"), explain("E0002"));
+    assert_eq!(explain("E9999"), None);
+}
+
+#[test]
+fn test_explain_examples() {
+    use crate::analysis::analyze;
+    use crate::explain::EXPLANATIONS;
+
+    let mut checked = 0;
+    for (code, text) in EXPLANATIONS {
+        for example in examples(text, "error")
+            .into_iter()
+            .chain(examples(text, "warning"))
+        {
+            assert!(codes(example).contains(code), "{}: {}", code, example);
+            checked += 1;
+        }
+        for example in examples(text, "ok") {
+            assert_eq!(
+                analyze(example).diagnostics,
+                vec![],
+                "{}: {}",
+                code,
+                example
+            );
+        }
+    }
+
+    assert_eq!(checked, 18);
+}
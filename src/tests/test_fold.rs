@@ -0,0 +1,83 @@
+#[allow(dead_code)]
+fn fold(code: &str) -> Result<crate::parser::AstNode, crate::compiler::CompilerError> {
+    use crate::optimizer::fold_constants;
+    use crate::parser::parse;
+
+    fold_constants(&parse(code).unwrap())
+}
+
+#[test]
+fn test_fold_int() {
+    use crate::compiler::{ByteCode, OpCode};
+
+    let ast = fold("1 + 2 * 3 / 4;").unwrap();
+    assert_eq!(ast.as_code(), "2\n");
+
+    let codes = ast.compile().unwrap();
+    assert_eq!(
+        codes,
+        vec![ByteCode::from_args(OpCode::Push, &["2"]).unwrap()]
+    );
+}
+
+#[test]
+fn test_fold_int_semantics() {
+    assert_eq!(fold("7 / 2;").unwrap().as_code(), "3\n");
+    assert_eq!(fold("0 - 7 / 2;").unwrap().as_code(), "-3\n");
+    assert_eq!(fold("(0 - 7) % 3;").unwrap().as_code(), "-1\n");
+}
+
+#[test]
+fn test_fold_float() {
+    assert_eq!(fold("1.5 + 0.5;").unwrap().as_code(), "2.0\n");
+    assert_eq!(fold("1 / 4.0;").unwrap().as_code(), "0.25\n");
+    assert_eq!(fold("2.5 > 2;").unwrap().as_code(), "true\n");
+}
+
+#[test]
+fn test_fold_bool_and_string() {
+    assert_eq!(fold("true == false;").unwrap().as_code(), "false\n");
+    assert_eq!(fold("\"ab\" + \"cd\";").unwrap().as_code(), "\"abcd\"\n");
+    assert_eq!(fold("\"ab\" < \"b\";").unwrap().as_code(), "true\n");
+    assert_eq!(fold("1 == true;").unwrap().as_code(), "false\n");
+}
+
+#[test]
+fn test_fold_keeps_non_constant() {
+    assert_eq!(fold("a + 2 * 3;").unwrap().as_code(), "(a + 6)\n");
+    assert_eq!(
+        fold("let a = b * (1 + 1);").unwrap().as_code(),
+        "let a = (b * 2);\n"
+    );
+    // 类型错误留给运行期处理
+    assert_eq!(fold("true + false;").unwrap().as_code(), "(true + false)\n");
+}
+
+#[test]
+fn test_fold_division_by_zero() {
+    use crate::compiler::CompilerError;
+
+    assert!(matches!(
+        fold("1 / (2 - 2);"),
+        Err(CompilerError::DivisionByZero(_))
+    ));
+    assert!(matches!(
+        fold("let a = 1 % 0;"),
+        Err(CompilerError::DivisionByZero(_))
+    ));
+    assert!(matches!(
+        fold("1.5 / 0.0;"),
+        Err(CompilerError::DivisionByZero(_))
+    ));
+}
+
+#[test]
+fn test_optimize_level() {
+    use crate::optimizer::optimize;
+    use crate::parser::parse;
+
+    let ast = parse("1 + 2;").unwrap();
+    assert_eq!(optimize(&ast, 0).unwrap().0.as_code(), "(1 + 2)\n");
+    assert_eq!(optimize(&ast, 1).unwrap().0.as_code(), "3\n");
+    assert!(optimize(&parse("1 / 0;").unwrap(), 0).is_ok());
+}
@@ -0,0 +1,49 @@
+#[test]
+fn test_format() {
+    use crate::format::format;
+
+    let code = "let a:int=1 # one\nlet b = (a+2)*3;if a>b{rtb a}elif a==b {\nb=a;c=1\n\n\n\nd=( 2 )} else {}\n\n\n# end";
+    let expected = "let a: int = 1 # one\nlet b = (a + 2) * 3;\nif a > b {\n    rtb a\n} elif a == b {\n    b = a;\n    c = 1\n\n    d = (2)\n} else {\n}\n\n# end\n";
+
+    assert_eq!(format(code).unwrap(), expected);
+    assert_eq!(format(expected).unwrap(), expected);
+}
+
+#[test]
+fn test_format_keeps_statement_boundaries() {
+    use crate::format::format;
+
+    // 关键字之后的换行不是语句的结束，没有 `;` 的语句之间的换行需要保留
+    let code = "let \na = 1\na\n(b)\nrtb \n1 +\n2";
+    assert_eq!(format(code).unwrap(), "let a = 1\na\n(b)\nrtb 1 + 2\n");
+}
+
+#[test]
+fn test_format_syntax_error() {
+    use crate::format::format;
+
+    assert!(format("let a = ;").is_err());
+    assert_eq!(format("").unwrap(), "");
+}
+
+#[test]
+fn test_format_generated_programs() {
+    use crate::format::format;
+    use crate::fuzz::Generator;
+    use crate::parser::parse;
+
+    // 格式化不改变程序的含义，且格式化的结果不再变化
+    for seed in 0..300 {
+        let program = Generator::new(seed).program();
+        let formatted = format(&program).unwrap();
+
+        assert_eq!(
+            format!("{:?}", parse(&formatted).unwrap()),
+            format!("{:?}", parse(&program).unwrap()),
+            "seed {}: {:?}",
+            seed,
+            formatted
+        );
+        assert_eq!(format(&formatted).unwrap(), formatted, "seed {}", seed);
+    }
+}
@@ -0,0 +1,107 @@
+#[test]
+fn test_fuzz_generated_programs_parse() {
+    use crate::fuzz::{parse_and_compile, Generator};
+    use crate::parser::parse;
+
+    for seed in 0..500 {
+        let program = Generator::new(seed).program();
+
+        assert!(parse(&program).is_ok(), "seed {}: {:?}", seed, program);
+        let _ = parse_and_compile(&program);
+    }
+}
+
+#[test]
+fn test_fuzz_generator_deterministic() {
+    use crate::fuzz::Generator;
+
+    assert_eq!(Generator::new(7).program(), Generator::new(7).program());
+    assert_eq!(
+        Generator::from_bytes(b"hare").program(),
+        Generator::from_bytes(b"hare").program()
+    );
+}
+
+#[test]
+fn test_fuzz_mutated_bytes() {
+    use crate::fuzz::{fuzz_bytes, Generator};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    let mut rng = StdRng::seed_from_u64(0);
+
+    for seed in 0..300 {
+        let mut data = Generator::new(seed).program().into_bytes();
+
+        for _ in 0..rng.gen_range(1..4) {
+            if data.is_empty() {
+                break;
+            }
+            let index = rng.gen_range(0..data.len());
+            match rng.gen_range(0..3) {
+                0 => data[index] = rng.gen(),
+                1 => data.truncate(index),
+                _ => {
+                    let byte = data[index];
+                    data.insert(index, byte);
+                }
+            }
+        }
+
+        let _ = fuzz_bytes(&data);
+    }
+}
+
+#[test]
+fn test_fuzz_regressions() {
+    use crate::fuzz::{parse_and_compile, FuzzError};
+    use crate::parser::{MAX_EXPRESSION_DEPTH, MAX_NESTING_DEPTH};
+
+    let deep_parens = format!(
+        "{}1{};",
+        "(".repeat(MAX_NESTING_DEPTH + 1),
+        ")".repeat(MAX_NESTING_DEPTH + 1)
+    );
+    let deep_ifs = format!(
+        "{}{}",
+        "if true {".repeat(MAX_NESTING_DEPTH + 1),
+        "}".repeat(MAX_NESTING_DEPTH + 1)
+    );
+    // 没有括号的长运算符链与 elif 链同样会使编译时的递归过深
+    let long_chain = format!("let a = 1{};", " + 1".repeat(MAX_EXPRESSION_DEPTH * 2));
+    let long_elifs = format!(
+        "let a = 1; if a == 0 {{ a = 0; }}{} else {{ a = 2; }}",
+        " elif a == 1 { a = 1; }".repeat(MAX_EXPRESSION_DEPTH * 10)
+    );
+
+    for input in [
+        "let a = 170141183460469231731687303715884105728;",
+        "1 + 999999999999999999999999999999999999999999;",
+        "let a = ١٢;",
+        "let a = 1.٣;",
+        deep_parens.as_str(),
+        deep_ifs.as_str(),
+        long_chain.as_str(),
+        long_elifs.as_str(),
+    ] {
+        assert!(
+            matches!(parse_and_compile(input), Err(FuzzError::ParserError(_))),
+            "{:?}",
+            input
+        );
+    }
+
+    // 恰好达到最大嵌套深度的输入可以被正常编译
+    let nested = format!(
+        "{}{}1{};{}",
+        "if true {".repeat(MAX_NESTING_DEPTH / 2),
+        "(".repeat(MAX_NESTING_DEPTH / 2),
+        ")".repeat(MAX_NESTING_DEPTH / 2),
+        "}".repeat(MAX_NESTING_DEPTH / 2)
+    );
+    assert!(parse_and_compile(&nested).is_ok());
+    assert!(matches!(
+        parse_and_compile("1 / 0;"),
+        Err(FuzzError::CompilerError(_))
+    ));
+}
@@ -0,0 +1,175 @@
+#[test]
+fn test_if_value() {
+    use super::output;
+    use crate::compiler::verify::verify;
+    use crate::interpreter::Interpreter;
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let programs = [
+        (
+            "let c = true; let y = if c { rtb 1 } else { rtb 2 }; y;",
+            "1\nc = true\ny = 1\n",
+        ),
+        (
+            "let a = 2; let y = if a > 3 { rtb 1; } elif a > 1 { rtb 2; } else { rtb 3; }; y;",
+            "2\na = 2\ny = 2\n",
+        ),
+        (
+            "let a = 0; (if a == 0 { a = 5; rtb a; } else { rtb 0; }) * 2;",
+            "10\na = 5\n",
+        ),
+        // 分支中的 if 语句的 `rtb` 提前结束该分支
+        (
+            "let y = if true { if true { rtb 7; } rtb 8; } else { rtb 9; }; y;",
+            "7\ny = 7\n",
+        ),
+        (
+            "let y = if false { rtb 7; } else { if false { rtb 8; } rtb 9; }; y;",
+            "9\ny = 9\n",
+        ),
+    ];
+
+    for (program, expected) in programs {
+        let ast = parse(program).unwrap();
+        let codes = ast.compile().unwrap();
+        verify(&codes).unwrap();
+        assert_eq!(output(&codes), expected, "{}", program);
+
+        // 寄存器目标不经过堆中的临时变量，输出与栈式字节码相同
+        let registers = lower(&ast).unwrap().emit_registers(2).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.run_registers(&registers).unwrap();
+        assert_eq!(interpreter.output(), expected, "{}", program);
+    }
+}
+
+#[test]
+fn test_if_value_errors() {
+    use crate::compiler::CompilerError;
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let check = |program: &str| {
+        let ast = parse(program).unwrap();
+        let error = ast.compile().unwrap_err();
+        assert_eq!(lower(&ast).unwrap_err().code(), error.code(), "{}", program);
+        error
+    };
+
+    assert!(matches!(
+        check("let y = if true { rtb 1; };"),
+        CompilerError::IfWithoutElse(_)
+    ));
+    assert!(matches!(
+        check("let y = if true { rtb 1; } elif false { rtb 2; } else { 3; };"),
+        CompilerError::BlockWithoutValue(_)
+    ));
+    assert!(matches!(
+        check("let y = if true { rtb 1; } else { rtb 1 > 2; };"),
+        CompilerError::IncompatibleBranches(_, _, _)
+    ));
+    assert!(matches!(
+        check("let y = if true { rtb 1; } elif true { rtb 2; } else { rtb 2.5; };"),
+        CompilerError::IncompatibleBranches(_, _, _)
+    ));
+
+    // 变量的类型来自之前的赋值
+    for program in [
+        "let a = \"x\"; let y = if true { rtb 1; } else { rtb a; };",
+        "let a = 1; let c = true; let y = if c { rtb a; } else { rtb \"x\"; };",
+        "let a = 1; let b = a * 2.5; let y = if a > 0 { rtb b; } elif a < 0 { rtb a; } else { rtb 0.5; };",
+        "let a = 1; let y = if a > 0 { a = 2; rtb a; } else { rtb a == 1; };",
+    ] {
+        assert!(
+            matches!(check(program), CompilerError::IncompatibleBranches(_, _, _)),
+            "{}",
+            program
+        );
+    }
+
+    // 在分支中可能被赋值为其他类型的变量的类型无法确定，不参与比较；作为语句的 if 不需要 else
+    for program in [
+        "let a = 1; let c = true; if c { a = \"x\"; } let y = if c { rtb a; } else { rtb 2; };",
+        "let a = 1; let y = if a > 0 { a = \"x\"; rtb a; } else { rtb 2; };",
+        "let a = 1; let y = { if a > 1 { rtb 0; } a = 1.5; rtb 1; }; let z = if true { rtb a; } else { rtb 2; };",
+        "let a = 1; if a > 0 { a = 2; }",
+    ] {
+        assert!(parse(program).unwrap().compile().is_ok(), "{}", program);
+    }
+}
+
+#[test]
+fn test_if_value_variable_types() {
+    use crate::analysis::analyze;
+    use crate::compiler::c::compile_c;
+    use crate::compiler::debug::compile_items;
+    use crate::compiler::section::SectionNamer;
+    use crate::parser::{parse, parse_items};
+
+    // 逐项编译时，之前的项中变量的类型同样可见
+    let program = "let a = 1;\nlet c = true;\nlet y = if c { rtb a; } else { rtb \"x\"; };\n";
+
+    let codes: Vec<_> = analyze(program)
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.code)
+        .collect();
+    assert_eq!(codes, [Some("E0118")]);
+
+    let (items, _) = parse_items(program);
+    let err = compile_items(program, &items, 0, &mut SectionNamer::new()).unwrap_err();
+    assert_eq!(err.value.code(), "E0118");
+    assert_eq!(
+        &program[err.span.start..err.span.end],
+        "if c { rtb a; } else { rtb \"x\"; }"
+    );
+
+    let err = compile_c(&parse(program).unwrap()).unwrap_err();
+    assert_eq!(err.code(), "E0118");
+}
+
+#[test]
+fn test_if_value_infer() {
+    use crate::analysis::types::{infer, Type};
+    use crate::parser::parse_expr;
+    use crate::parser::{BlueArchParser, Rule};
+    use pest::Parser;
+
+    let ty = |code: &str| {
+        let pair = BlueArchParser::parse(Rule::expr, code)
+            .unwrap()
+            .next()
+            .unwrap();
+        infer(&parse_expr(&pair).unwrap(), &|_| Type::Int)
+    };
+
+    assert_eq!(ty("if a { rtb 1; } else { rtb a; }"), Type::Int);
+    assert_eq!(ty("if a { rtb 1; } else { rtb 2.0; }"), Type::Unknown);
+    assert_eq!(ty("if a { rtb 1; }"), Type::Unknown);
+    assert_eq!(ty("(if a { rtb 1; } else { rtb 2; }) > 0"), Type::Bool);
+}
+
+#[test]
+fn test_if_value_jump_if_else() {
+    use super::output;
+    use crate::compiler::{ByteCode, OpCode};
+    use crate::optimizer::optimize_bytecodes;
+    use crate::parser::parse;
+
+    let codes = parse("let y = if false { rtb 1; } else { rtb 2; };")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let jump = codes
+        .iter()
+        .find(|code| code.op == OpCode::JumpIfElse)
+        .unwrap();
+    assert_eq!(jump.targets(), vec!["00000000", "00000001"]);
+    assert!(ByteCode::from_args(OpCode::JumpIfElse, &["00000000"]).is_err());
+
+    // 条件为常量时只保留进入的分支
+    let (optimized, _) = optimize_bytecodes(&codes, 2);
+    assert!(optimized.iter().all(|code| code.op != OpCode::JumpIfElse));
+    assert_eq!(output(&optimized), output(&codes));
+}
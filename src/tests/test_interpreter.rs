@@ -0,0 +1,64 @@
+#[allow(dead_code)]
+fn run(code: &str) -> Result<String, crate::interpreter::RuntimeError> {
+    use crate::interpreter::Interpreter;
+    use crate::parser::parse;
+
+    let codes = parse(code).unwrap().compile().unwrap();
+    let mut interpreter = Interpreter::new();
+    interpreter.run(&codes)?;
+    Ok(interpreter.output())
+}
+
+#[test]
+fn test_interpreter_expr() {
+    let code = std::fs::read_to_string("examples/expr.ba").unwrap();
+    let code = code
+        .replace("true + false;\n", "")
+        .replace("hello + 1;\n", "");
+
+    assert_eq!(run(&code).unwrap(), "2\n9\n");
+}
+
+#[test]
+fn test_interpreter_assign() {
+    assert_eq!(
+        run("let a = 1; let b: int = a + 2; a = b * 10;").unwrap(),
+        "a = 30\nb = 3\n"
+    );
+}
+
+#[test]
+fn test_interpreter_if() {
+    let code = "if a == 1 { let b = 1; } elif a == 2 { let b = 2; } else { let b = 3; }";
+
+    for (a, b) in [(1, 1), (2, 2), (3, 3)] {
+        let output = run(&format!("let a = {}; {}", a, code)).unwrap();
+        assert_eq!(output, format!("a = {}\nb = {}\n", a, b));
+    }
+}
+
+#[test]
+fn test_interpreter_if_condition_once() {
+    let code = "let a = 1; if a == 1 { a = 2; } elif a == 2 { a = 3; }";
+
+    assert_eq!(run(code).unwrap(), "a = 2\n");
+}
+
+#[test]
+fn test_interpreter_errors() {
+    use crate::compiler::value::ValueError;
+    use crate::interpreter::RuntimeError;
+
+    assert_eq!(
+        run("hello + 1;"),
+        Err(RuntimeError::UndefinedName("hello".to_string()))
+    );
+    assert!(matches!(
+        run("true + false;"),
+        Err(RuntimeError::ValueError(ValueError::TypeError(_)))
+    ));
+    assert!(matches!(
+        run("if 1 { let a = 1; }"),
+        Err(RuntimeError::InvalidCondition(_))
+    ));
+}
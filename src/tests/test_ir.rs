@@ -0,0 +1,124 @@
+#[test]
+fn test_ir_dump() {
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let ir = lower(&parse("let a = 1; a + 2 * 3;").unwrap()).unwrap();
+
+    assert_eq!(ir.temps, 6);
+    assert_eq!(
+        ir.to_string(),
+        "bb0:\n    %0 = const 1\n    store a, %0\n    %1 = load a\n    %2 = const 2\n    %3 = const 3\n    %4 = * %2, %3\n    %5 = + %1, %4\n    yield %5\n    return\n"
+    );
+}
+
+#[test]
+fn test_ir_if() {
+    use crate::ir::{lower, BlockId, Terminator};
+    use crate::parser::parse;
+
+    let code = std::fs::read_to_string("examples/if.ba").unwrap();
+    let ir = lower(&parse(&code).unwrap()).unwrap();
+
+    // 入口、汇合块、三个条件的 then 块、两个 elif 的条件块与 else 块
+    assert_eq!(ir.blocks.len(), 8);
+    assert!(matches!(
+        ir.block(ir.entry).terminator,
+        Terminator::Branch(_, _, _)
+    ));
+
+    let post_dominators = ir.immediate_post_dominators();
+    for block in &ir.blocks {
+        if let Terminator::Branch(_, _, _) = block.terminator {
+            assert_eq!(post_dominators[&block.id], Some(BlockId(1)));
+        }
+    }
+    assert_eq!(ir.block(BlockId(1)).terminator, Terminator::Return(None));
+}
+
+#[test]
+fn test_ir_emit() {
+    use crate::compiler::{ByteCode, OpCode};
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let codes = lower(&parse("let a = 1; a + 2;").unwrap())
+        .unwrap()
+        .emit()
+        .unwrap();
+
+    assert_eq!(
+        codes,
+        vec![
+            ByteCode::from_args(OpCode::Push, &["1"]).unwrap(),
+            ByteCode::from_args(OpCode::StoreName, &["a"]).unwrap(),
+            ByteCode::from_args(OpCode::LoadName, &["a"]).unwrap(),
+            ByteCode::from_args(OpCode::Push, &["2"]).unwrap(),
+            ByteCode::from_args(OpCode::Add, &[]).unwrap(),
+        ]
+    );
+}
+
+#[test]
+fn test_ir_emit_equivalent() {
+    use super::output;
+    use crate::interpreter::Interpreter;
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let programs = [
+        "1 + 2 * 3 / 4; (1 + 2) * 3;",
+        "let a = 2; if a == 1 { let b = 1; } elif a == 2 { let b = 2; 1 + 1; } else { let b = 3; }",
+        "let a = 1; if a > 0 { if a > 1 { a = 10; } else { a = 20; } } a;",
+        "let a = \"x\"; if a == \"y\" { a = \"z\"; } a + \"w\";",
+    ];
+
+    for program in programs {
+        // 栈式字节码与寄存器指令由同一份中间表示生成，可观察的结果相同
+        let ir = lower(&parse(program).unwrap()).unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter
+            .run_registers(&ir.emit_registers(2).unwrap())
+            .unwrap();
+        assert_eq!(
+            output(&ir.emit().unwrap()),
+            interpreter.output(),
+            "{}",
+            program
+        );
+    }
+}
+
+#[test]
+fn test_ir_emit_named_temp() {
+    use super::output;
+    use crate::ir::{BasicBlock, BlockId, Instr, IrProgram, Temp, Terminator};
+
+    // %0 在 bb0 中定义，在 then 分支的 Section 中使用，不在同一个栈帧中，因此需要存入堆中
+    let ir = IrProgram {
+        blocks: vec![
+            BasicBlock {
+                id: BlockId(0),
+                instrs: vec![
+                    Instr::Const(Temp(0), "7".to_string()),
+                    Instr::Const(Temp(1), "true".to_string()),
+                ],
+                terminator: Terminator::Branch(Temp(1), BlockId(1), BlockId(2)),
+            },
+            BasicBlock {
+                id: BlockId(1),
+                instrs: vec![Instr::Store("a".to_string(), Temp(0))],
+                terminator: Terminator::Jump(BlockId(2)),
+            },
+            BasicBlock {
+                id: BlockId(2),
+                instrs: vec![],
+                terminator: Terminator::Return(None),
+            },
+        ],
+        entry: BlockId(0),
+        temps: 2,
+    };
+
+    assert_eq!(output(&ir.emit().unwrap()), "%0 = 7\na = 7\n");
+}
@@ -0,0 +1,279 @@
+//! 语言服务器的测试，使用脚本化的客户端：将一系列消息写入输入，运行服务器直到输入结束，再读出服务器发送的所有消息
+
+/// 测试中使用的文档地址
+#[allow(dead_code)]
+const URI: &str = "file:///test.ba";
+
+/// 依次发送消息，返回服务器发送的所有消息
+#[allow(dead_code)]
+fn session(messages: &[serde_json::Value]) -> Vec<serde_json::Value> {
+    use crate::lsp::{read_message, run, write_message};
+
+    let mut input: Vec<u8> = vec![];
+    for message in messages {
+        write_message(&mut input, message).unwrap();
+    }
+
+    let mut output: Vec<u8> = vec![];
+    run(input.as_slice(), &mut output).unwrap();
+
+    let mut reader = output.as_slice();
+    let mut responses = vec![];
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        responses.push(message);
+    }
+    responses
+}
+
+#[allow(dead_code)]
+fn request(id: u64, method: &str, params: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+}
+
+#[allow(dead_code)]
+fn notification(method: &str, params: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// 初始化并打开文档的消息
+#[allow(dead_code)]
+fn open(text: &str) -> Vec<serde_json::Value> {
+    use serde_json::json;
+
+    vec![
+        request(0, "initialize", json!({ "capabilities": {} })),
+        notification("initialized", json!({})),
+        notification(
+            "textDocument/didOpen",
+            json!({ "textDocument": { "uri": URI, "languageId": "hare", "version": 1, "text": text } }),
+        ),
+    ]
+}
+
+/// 返回指定 id 的请求的结果
+#[allow(dead_code)]
+fn result(responses: &[serde_json::Value], id: u64) -> serde_json::Value {
+    responses
+        .iter()
+        .find(|response| response["id"] == id)
+        .unwrap_or_else(|| panic!("no response to request {}", id))["result"]
+        .clone()
+}
+
+/// 对文档中某个位置的请求的参数
+#[allow(dead_code)]
+fn at(line: u64, character: u64) -> serde_json::Value {
+    serde_json::json!({
+        "textDocument": { "uri": URI },
+        "position": { "line": line, "character": character },
+        "context": { "includeDeclaration": true },
+    })
+}
+
+#[test]
+fn test_lsp_initialize_and_shutdown() {
+    use serde_json::json;
+
+    let responses = session(&[
+        request(1, "textDocument/hover", at(0, 0)),
+        request(2, "initialize", json!({ "capabilities": {} })),
+        request(3, "workspace/symbol", json!({})),
+        request(4, "shutdown", json!(null)),
+        notification("exit", json!(null)),
+        request(5, "shutdown", json!(null)),
+    ]);
+
+    assert_eq!(responses.len(), 4);
+    assert_eq!(responses[0]["error"]["code"], -32002);
+    let capabilities = &responses[1]["result"]["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(capabilities["hoverProvider"], true);
+    assert_eq!(capabilities["documentFormattingProvider"], true);
+    assert_eq!(responses[2]["error"]["code"], -32601);
+    assert_eq!(
+        responses[3],
+        json!({ "jsonrpc": "2.0", "id": 4, "result": null })
+    );
+}
+
+#[test]
+fn test_lsp_malformed_message() {
+    use crate::lsp::transport::MAX_CONTENT_LENGTH;
+    use crate::lsp::{read_message, run, write_message, LspError};
+    use serde_json::json;
+
+    // 内容不是合法 JSON 的消息以解析错误回复，服务器继续处理之后的请求
+    let mut input: Vec<u8> = b"Content-Length: 5\r\n\r\n{bad}".to_vec();
+    write_message(&mut input, &request(1, "initialize", json!({}))).unwrap();
+    write_message(&mut input, &request(2, "shutdown", json!(null))).unwrap();
+    let mut output: Vec<u8> = vec![];
+    run(input.as_slice(), &mut output).unwrap();
+
+    let mut reader = output.as_slice();
+    let mut responses = vec![];
+    while let Some(message) = read_message(&mut reader).unwrap() {
+        responses.push(message);
+    }
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["id"], json!(null));
+    assert_eq!(responses[0]["error"]["code"], -32700);
+    assert_eq!(responses[1]["id"], 1);
+    assert_eq!(
+        responses[2],
+        json!({ "jsonrpc": "2.0", "id": 2, "result": null })
+    );
+
+    // 过大的 `Content-Length` 在分配内存之前被拒绝
+    let header = format!("Content-Length: {}\r\n\r\n", MAX_CONTENT_LENGTH + 1);
+    assert!(matches!(
+        read_message(&mut header.as_bytes()),
+        Err(LspError::Protocol(_))
+    ));
+}
+
+#[test]
+fn test_lsp_diagnostics() {
+    use serde_json::json;
+
+    let mut messages = open("let a = 1;\nlet b = ;\n");
+    messages.push(notification(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": "let a = 1;\nlet b = 1 / 0;\n" }],
+        }),
+    ));
+    messages.push(notification(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 3 },
+            "contentChanges": [{ "text": "let a = 1;\n" }],
+        }),
+    ));
+    let responses = session(&messages);
+
+    let published: Vec<&serde_json::Value> = responses
+        .iter()
+        .filter(|response| response["method"] == "textDocument/publishDiagnostics")
+        .map(|response| &response["params"]["diagnostics"])
+        .collect();
+    assert_eq!(published.len(), 3);
+
+    assert_eq!(published[0][0]["severity"], 1);
+    assert_eq!(published[0][0]["message"], "expected expression after '='");
+    assert_eq!(
+        published[0][0]["range"]["start"],
+        json!({ "line": 1, "character": 8 })
+    );
+
+    assert_eq!(published[1][0]["severity"], 1);
+    assert_eq!(
+        published[1][0]["range"],
+        json!({ "start": { "line": 1, "character": 8 }, "end": { "line": 1, "character": 13 } })
+    );
+    assert!(published[1][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("Division by zero"));
+
+    assert_eq!(published[2], &json!([]));
+}
+
+#[test]
+fn test_lsp_navigation() {
+    use serde_json::json;
+
+    let text = "let count = 1;\nlet 名字 = \"a\";\nif count > 0 {\n    count = count + 1.5;\n}\n";
+    let mut messages = open(text);
+    messages.extend([
+        request(1, "textDocument/hover", at(0, 6)),
+        request(2, "textDocument/hover", at(3, 12)),
+        request(3, "textDocument/hover", at(3, 25)),
+        request(4, "textDocument/definition", at(3, 14)),
+        request(5, "textDocument/references", at(2, 3)),
+        request(
+            6,
+            "textDocument/documentSymbol",
+            json!({ "textDocument": { "uri": URI } }),
+        ),
+        request(7, "textDocument/hover", at(1, 6)),
+    ]);
+    let responses = session(&messages);
+
+    assert_eq!(
+        result(&responses, 1)["contents"]["value"],
+        "```hare\nlet count: int\n```"
+    );
+    assert_eq!(
+        result(&responses, 2)["contents"]["value"],
+        "```hare\ncount: int\n```"
+    );
+    assert_eq!(result(&responses, 3), json!(null));
+
+    assert_eq!(
+        result(&responses, 4),
+        json!({
+            "uri": URI,
+            "range": { "start": { "line": 0, "character": 4 }, "end": { "line": 0, "character": 9 } },
+        })
+    );
+
+    let references: Vec<(u64, u64)> = result(&responses, 5)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| {
+            let start = &location["range"]["start"];
+            (
+                start["line"].as_u64().unwrap(),
+                start["character"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(references, vec![(0, 4), (2, 3), (3, 4), (3, 12)]);
+
+    let symbols = result(&responses, 6);
+    assert_eq!(symbols.as_array().unwrap().len(), 2);
+    assert_eq!(symbols[0]["name"], "count");
+    assert_eq!(symbols[0]["detail"], "int");
+    assert_eq!(symbols[0]["kind"], 13);
+    assert_eq!(symbols[1]["name"], "名字");
+    assert_eq!(symbols[1]["detail"], "str");
+    // 位置以 UTF-16 编码单元计
+    assert_eq!(
+        symbols[1]["selectionRange"]["end"],
+        json!({ "line": 1, "character": 6 })
+    );
+
+    assert_eq!(
+        result(&responses, 7)["contents"]["value"],
+        "```hare\nlet 名字: str\n```"
+    );
+}
+
+#[test]
+fn test_lsp_formatting() {
+    use serde_json::json;
+
+    let params = json!({ "textDocument": { "uri": URI }, "options": { "tabSize": 4, "insertSpaces": true } });
+    let mut messages = open("let a=1;if a{a=2}");
+    messages.push(request(1, "textDocument/formatting", params.clone()));
+    messages.push(notification(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": URI, "version": 2 },
+            "contentChanges": [{ "text": "let a =" }],
+        }),
+    ));
+    messages.push(request(2, "textDocument/formatting", params));
+    let responses = session(&messages);
+
+    assert_eq!(
+        result(&responses, 1),
+        json!([{
+            "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 17 } },
+            "newText": "let a = 1;\nif a {\n    a = 2\n}\n",
+        }])
+    );
+    assert_eq!(result(&responses, 2), json!(null));
+}
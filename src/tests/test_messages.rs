@@ -0,0 +1,76 @@
+#[allow(dead_code)]
+fn messages(code: &str) -> Vec<(String, Option<String>)> {
+    use crate::parser::parse_with_recovery;
+
+    parse_with_recovery(code)
+        .1
+        .into_iter()
+        .map(|diagnostic| (diagnostic.message(), diagnostic.hint))
+        .collect()
+}
+
+#[test]
+fn test_messages_expected_expression() {
+    assert_eq!(messages("let a = ;")[0].0, "expected expression after '='");
+    assert_eq!(messages("a = 1 +;")[0].0, "expected expression after '+'");
+    assert_eq!(messages("if ;")[0].0, "expected expression after 'if'");
+    // 块可以作为条件，缺少的是之后的块
+    assert_eq!(messages("if { }")[0].0, "missing '{' after if condition");
+    assert_eq!(messages("1 + (2;")[0].0, "missing ')'");
+    assert_eq!(messages("let a: = 1;")[0].0, "expected type name after ':'");
+}
+
+#[test]
+fn test_messages_hints() {
+    let let_without_value = messages("let a;");
+    assert_eq!(
+        let_without_value[0].0,
+        "missing '=' in variable declaration"
+    );
+    assert!(let_without_value[0].1.is_some());
+
+    let unclosed = messages("if a {\n    let b = 1;\n");
+    assert_eq!(unclosed[0].0, "unclosed block");
+    assert!(unclosed[0].1.is_some());
+
+    let elif = messages("let a = 1;\nelif a { }");
+    assert_eq!(elif[0].0, "'elif' without a preceding 'if'");
+    assert!(elif[0].1.is_some());
+
+    assert_eq!(messages("if a { } else 1")[0].0, "missing '{' after 'else'");
+    assert_eq!(
+        messages("if a let b = 1;")[0].0,
+        "missing '{' after if condition"
+    );
+}
+
+#[test]
+fn test_messages_render() {
+    use crate::parser::parse_with_recovery;
+
+    let code = "let a = 1;\nlet b = ;";
+    let (_, diagnostics) = parse_with_recovery(code);
+
+    assert_eq!(
+        diagnostics[0].render(code),
+        "error: expected expression after '='\n --> 2:9\n  |\n2 | let b = ;\n  |         ^\n"
+    );
+}
+
+#[test]
+fn test_messages_no_rule_names() {
+    use crate::fuzz::Generator;
+    use crate::parser::parse_with_recovery;
+
+    // 任何错误信息都不应该出现语法规则的内部名字
+    for seed in 0..200 {
+        let mut code = Generator::new(seed).program();
+        code.insert_str(code.len() / 2, " = ( ");
+
+        for diagnostic in parse_with_recovery(&code).1 {
+            let message = diagnostic.message();
+            assert!(!message.contains("_statement"), "{}", message);
+            assert!(!message.contains("EOI"), "{}", message);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+#[test]
+fn test_operand_validation() {
+    use crate::compiler::operand::{Operand, OperandError};
+    use crate::compiler::value::Value;
+    use crate::compiler::{ByteCode, OpCode};
+
+    assert_eq!(
+        ByteCode::new(OpCode::Push, vec![]),
+        Err(OperandError::WrongArity(
+            OpCode::Push,
+            "at least one constant".to_string(),
+            0
+        ))
+    );
+    assert_eq!(
+        ByteCode::new(OpCode::Add, vec![Operand::Int(1)]),
+        Err(OperandError::WrongArity(
+            OpCode::Add,
+            "no operands".to_string(),
+            1
+        ))
+    );
+    assert_eq!(
+        ByteCode::new(OpCode::LoadName, vec![Operand::Section("s".to_string())]),
+        Err(OperandError::WrongKind(
+            OpCode::LoadName,
+            "s".to_string(),
+            "name".to_string()
+        ))
+    );
+    assert_eq!(
+        ByteCode::new(OpCode::Jump, vec![Operand::Name("a".to_string())])
+            .unwrap_err()
+            .to_string(),
+        "operand `a` of Jump must be section label"
+    );
+    assert_eq!(
+        ByteCode::from_args(OpCode::Push, &["1", "x"]),
+        Err(OperandError::WrongKind(
+            OpCode::Push,
+            "x".to_string(),
+            "constant".to_string()
+        ))
+    );
+
+    // 整数常量总是作为立即数
+    let push = ByteCode::new(
+        OpCode::Push,
+        vec![
+            Operand::Constant(Value::Int(7)),
+            Operand::Constant(Value::Float(0.5)),
+            Operand::Constant(Value::Str("s".to_string())),
+        ],
+    )
+    .unwrap();
+    assert_eq!(
+        push,
+        ByteCode::from_args(OpCode::Push, &["7", "0.5", "\"s\""]).unwrap()
+    );
+    assert_eq!(push.operands()[0], Operand::Int(7));
+    assert_eq!(push.args(), vec!["7", "0.5", "\"s\""]);
+}
+
+#[test]
+fn test_operand_text() {
+    use crate::compiler::format_bytecodes;
+    use crate::parser::parse;
+
+    let codes = parse("let a = 1.5 + 2; if a > 1 { a = \"s\"; }")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let expected = r#"Push ["1.5"]
+Push ["2"]
+Add []
+StoreName ["a"]
+MakeSection ["00000000"]
+Push ["\"s\""]
+StoreName ["a"]
+EndMakeSection []
+LoadName ["a"]
+Push ["1"]
+Gt []
+JumpIf ["00000000"]
+"#;
+
+    assert_eq!(format_bytecodes(&codes), expected);
+}
+
+#[test]
+fn test_operand_object_pool() {
+    use crate::compiler::object::{Object, ObjectError};
+    use crate::compiler::operand::OperandError;
+    use crate::compiler::{ByteCode, OpCode};
+    use crate::parser::parse;
+
+    let codes = parse("let s = \"x\"; s = s + \"x\"; let t = s; if t == \"xx\" { t = 1.5; }")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let bytes = Object::new(codes.clone()).to_bytes();
+    assert_eq!(Object::load(&bytes).unwrap().codes, codes);
+
+    // 常量与名字只在池中出现一次
+    let count = |needle: &[u8]| bytes.windows(needle.len()).filter(|w| *w == needle).count();
+    assert_eq!(count(b"xx"), 1);
+    assert_eq!(count(&[1, 0, 0, 0, b'x']), 1);
+    assert_eq!(count(&[1, 0, 0, 0, b's']), 1);
+
+    // 文件头之后依次为常量池（0 项）、名字表（1 项）与指令
+    let object = Object::new(vec![ByteCode::from_args(OpCode::LoadName, &["a"]).unwrap()]);
+    let bytes = object.to_bytes();
+    assert_eq!(&bytes[11..24], &[0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0, 0, b'a']);
+    assert_eq!(&bytes[28..38], &[18, 1, 0, 0, 0, 2, 0, 0, 0, 0]);
+
+    let mut corrupted = bytes.clone();
+    corrupted[34] = 1;
+    assert_eq!(
+        Object::from_bytes(&corrupted),
+        Err(ObjectError::InvalidIndex(1, 34))
+    );
+    corrupted[33] = 9;
+    assert_eq!(
+        Object::from_bytes(&corrupted),
+        Err(ObjectError::InvalidTag(9, 33))
+    );
+    corrupted[33] = 3;
+    corrupted[34] = 0;
+    assert_eq!(
+        Object::from_bytes(&corrupted),
+        Err(ObjectError::InvalidInstruction(
+            28,
+            OperandError::WrongKind(OpCode::LoadName, "a".to_string(), "name".to_string())
+        ))
+    );
+}
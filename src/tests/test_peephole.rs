@@ -0,0 +1,217 @@
+#[allow(dead_code)]
+fn code(op: crate::compiler::OpCode, args: &[&str]) -> crate::compiler::ByteCode {
+    crate::compiler::ByteCode::from_args(op, args).unwrap()
+}
+
+/// 只使用名为 `name` 的规则进行优化，断言规则发生了改写且可观察的结果不变
+#[allow(dead_code)]
+fn assert_rule(
+    name: &str,
+    codes: Vec<crate::compiler::ByteCode>,
+) -> Vec<crate::compiler::ByteCode> {
+    use super::output;
+    use crate::optimizer::peephole::{peephole_with, rule};
+
+    let rule = rule(name).unwrap();
+    let (optimized, stats) = peephole_with(&codes, std::slice::from_ref(rule));
+
+    assert!(stats.rewrites.get(name).copied().unwrap_or(0) > 0);
+    assert_eq!(output(&codes), output(&optimized));
+    optimized
+}
+
+#[test]
+fn test_peephole_push_push() {
+    use crate::compiler::OpCode::*;
+
+    let optimized = assert_rule(
+        "push-push",
+        vec![code(Push, &["1"]), code(Push, &["2"]), code(Add, &[])],
+    );
+    assert_eq!(optimized, vec![code(Push, &["1", "2"]), code(Add, &[])]);
+}
+
+#[test]
+fn test_peephole_push_pop() {
+    use crate::compiler::OpCode::*;
+
+    let optimized = assert_rule(
+        "push-pop",
+        vec![
+            code(Push, &["1"]),
+            code(Push, &["2", "3"]),
+            code(Pop, &[]),
+            code(Push, &["4"]),
+            code(Pop, &[]),
+        ],
+    );
+    assert_eq!(optimized, vec![code(Push, &["1"]), code(Push, &["2"])]);
+}
+
+#[test]
+fn test_peephole_dup_pop() {
+    use crate::compiler::OpCode::*;
+
+    let optimized = assert_rule(
+        "dup-pop",
+        vec![code(Push, &["1"]), code(Dup, &[]), code(Pop, &[])],
+    );
+    assert_eq!(optimized, vec![code(Push, &["1"])]);
+}
+
+#[test]
+fn test_peephole_push_dup() {
+    use crate::compiler::OpCode::*;
+
+    let optimized = assert_rule(
+        "push-dup",
+        vec![code(Push, &["1", "2"]), code(Dup, &[]), code(Add, &[])],
+    );
+    assert_eq!(
+        optimized,
+        vec![code(Push, &["1", "2", "2"]), code(Add, &[])]
+    );
+}
+
+#[test]
+fn test_peephole_push_neg() {
+    use crate::compiler::OpCode::*;
+
+    let optimized = assert_rule(
+        "push-neg",
+        vec![
+            code(Push, &["3"]),
+            code(Neg, &[]),
+            code(Push, &["true"]),
+            code(Neg, &[]),
+        ],
+    );
+    assert_eq!(optimized, vec![code(Push, &["-3"]), code(Push, &["false"])]);
+}
+
+#[test]
+fn test_peephole_store_load() {
+    use crate::compiler::OpCode::*;
+    use crate::parser::parse;
+
+    let codes = parse("let a = 1; a + 1;").unwrap().compile().unwrap();
+    let optimized = assert_rule("store-load", codes);
+    assert_eq!(optimized[1], code(Dup, &[]));
+    assert_eq!(optimized[2], code(StoreName, &["a"]));
+}
+
+#[test]
+fn test_peephole_const_jump_if() {
+    use crate::compiler::OpCode::*;
+    use crate::parser::parse;
+
+    let codes = parse("if true { let a = 1; } if false { let b = 1; }")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let optimized = assert_rule("const-jump-if", codes);
+    assert_eq!(optimized.iter().filter(|code| code.op == JumpIf).count(), 0);
+    assert_eq!(optimized.iter().filter(|code| code.op == Jump).count(), 1);
+}
+
+#[test]
+fn test_peephole_empty_section() {
+    use crate::compiler::OpCode::*;
+    use crate::parser::parse;
+
+    let codes = parse("let a = true; if a {} else { let b = 1; }")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let optimized = assert_rule("empty-section", codes);
+    assert_eq!(optimized.iter().filter(|code| code.op == JumpIf).count(), 1);
+}
+
+#[test]
+fn test_peephole_thread_jumps() {
+    use crate::compiler::OpCode::*;
+
+    // Section s 只包含跳转到 t 的指令
+    let codes = vec![
+        code(MakeSection, &["t"]),
+        code(Push, &["1"]),
+        code(StoreName, &["b"]),
+        code(EndMakeSection, &[]),
+        code(MakeSection, &["s"]),
+        code(Jump, &["t"]),
+        code(EndMakeSection, &[]),
+        code(Push, &["true"]),
+        code(JumpIf, &["s"]),
+    ];
+
+    let optimized = assert_rule("thread-jumps", codes);
+    let jump_if = optimized.iter().find(|code| code.op == JumpIf).unwrap();
+    assert_eq!(jump_if.args(), vec!["t".to_string()]);
+}
+
+#[test]
+fn test_peephole_thread_jumps_return_value() {
+    use super::output;
+    use crate::compiler::OpCode::*;
+    use crate::optimizer::peephole::{peephole_with, rule};
+
+    // 目标 Section 有返回值，而中间的 Section 会丢弃它，因此不能跳过中间的 Section
+    let codes = vec![
+        code(MakeSection, &["t"]),
+        code(Push, &["1"]),
+        code(Return, &[]),
+        code(EndMakeSection, &[]),
+        code(MakeSection, &["s"]),
+        code(Jump, &["t"]),
+        code(EndMakeSection, &[]),
+        code(Jump, &["s"]),
+    ];
+    let (optimized, stats) =
+        peephole_with(&codes, std::slice::from_ref(rule("thread-jumps").unwrap()));
+    assert!(stats.rewrites.is_empty());
+    assert_eq!(optimized, codes);
+
+    let mut codes = codes;
+    codes.insert(6, code(Return, &[]));
+    let optimized = assert_rule("thread-jumps", codes);
+    assert_eq!(optimized.last(), Some(&code(Jump, &["t"])));
+    assert_eq!(output(&optimized), "1\n");
+}
+
+#[test]
+fn test_peephole_dead_section() {
+    use crate::compiler::OpCode::*;
+
+    let optimized = assert_rule(
+        "dead-section",
+        vec![
+            code(MakeSection, &["s"]),
+            code(MakeSection, &["t"]),
+            code(EndMakeSection, &[]),
+            code(EndMakeSection, &[]),
+            code(Push, &["1"]),
+        ],
+    );
+    assert_eq!(optimized, vec![code(Push, &["1"])]);
+}
+
+#[test]
+fn test_peephole_fixed_point() {
+    use super::output;
+    use crate::optimizer::{optimize, peephole};
+    use crate::parser::parse;
+
+    let code = "let a = 1; if a == 1 { 1 + 2; } elif a == 2 { let b = 2; } else { if true { let b = 3; } }";
+    let codes = optimize(&parse(code).unwrap(), 1)
+        .unwrap()
+        .0
+        .compile()
+        .unwrap();
+    let (optimized, stats) = peephole(&codes);
+
+    assert_eq!(output(&codes), output(&optimized));
+    assert_eq!(stats.before, codes.len());
+    assert_eq!(stats.after, optimized.len());
+    assert!(stats.removed() > 0);
+    assert!(stats.iterations > 1);
+}
@@ -0,0 +1,57 @@
+#[test]
+fn test_recover_multiple_errors() {
+    use crate::parser::ast::AstNode;
+    use crate::parser::parse_with_recovery;
+
+    let code = "let a = 1;\nlet b = ;\nlet c = a + 2;\nc = * 3;\nlet d = c;";
+    let (ast, diagnostics) = parse_with_recovery(code);
+
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].span.location(code), (2, 9));
+    assert_eq!(diagnostics[1].span.location(code), (4, 5));
+
+    let AstNode::Program(nodes) = ast else {
+        panic!("expected a program");
+    };
+    assert_eq!(nodes.len(), 5);
+    assert_eq!(nodes[1], AstNode::Error("let b = ;".to_string()));
+    assert_eq!(nodes[3], AstNode::Error("c = * 3;".to_string()));
+    assert!(matches!(nodes[4], AstNode::Assign(_, _, _)));
+}
+
+#[test]
+fn test_recover_inside_blocks() {
+    use crate::parser::parse_with_recovery;
+
+    let code = "if a {\n    let b = ;\n    b = 1;\n} elif a == 2 {\n    let c = +;\n} else {\n}\nlet d = 1;";
+    let (_, diagnostics) = parse_with_recovery(code);
+
+    let locations: Vec<(usize, usize)> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.location(code))
+        .collect();
+    assert_eq!(locations, vec![(2, 13), (5, 13)]);
+}
+
+#[test]
+fn test_recover_unclosed_block() {
+    use crate::parser::errors::ParserError;
+    use crate::parser::parse_with_recovery;
+
+    let (_, diagnostics) = parse_with_recovery("let a = 1;\nif a {\n    a = 2;\n");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].span.start, 16);
+    assert!(matches!(diagnostics[0].error, ParserError::UnclosedBlock));
+}
+
+#[test]
+fn test_recover_valid_program() {
+    use crate::parser::{parse, parse_with_recovery};
+
+    let code = std::fs::read_to_string("examples/if.ba").unwrap();
+    let (ast, diagnostics) = parse_with_recovery(&code);
+
+    assert!(diagnostics.is_empty());
+    assert_eq!(ast.as_code(), parse(&code).unwrap().as_code());
+}
@@ -0,0 +1,105 @@
+/// 分别以栈式字节码与寄存器指令执行程序，返回可观察的结果，出现运行时错误时为 `None`
+#[allow(dead_code)]
+fn run_both(program: &str, registers: usize) -> Option<(Option<String>, Option<String>)> {
+    use crate::interpreter::Interpreter;
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let ast = parse(program).ok()?;
+    let codes = ast.compile().ok()?;
+    let instrs = lower(&ast).ok()?.emit_registers(registers).unwrap();
+
+    let mut stack = Interpreter::new();
+    let stack = stack.run(&codes).ok().map(|_| stack.output());
+    let mut register = Interpreter::new();
+    let register = register
+        .run_registers(&instrs)
+        .ok()
+        .map(|_| register.output());
+
+    Some((stack, register))
+}
+
+#[test]
+fn test_register_dump() {
+    use crate::ir::lower;
+    use crate::parser::parse;
+
+    let ast = parse("let a = 1; if a > 0 { a = 2; } a + 3;").unwrap();
+    let program = lower(&ast).unwrap().emit_registers(4).unwrap();
+
+    assert_eq!(
+        program.to_string(),
+        "; registers: 4, slots: 0\n\
+         0000: r0 = const 1\n\
+         0001: store a, r0\n\
+         0002: r0 = load a\n\
+         0003: r1 = const 0\n\
+         0004: r1 = > r0, r1\n\
+         0005: jumpifnot r1, 0008\n\
+         0006: r1 = const 2\n\
+         0007: store a, r1\n\
+         0008: r1 = load a\n\
+         0009: r0 = const 3\n\
+         0010: r0 = + r1, r0\n\
+         0011: yield r0\n\
+         0012: halt\n"
+    );
+}
+
+#[test]
+fn test_register_linear_scan() {
+    use crate::compiler::register::{Reg, Slot};
+    use crate::ir::regalloc::{linear_scan, Interval, Location};
+    use crate::ir::Temp;
+
+    let interval = |temp, start, end| Interval {
+        temp: Temp(temp),
+        start,
+        end,
+    };
+    // %0 活跃到最后，寄存器不足时它被溢出；%1 的终点与 %2 的起点相同，%2 复用它的寄存器
+    let intervals = [
+        interval(0, 0, 9),
+        interval(1, 1, 3),
+        interval(2, 3, 5),
+        interval(3, 4, 6),
+    ];
+    let allocation = linear_scan(&intervals, 2);
+
+    assert_eq!(allocation.locations[&Temp(0)], Location::Spilled(Slot(0)));
+    assert_eq!(allocation.locations[&Temp(1)], Location::Register(Reg(1)));
+    assert_eq!(allocation.locations[&Temp(2)], Location::Register(Reg(1)));
+    assert_eq!(allocation.locations[&Temp(3)], Location::Register(Reg(0)));
+    assert_eq!(allocation.slots, 1);
+
+    let allocation = linear_scan(&intervals, 0);
+    assert_eq!(allocation.slots, 4);
+}
+
+#[test]
+fn test_register_equivalent() {
+    use crate::fuzz::Generator;
+
+    let mut programs: Vec<String> = std::fs::read_dir("examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ba"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+    programs.push("let a = 1; a + (a * (2 + (3 * (4 + a)))); a / 0;".to_string());
+    programs.extend((0..300).map(|seed| Generator::new(seed).program()));
+
+    // 错误信息可能不同：栈式字节码在 else 分支之前对条件取反，报告的是取反后的值
+    let mut compared = 0;
+    for program in &programs {
+        // 只有保留寄存器时，所有的临时变量都被溢出
+        for registers in [2, 3, 16] {
+            if let Some((stack, register)) = run_both(program, registers) {
+                assert_eq!(register, stack, "{} registers: {}", registers, program);
+                compared += 1;
+            }
+        }
+    }
+    assert!(compared > 300, "only {} programs were compared", compared);
+}
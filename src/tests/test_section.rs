@@ -0,0 +1,71 @@
+#[test]
+fn test_section_names_deterministic() {
+    use crate::parser::parse;
+
+    let code = std::fs::read_to_string("examples/if.ba").unwrap();
+    let ast = parse(&code).unwrap();
+
+    assert_eq!(ast.compile().unwrap(), ast.compile().unwrap());
+}
+
+#[test]
+fn test_section_names_sequential() {
+    use crate::compiler::section::find_sections;
+    use crate::parser::parse;
+
+    let code = std::fs::read_to_string("examples/if.ba").unwrap();
+    let codes = parse(&code).unwrap().compile().unwrap();
+    let names: Vec<String> = find_sections(&codes)
+        .into_iter()
+        .map(|section| section.name)
+        .collect();
+
+    assert_eq!(
+        names,
+        vec!["00000000", "00000001", "00000002", "00000003", "00000004", "00000005"]
+    );
+}
+
+#[test]
+fn test_section_names_unique_across_statements() {
+    use crate::compiler::section::{find_sections, SectionNamer};
+    use crate::parser::parse;
+    use std::collections::HashSet;
+
+    let ast = parse("if a { let b = 1; } if a { let b = 2; } else { let b = 3; }").unwrap();
+    let mut namer = SectionNamer::new();
+    let mut codes = ast.compile_with(&mut namer).unwrap();
+    // 同一个生成器继续生成的名字也不会与之前的重复
+    codes.extend(ast.compile_with(&mut namer).unwrap());
+
+    let sections = find_sections(&codes);
+    let names: HashSet<String> = sections
+        .iter()
+        .map(|section| section.name.clone())
+        .collect();
+    assert_eq!(sections.len(), 6);
+    assert_eq!(names.len(), 6);
+}
+
+#[test]
+fn test_section_names_seeded() {
+    use crate::compiler::section::SectionNamer;
+    use std::collections::HashSet;
+
+    let names = |seed: u64| {
+        let mut namer = SectionNamer::seeded(seed);
+        (0..1000)
+            .map(|_| namer.next_name())
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(names(42), names(42));
+    assert_ne!(names(42), names(43));
+    assert_eq!(
+        names(42).into_iter().collect::<HashSet<String>>().len(),
+        1000
+    );
+    assert!(names(42)
+        .iter()
+        .all(|name| name.len() == 8 && name.chars().all(|c| c.is_ascii_digit())));
+}
@@ -0,0 +1,173 @@
+//! 抽象语法树的序列化测试
+//!
+//! `tests/ast` 下的每个 `.ba` 文件的抽象语法树以 JSON 保存在同名的 `.json` 文件中。
+//! 设置环境变量 `HARE_BLESS=1` 运行测试会重写所有 `.json` 文件：
+//!
+//! ```text
+//! HARE_BLESS=1 cargo test serialize
+//! ```
+
+/// 存放抽象语法树测试用例的目录
+#[allow(dead_code)]
+const AST_DIR: &str = "tests/ast";
+
+/// 解析代码，返回抽象语法树与对应的区间树
+#[allow(dead_code)]
+fn parse_with_spans(code: &str) -> (crate::parser::AstNode, crate::parser::serialize::SpanTree) {
+    use crate::parser::serialize::SpanTree;
+    use crate::parser::{parse, parse_items};
+
+    let ast = parse(code).unwrap();
+    let spans = SpanTree::from_items(code, &parse_items(code).0);
+    (ast, spans)
+}
+
+#[test]
+fn test_serialize_fixtures() {
+    use crate::parser::serialize::{from_json_str, to_json};
+
+    let bless = std::env::var("HARE_BLESS").is_ok_and(|value| value == "1");
+    let mut files: Vec<std::path::PathBuf> = std::fs::read_dir(AST_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ba"))
+        .collect();
+    files.sort();
+    assert!(!files.is_empty(), "no AST fixtures found");
+
+    for path in files {
+        let code = std::fs::read_to_string(&path).unwrap();
+        let (ast, spans) = parse_with_spans(&code);
+        let actual = format!("{:#}\n", to_json(&ast, Some(&spans)));
+        let fixture = path.with_extension("json");
+
+        if bless {
+            std::fs::write(&fixture, &actual).unwrap();
+            continue;
+        }
+
+        // 保存的语法树可以被加载，且与解析的结果相同
+        let expected = std::fs::read_to_string(&fixture)
+            .unwrap_or_else(|_| panic!("{} is missing", fixture.display()));
+        let loaded = from_json_str(&expected).unwrap();
+        assert_eq!(
+            format!("{:?}", loaded),
+            format!("{:?}", ast),
+            "{}",
+            path.display()
+        );
+        assert_eq!(
+            expected,
+            actual,
+            "{} does not match, run `HARE_BLESS=1 cargo test serialize` to update it",
+            fixture.display()
+        );
+    }
+}
+
+#[test]
+fn test_serialize_round_trip() {
+    use crate::parser::serialize::{from_json, to_json};
+
+    for dir in ["examples", "tests/snapshot"] {
+        let paths = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "ba"));
+        for path in paths {
+            let code = std::fs::read_to_string(&path).unwrap();
+            let Ok(ast) = crate::parser::parse(&code) else {
+                continue;
+            };
+
+            let loaded = from_json(&to_json(&ast, None)).unwrap();
+            assert_eq!(
+                format!("{:?}", loaded),
+                format!("{:?}", ast),
+                "{}",
+                path.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_serialize_spans() {
+    use crate::parser::serialize::to_json;
+
+    let code = "let a = (1 + 2) * b;\nif ok { rtb 1; }";
+    let (ast, spans) = parse_with_spans(code);
+    let json = to_json(&ast, Some(&spans));
+    let text = |pointer: &str| {
+        let span = &json.pointer(pointer).unwrap()["span"];
+        &code[span["start"].as_u64().unwrap() as usize..span["end"].as_u64().unwrap() as usize]
+    };
+
+    assert_eq!(text(""), code);
+    assert_eq!(text("/body/0"), "let a = (1 + 2) * b");
+    assert_eq!(text("/body/0/value"), "(1 + 2) * b");
+    assert_eq!(text("/body/0/value/left"), "1 + 2");
+    assert_eq!(text("/body/0/value/right"), "b");
+    // `format_ast` 加上的包装节点与被包装的节点共用区间
+    assert_eq!(text("/body/1/condition"), "ok");
+    assert_eq!(text("/body/1/condition/left"), "ok");
+    assert_eq!(text("/body/1/then/body/0/value"), "1");
+}
+
+#[test]
+fn test_serialize_literals() {
+    use crate::parser::serialize::{from_json, to_json, AstJsonError};
+    use crate::parser::AstNode;
+    use serde_json::json;
+
+    let constant = |literal: &str| {
+        let json = to_json(&AstNode::Constant(literal.to_string()), None);
+        (json["type"].clone(), json["value"].clone())
+    };
+    assert_eq!(constant("1"), (json!("int"), json!("1")));
+    assert_eq!(constant("2.0"), (json!("float"), json!(2.0)));
+    assert_eq!(constant("true"), (json!("bool"), json!(true)));
+    assert_eq!(constant("\"hi\""), (json!("string"), json!("hi")));
+
+    // 整数也可以写成 JSON 数字，浮点数保留小数点
+    let node = |value| from_json(&value).map(|node| node.as_code());
+    assert_eq!(
+        node(json!({ "kind": "Constant", "type": "int", "value": 7 })).unwrap(),
+        "7"
+    );
+    assert_eq!(
+        node(json!({ "kind": "Constant", "type": "float", "value": 3 })).unwrap(),
+        "3.0"
+    );
+    assert!(matches!(
+        node(json!({ "kind": "Constant", "type": "int", "value": "x" })),
+        Err(AstJsonError::InvalidConstant(_, _))
+    ));
+    assert!(matches!(
+        node(json!({ "kind": "Loop" })),
+        Err(AstJsonError::UnknownKind(_))
+    ));
+    assert!(matches!(
+        node(json!({ "kind": "SetValue", "target": { "kind": "Identifier", "name": "a" } })),
+        Err(AstJsonError::InvalidField(_, "value"))
+    ));
+}
+
+#[test]
+fn test_serialize_sexpr() {
+    use crate::parser::serialize::to_sexpr;
+
+    let (ast, spans) = parse_with_spans("1 + 2;");
+    assert_eq!(
+        to_sexpr(&ast, Some(&spans)),
+        "(program @0..6 (expr @0..5 + (constant @0..1 int 1) (constant @4..5 int 2)))\n"
+    );
+
+    // 超过一行的宽度时折行
+
+    let (ast, _) = parse_with_spans("if a > 1 { a = a * 10; } else { rtb \"done\"; }");
+    assert_eq!(
+        to_sexpr(&ast, None),
+        "(program\n  (if\n    (expr > (identifier \"a\") (constant int 1))\n    (block\n      (set_value (identifier \"a\") (expr * (identifier \"a\") (constant int 10))))\n    (else (block (return_block (constant string \"done\"))))))\n"
+    );
+}
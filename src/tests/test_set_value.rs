@@ -2,7 +2,7 @@
 fn test_set_value1() {
     use crate::parser::parse_pairs;
     use crate::parser::BlueArchParser;
-    use crate::Rule;
+    use crate::parser::Rule;
     use pest::Parser;
 
     let expr = "a = 1";
@@ -22,7 +22,7 @@ fn test_set_value1() {
 fn test_set_value2() {
     use crate::parser::parse_pairs;
     use crate::parser::BlueArchParser;
-    use crate::Rule;
+    use crate::parser::Rule;
     use pest::Parser;
 
     let expr = "a = 1 + 2";
@@ -39,7 +39,7 @@ fn test_set_value2() {
 fn test_set_value3() {
     use crate::parser::parse_pairs;
     use crate::parser::BlueArchParser;
-    use crate::Rule;
+    use crate::parser::Rule;
     use pest::Parser;
 
     let expr = "a = 1 + 2 * 3";
@@ -0,0 +1,188 @@
+//! 快照测试
+//!
+//! 发现 `SNAPSHOT_DIRS` 下的所有 `.ba` 文件，将其抽象语法树、诊断信息、字节码与运行结果渲染为文本，
+//! 与同名的 `.snap` 文件比较。设置环境变量 `HARE_BLESS=1` 运行测试会重写所有 `.snap` 文件：
+//!
+//! ```text
+//! HARE_BLESS=1 cargo test snapshot
+//! ```
+//!
+//! `.ba` 文件中形如 `# opt-level: 1` 的注释指定编译时的优化等级，默认为 0。
+
+/// 存放快照测试用例的目录
+#[allow(dead_code)]
+const SNAPSHOT_DIRS: &[&str] = &["examples", "tests/snapshot"];
+
+/// 找出目录下的所有 `.ba` 文件（包括子目录），按路径排序
+#[allow(dead_code)]
+fn discover(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut files: Vec<std::path::PathBuf> = vec![];
+
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            files.extend(discover(&path));
+        } else if path.extension().is_some_and(|extension| extension == "ba") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// 读取源代码中的 `# opt-level: N` 注释
+#[allow(dead_code)]
+fn opt_level(code: &str) -> u8 {
+    code.lines()
+        .filter_map(|line| line.trim().strip_prefix("# opt-level:"))
+        .find_map(|level| level.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// 编译并运行源代码，渲染为快照文本
+#[allow(dead_code)]
+fn render(code: &str) -> String {
+    use crate::compiler::format_bytecodes;
+    use crate::interpreter::Interpreter;
+    use crate::optimizer::{optimize, optimize_bytecodes};
+    use crate::parser::parse_with_recovery;
+
+    let level = opt_level(code);
+    let mut ast = String::new();
+    let mut diagnostics = String::new();
+    let mut bytecode = String::new();
+    let mut output = String::new();
+
+    // 每个阶段失败时记录诊断信息，并跳过之后的阶段
+    let _ = (|| -> Option<()> {
+        let (program, errors) = parse_with_recovery(code);
+        ast = format!("{:#?}\n", program);
+        for err in &errors {
+            diagnostics.push_str(&err.render(code));
+        }
+        if !errors.is_empty() {
+            return None;
+        }
+
+        let (program, warnings) = optimize(&program, level)
+            .map_err(|err| diagnostics.push_str(&format!("error: {}\n", err)))
+            .ok()?;
+        for warning in warnings {
+            diagnostics.push_str(&format!("warning: {}\n", warning));
+        }
+
+        let codes = program
+            .compile()
+            .map_err(|err| diagnostics.push_str(&format!("error: {}\n", err)))
+            .ok()?;
+        let (codes, _) = optimize_bytecodes(&codes, level);
+        bytecode = format_bytecodes(&codes);
+
+        let mut interpreter = Interpreter::new();
+        if let Err(err) = interpreter.run(&codes) {
+            diagnostics.push_str(&format!("runtime error: {}\n", err));
+        }
+        output = interpreter.output();
+        Some(())
+    })();
+
+    [
+        ("ast", ast),
+        ("diagnostics", diagnostics),
+        ("bytecode", bytecode),
+        ("output", output),
+    ]
+    .iter()
+    .map(|(name, content)| format!("=== {}\n{}", name, content))
+    .collect()
+}
+
+/// 逐行比较两段文本，以 `-`（期望）与 `+`（实际）标记不同的行
+#[allow(dead_code)]
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+
+    // 最长公共子序列，lcs[i][j] 为 expected[i..] 与 actual[j..] 的结果
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut output = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            output.push_str(&format!("  {}\n", expected[i]));
+            i += 1;
+            j += 1;
+        } else if i < expected.len() && (j == actual.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            output.push_str(&format!("- {}\n", expected[i]));
+            i += 1;
+        } else {
+            output.push_str(&format!("+ {}\n", actual[j]));
+            j += 1;
+        }
+    }
+
+    output
+}
+
+#[test]
+fn test_snapshot() {
+    use std::path::Path;
+
+    let bless = std::env::var("HARE_BLESS").is_ok_and(|value| value == "1");
+    let mut failures: Vec<String> = vec![];
+    let mut count = 0;
+
+    for dir in SNAPSHOT_DIRS {
+        for path in discover(Path::new(dir)) {
+            let code = std::fs::read_to_string(&path).unwrap();
+            let actual = render(&code);
+            let snapshot = path.with_extension("snap");
+            count += 1;
+
+            if bless {
+                std::fs::write(&snapshot, &actual).unwrap();
+                continue;
+            }
+
+            match std::fs::read_to_string(&snapshot) {
+                Ok(expected) if expected == actual => {}
+                Ok(expected) => failures.push(format!(
+                    "{} does not match:\n{}",
+                    snapshot.display(),
+                    diff(&expected, &actual)
+                )),
+                Err(_) => failures.push(format!("{} is missing", snapshot.display())),
+            }
+        }
+    }
+
+    assert!(count > 0, "no snapshot tests found");
+    assert!(
+        failures.is_empty(),
+        "{}\nrun `HARE_BLESS=1 cargo test snapshot` to update the snapshots",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_snapshot_diff() {
+    assert_eq!(diff("a\nb\nc\n", "a\nx\nc\n"), "  a\n- b\n+ x\n  c\n");
+    assert_eq!(diff("", "a\n"), "+ a\n");
+}
+
+#[test]
+fn test_snapshot_opt_level() {
+    assert_eq!(opt_level("# opt-level: 2\n1 + 1;"), 2);
+    assert_eq!(opt_level("1 + 1;"), 0);
+}
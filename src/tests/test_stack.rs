@@ -0,0 +1,127 @@
+#[test]
+fn test_stack_usage() {
+    use crate::compiler::verify::stack_usage;
+    use crate::interpreter::Interpreter;
+    use crate::parser::parse;
+
+    let codes = parse("let a = 1 + 2 * 3; if a > 1 { a = 0; } else { a = (a + 1) * (a - 1); }")
+        .unwrap()
+        .compile()
+        .unwrap();
+    let usage = stack_usage(&codes).unwrap();
+
+    // 调用 else Section 时栈中还留有条件的一份拷贝，其上是 `(a + 1) * (a - 1)`
+    assert_eq!(usage.program, Some(4));
+    assert_eq!(usage.sections["00000000"], Some(1));
+    assert_eq!(usage.sections["00000001"], Some(3));
+
+    // 实际执行时没有进入 else 分支，最大深度来自 `1 + 2 * 3`
+    let mut interpreter = Interpreter::new();
+    interpreter.run(&codes).unwrap();
+    assert_eq!(interpreter.max_stack(), 3);
+    assert_eq!(interpreter.section_max_stack("00000000"), Some(1));
+    assert_eq!(interpreter.section_max_stack("00000001"), None);
+
+    // 修改 `a` 后重新计算条件，进入 else 分支
+    interpreter
+        .run(&parse("a = 1;").unwrap().compile().unwrap())
+        .unwrap();
+    interpreter.run(&codes[20..]).unwrap();
+    assert_eq!(interpreter.max_stack(), 4);
+    assert_eq!(interpreter.section_max_stack("00000001"), Some(3));
+}
+
+#[test]
+fn test_stack_usage_observed() {
+    use crate::compiler::verify::stack_usage;
+    use crate::fuzz::Generator;
+    use crate::interpreter::Interpreter;
+    use crate::optimizer::{optimize, optimize_bytecodes};
+    use crate::parser::parse;
+
+    let mut checked = 0;
+    for seed in 0..300 {
+        let ast = parse(&Generator::new(seed).program()).unwrap();
+
+        for level in 0..=2 {
+            let Ok((ast, _)) = optimize(&ast, level) else {
+                continue;
+            };
+            let Ok(codes) = ast.compile() else {
+                continue;
+            };
+            let (codes, _) = optimize_bytecodes(&codes, level);
+            let usage = stack_usage(&codes).unwrap();
+
+            let mut interpreter = Interpreter::new();
+            if interpreter.run(&codes).is_err() {
+                continue;
+            }
+            checked += 1;
+
+            assert!(interpreter.max_stack() <= usage.program.unwrap());
+            for (name, max_stack) in &usage.sections {
+                if let Some(observed) = interpreter.section_max_stack(name) {
+                    assert!(observed <= max_stack.unwrap(), "seed {} @{}", seed, name);
+                }
+            }
+        }
+    }
+    assert!(checked > 100);
+}
+
+#[test]
+fn test_stack_usage_object() {
+    use crate::compiler::asm::assemble;
+    use crate::compiler::object::{Object, ObjectError};
+
+    let codes = assemble(
+        "
+        .section @loop
+            Push 1 2
+            LoadName go
+            JumpIf @loop
+        .end
+        .section @pair
+            Push 1 2
+            Add
+            Push 3
+            Return
+        .end
+        Push 0
+        Jump @pair
+        Jump @loop
+        ",
+    )
+    .unwrap();
+
+    let object = Object::new(codes);
+    assert_eq!(object.max_stack, None);
+    assert_eq!(object.sections[0].max_stack, None);
+    assert_eq!(object.sections[1].max_stack, Some(2));
+
+    let loaded = Object::load(&object.to_bytes()).unwrap();
+    assert_eq!(loaded, object);
+
+    // 记录的栈深度与字节码计算的结果不一致的目标文件被拒绝
+    let mut tampered = object.clone();
+    tampered.max_stack = Some(1);
+    assert_eq!(
+        Object::load(&tampered.to_bytes()),
+        Err(ObjectError::StackMismatch)
+    );
+
+    let mut tampered = object.clone();
+    tampered.sections[1].max_stack = Some(1);
+    assert_eq!(
+        Object::load(&tampered.to_bytes()),
+        Err(ObjectError::SectionStackMismatch("pair".to_string()))
+    );
+
+    let mut tampered = object;
+    tampered.sections[0].max_stack = Some(2);
+    assert_eq!(
+        Object::load(&tampered.to_bytes()),
+        Err(ObjectError::SectionStackMismatch("loop".to_string()))
+    );
+}
@@ -0,0 +1,204 @@
+#[test]
+fn test_verify_compiled() {
+    use crate::compiler::section::SectionNamer;
+    use crate::compiler::verify::verify;
+    use crate::fuzz::Generator;
+    use crate::optimizer::{optimize, optimize_bytecodes};
+    use crate::parser::parse;
+
+    let mut programs: Vec<String> = std::fs::read_dir("examples")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ba"))
+        .map(|path| std::fs::read_to_string(path).unwrap())
+        .collect();
+    programs.extend((0..200).map(|seed| Generator::new(seed).program()));
+
+    for program in programs {
+        let ast = parse(&program).unwrap();
+        for level in 0..=2 {
+            let Ok((ast, _)) = optimize(&ast, level) else {
+                continue;
+            };
+            let Ok(codes) = ast.compile_with(&mut SectionNamer::new()) else {
+                continue;
+            };
+            let (codes, _) = optimize_bytecodes(&codes, level);
+            assert_eq!(verify(&codes), Ok(()), "level {}:\n{}", level, program);
+        }
+    }
+}
+
+#[test]
+fn test_verify_errors() {
+    use crate::compiler::verify::{verify, VerifyError};
+    use crate::compiler::{ByteCode, OpCode};
+
+    let codes = vec![
+        ByteCode::from_args(OpCode::Jump, &["a"]).unwrap(),
+        ByteCode::from_args(OpCode::MakeSection, &["a"]).unwrap(),
+        ByteCode::from_args(OpCode::EndMakeSection, &[]).unwrap(),
+        ByteCode::from_args(OpCode::EndMakeSection, &[]).unwrap(),
+        ByteCode::from_args(OpCode::Return, &[]).unwrap(),
+        ByteCode::from_args(OpCode::MakeSection, &["a"]).unwrap(),
+        ByteCode::from_args(OpCode::MakeSection, &["b"]).unwrap(),
+        ByteCode::from_args(OpCode::EndMakeSection, &[]).unwrap(),
+    ];
+
+    assert_eq!(
+        verify(&codes),
+        Err(vec![
+            VerifyError::UndefinedSection(0, "a".to_string()),
+            VerifyError::UnmatchedEnd(3),
+            VerifyError::ReturnOutsideSection(4),
+            VerifyError::DuplicateSection(5, "a".to_string()),
+            VerifyError::UnclosedSection(5, "a".to_string()),
+        ])
+    );
+    assert_eq!(
+        VerifyError::UndefinedSection(0, "a".to_string()).to_string(),
+        "instruction 0000: section @a is not defined before the jump"
+    );
+}
+
+#[test]
+fn test_verify_stack() {
+    use crate::compiler::asm::assemble;
+    use crate::compiler::verify::{verify, VerifyError};
+    use crate::compiler::OpCode;
+
+    let verify_text = |text: &str| verify(&assemble(text).unwrap());
+
+    // Section 的返回值只在执行了 `Return` 且栈中有值时存在
+    let text = "
+        .section @one
+            Push 1
+            Return
+        .end
+        .section @none
+            Push 1
+        .end
+        Jump @one
+        Pop
+        Push true
+        JumpIf @one
+        Pop
+        Jump @none
+        Pop
+    ";
+    assert_eq!(
+        verify_text(text),
+        Err(vec![
+            VerifyError::StackUnderflow(11, OpCode::Pop),
+            VerifyError::StackUnderflow(13, OpCode::Pop),
+        ])
+    );
+
+    // Section 不能使用调用者的值，`Return` 之后的指令不会执行
+    let text = "
+        Push 1 2
+        .section @a
+            Add
+            Return
+            Pop
+        .end
+    ";
+    assert_eq!(
+        verify_text(text),
+        Err(vec![VerifyError::StackUnderflow(2, OpCode::Add)])
+    );
+
+    // 递归调用的返回值可能不存在；嵌套的 Section 只在外层 Section 中可见
+    let text = "
+        .section @loop
+            .section @inner
+            .end
+            Jump @loop
+            Jump @inner
+            Return
+        .end
+        Jump @loop
+        Jump @inner
+    ";
+    assert_eq!(
+        verify_text(text),
+        Err(vec![VerifyError::UndefinedSection(8, "inner".to_string())])
+    );
+    assert_eq!(
+        verify_text(".section @loop\nJump @loop\nPop\n.end"),
+        Err(vec![VerifyError::StackUnderflow(2, OpCode::Pop)])
+    );
+}
+
+#[test]
+fn test_verify_object() {
+    use crate::compiler::object::{Object, ObjectError};
+    use crate::compiler::verify::VerifyError;
+    use crate::compiler::{ByteCode, OpCode};
+
+    let valid = Object::new(vec![ByteCode::from_args(OpCode::Push, &["1"]).unwrap()]);
+    assert_eq!(Object::load(&valid.to_bytes()), Ok(valid));
+
+    let invalid = Object::new(vec![ByteCode::from_args(OpCode::Pop, &[]).unwrap()]);
+    assert_eq!(
+        Object::load(&invalid.to_bytes()),
+        Err(ObjectError::Invalid(VerifyError::StackUnderflow(
+            0,
+            OpCode::Pop
+        )))
+    );
+    assert_eq!(Object::from_bytes(&invalid.to_bytes()), Ok(invalid));
+}
+
+#[test]
+fn test_verify_object_sections() {
+    use crate::compiler::asm::assemble;
+    use crate::compiler::object::{Object, ObjectError};
+
+    let codes = assemble(
+        "
+        .section @outer
+            .section @inner
+                Push 1
+                Return
+            .end
+            Push 2
+            Return
+        .end
+        Jump @outer
+        ",
+    )
+    .unwrap();
+    let object = Object::new(codes);
+
+    // 篡改 Section 的位置、嵌套深度或数量的目标文件被拒绝
+    let mut tampered = object.clone();
+    tampered.sections[1].section.end -= 1;
+    assert_eq!(
+        Object::load(&tampered.to_bytes()),
+        Err(ObjectError::SectionMismatch(1))
+    );
+
+    let mut tampered = object.clone();
+    tampered.sections[0].section.depth = 1;
+    assert_eq!(
+        Object::load(&tampered.to_bytes()),
+        Err(ObjectError::SectionMismatch(0))
+    );
+
+    let mut tampered = object.clone();
+    tampered.sections.swap(0, 1);
+    assert_eq!(
+        Object::load(&tampered.to_bytes()),
+        Err(ObjectError::SectionMismatch(0))
+    );
+
+    let mut tampered = object.clone();
+    tampered.sections.pop();
+    assert_eq!(
+        Object::load(&tampered.to_bytes()),
+        Err(ObjectError::SectionMismatch(1))
+    );
+
+    assert_eq!(Object::load(&object.to_bytes()), Ok(object));
+}
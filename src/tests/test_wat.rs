@@ -0,0 +1,92 @@
+/// 编译程序并用 `wat` 与 `wasmparser` 解析、校验生成的模块
+#[allow(dead_code)]
+fn compile_and_validate(program: &str) -> Result<String, String> {
+    use crate::compiler::wat::compile_wat;
+    use crate::parser::parse;
+
+    let ast = parse(program).map_err(|err| err.to_string())?;
+    let wat = compile_wat(&ast).map_err(|err| err.to_string())?;
+    let bytes = wat::parse_str(&wat).unwrap_or_else(|err| panic!("{}\n{}", err, wat));
+    if let Err(err) = wasmparser::validate(&bytes) {
+        panic!("{}\n{}", err, wat);
+    }
+    Ok(wat)
+}
+
+#[test]
+fn test_wat_module() {
+    let wat =
+        compile_and_validate("let a = 1; let b = a * 2.5; if b > 2.0 { a = a + 1; } a;").unwrap();
+
+    assert!(wat.contains("(func $main (export \"main\")"));
+    assert!(wat.contains("(local $a i64)"));
+    assert!(wat.contains("(local $b f64)"));
+    assert!(wat.contains("f64.convert_i64_s"));
+    assert!(wat.contains("call $yield_int"));
+    assert!(!wat.contains("$__lhs") && !wat.contains("$__rhs"));
+
+    // 临时局部变量只在浮点数除法与取模用到时声明
+    let wat = compile_and_validate("let x = 1.5; x / 2;").unwrap();
+    assert!(wat.contains("(local $__rhs f64)"));
+    assert!(!wat.contains("$__lhs"));
+    let wat = compile_and_validate("let x = 1.5; x % 2;").unwrap();
+    assert!(wat.contains("(local $__lhs f64)\n    (local $__rhs f64)"));
+}
+
+#[test]
+fn test_wat_valid() {
+    use crate::fuzz::Generator;
+
+    let programs = [
+        "1 + 2 * 3 / 4; (1 + 2) * 3 % 5; 7 - 10 >= 1; (1 < 2) == true;",
+        "let x = 1.5; let y = 2; x % y; x / y; y / 2 < x; x == y; true != false;",
+        "let a = 2; if a == 1 { let b = 1; } elif a == 2 { let b = 2; 1 + 1; } else { let b = 3; } b;",
+        "let a = 1; if a > 0 { if a > 1 { a = 10; } else { a = 20; } } { let c = a * a; c; } a;",
+        "let flag = 1 < 2; if flag { flag = false; } flag;",
+        "let 变量 = 1; 变量 + 1;",
+        "let x = { if 1 == 1 { rtb 5; } rtb 6; }; let y = { { rtb 3; } rtb 4; }; x + y;",
+        "let a = 2; let b = if a > 1 { rtb a * 2.0; } elif a == 0 { rtb 0.5; } else { rtb 1.0; }; { if b > 3.0 { rtb b; } b = 0.0; }",
+    ];
+    for program in programs {
+        compile_and_validate(program).unwrap();
+    }
+
+    // 生成的程序中被后端接受的部分也必须是合法的模块
+    let compiled = (0..500)
+        .filter(|seed| compile_and_validate(&Generator::new(*seed).program()).is_ok())
+        .count();
+    assert!(compiled > 50, "only {} programs were compiled", compiled);
+}
+
+#[test]
+fn test_wat_unsupported() {
+    let cases = [
+        ("let a = \"x\";", "string constant `\"x\"`"),
+        (
+            "let x = { if true { rtb 1; } rtb 1.5; };",
+            "block values of types int and float",
+        ),
+        (
+            "let a = 1; a = 1.5;",
+            "changing the type of variable `a` from int to float",
+        ),
+        ("if 1 { 2; }", "int condition `1`"),
+        ("true + 1;", "operator `+` on bool and int"),
+        ("170141183460469231731687303715884105727;", "outside of i64"),
+    ];
+
+    for (program, construct) in cases {
+        let err = compile_and_validate(program).unwrap_err();
+        assert!(
+            err.starts_with("The wasm backend does not support"),
+            "{}",
+            err
+        );
+        assert!(err.contains(construct), "{}", err);
+    }
+
+    assert_eq!(
+        compile_and_validate("a + 1;").unwrap_err(),
+        "Compile error: Undefined variable: a"
+    );
+}